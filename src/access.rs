@@ -0,0 +1,254 @@
+// Licensed under the Apache-2.0 license
+
+use crate::ast::{AccessType, OnReadType, OnWriteType};
+use anyhow::bail;
+
+/// What software sees when it accesses a field, as one of the access
+/// policies used by IP-XACT and UVM register models.
+///
+/// See [`Field::sw_access_policy`](crate::Field::sw_access_policy).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SwAccessPolicy {
+    /// Read only.
+    Ro,
+    /// Read and write.
+    Rw,
+    /// Read, and clear on read.
+    Rc,
+    /// Read, and set on read.
+    Rs,
+    /// Read and write, and clear on read.
+    Wrc,
+    /// Read and write, and set on read.
+    Wrs,
+    /// Read; any write clears.
+    Wc,
+    /// Read; any write sets.
+    Ws,
+    /// Any write sets; reads clear.
+    Wsrc,
+    /// Any write clears; reads set.
+    Wcrs,
+    /// Read; writing 1 clears the bit.
+    W1c,
+    /// Read; writing 1 sets the bit.
+    W1s,
+    /// Read; writing 1 toggles the bit.
+    W1t,
+    /// Read; writing 0 clears the bit.
+    W0c,
+    /// Read; writing 0 sets the bit.
+    W0s,
+    /// Read; writing 0 toggles the bit.
+    W0t,
+    /// Writing 1 sets the bit; reads clear.
+    W1src,
+    /// Writing 1 clears the bit; reads set.
+    W1crs,
+    /// Writing 0 sets the bit; reads clear.
+    W0src,
+    /// Writing 0 clears the bit; reads set.
+    W0crs,
+    /// Write only.
+    Wo,
+    /// Write only; any write clears.
+    Woc,
+    /// Write only; any write sets.
+    Wos,
+    /// Read, and write once after reset.
+    W1,
+    /// Write once after reset, and never read.
+    Wo1,
+    /// Not accessible to software.
+    NoAccess,
+    /// The side effects of a read or write are defined by the user, with
+    /// `ruser` or `wuser`.
+    User,
+}
+
+impl SwAccessPolicy {
+    /// The conventional name of the policy, e.g. `W1C`.
+    pub fn name(self) -> &'static str {
+        match self {
+            SwAccessPolicy::Ro => "RO",
+            SwAccessPolicy::Rw => "RW",
+            SwAccessPolicy::Rc => "RC",
+            SwAccessPolicy::Rs => "RS",
+            SwAccessPolicy::Wrc => "WRC",
+            SwAccessPolicy::Wrs => "WRS",
+            SwAccessPolicy::Wc => "WC",
+            SwAccessPolicy::Ws => "WS",
+            SwAccessPolicy::Wsrc => "WSRC",
+            SwAccessPolicy::Wcrs => "WCRS",
+            SwAccessPolicy::W1c => "W1C",
+            SwAccessPolicy::W1s => "W1S",
+            SwAccessPolicy::W1t => "W1T",
+            SwAccessPolicy::W0c => "W0C",
+            SwAccessPolicy::W0s => "W0S",
+            SwAccessPolicy::W0t => "W0T",
+            SwAccessPolicy::W1src => "W1SRC",
+            SwAccessPolicy::W1crs => "W1CRS",
+            SwAccessPolicy::W0src => "W0SRC",
+            SwAccessPolicy::W0crs => "W0CRS",
+            SwAccessPolicy::Wo => "WO",
+            SwAccessPolicy::Woc => "WOC",
+            SwAccessPolicy::Wos => "WOS",
+            SwAccessPolicy::W1 => "W1",
+            SwAccessPolicy::Wo1 => "WO1",
+            SwAccessPolicy::NoAccess => "NOACCESS",
+            SwAccessPolicy::User => "USER",
+        }
+    }
+
+    /// The IP-XACT `access` of the field, or `None` if software cannot
+    /// access it.
+    pub fn access(self) -> Option<&'static str> {
+        use SwAccessPolicy::*;
+        Some(match self {
+            Ro | Rc | Rs => "read-only",
+            Wo | Woc | Wos => "write-only",
+            W1 => "read-writeOnce",
+            Wo1 => "writeOnce",
+            NoAccess => return None,
+            _ => "read-write",
+        })
+    }
+
+    /// The IP-XACT `modifiedWriteValue` of the field, or `None` if writes
+    /// store the value written.
+    pub fn modified_write_value(self) -> Option<&'static str> {
+        use SwAccessPolicy::*;
+        Some(match self {
+            W1c | W1crs => "oneToClear",
+            W1s | W1src => "oneToSet",
+            W1t => "oneToToggle",
+            W0c | W0crs => "zeroToClear",
+            W0s | W0src => "zeroToSet",
+            W0t => "zeroToToggle",
+            Wc | Wcrs | Woc => "clear",
+            Ws | Wsrc | Wos => "set",
+            User => "modify",
+            _ => return None,
+        })
+    }
+
+    /// The IP-XACT `readAction` of the field, or `None` if reads have no side
+    /// effect.
+    pub fn read_action(self) -> Option<&'static str> {
+        use SwAccessPolicy::*;
+        Some(match self {
+            Rc | Wrc | Wsrc | W1src | W0src => "clear",
+            Rs | Wrs | Wcrs | W1crs | W0crs => "set",
+            User => "modify",
+            _ => return None,
+        })
+    }
+}
+
+impl std::fmt::Display for SwAccessPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The policy for a field with the given `sw` access and side effects.
+pub(crate) fn sw_access_policy(
+    sw: AccessType,
+    onread: Option<OnReadType>,
+    onwrite: Option<OnWriteType>,
+) -> Result<SwAccessPolicy, anyhow::Error> {
+    use OnReadType::*;
+    use OnWriteType::*;
+    use SwAccessPolicy::*;
+    if onread == Some(RUser) || onwrite == Some(WUser) {
+        return Ok(User);
+    }
+    Ok(match (sw, onread, onwrite) {
+        (AccessType::Na, None, None) => NoAccess,
+        (AccessType::R, None, None) => Ro,
+        (AccessType::R, Some(RClr), None) => Rc,
+        (AccessType::R, Some(RSet), None) => Rs,
+        (AccessType::W, None, None) => Wo,
+        (AccessType::W, None, Some(WClr)) => Woc,
+        (AccessType::W, None, Some(WSet)) => Wos,
+        (AccessType::W1, None, None) => Wo1,
+        (AccessType::Rw1, None, None) => W1,
+        (AccessType::Rw, None, None) => Rw,
+        (AccessType::Rw, Some(RClr), None) => Wrc,
+        (AccessType::Rw, Some(RSet), None) => Wrs,
+        (AccessType::Rw, None, Some(WClr)) => Wc,
+        (AccessType::Rw, None, Some(WSet)) => Ws,
+        (AccessType::Rw, Some(RClr), Some(WSet)) => Wsrc,
+        (AccessType::Rw, Some(RSet), Some(WClr)) => Wcrs,
+        (AccessType::Rw, None, Some(WoClr)) => W1c,
+        (AccessType::Rw, None, Some(WoSet)) => W1s,
+        (AccessType::Rw, None, Some(Wot)) => W1t,
+        (AccessType::Rw, None, Some(Wzc)) => W0c,
+        (AccessType::Rw, None, Some(Wzs)) => W0s,
+        (AccessType::Rw, None, Some(Wzt)) => W0t,
+        (AccessType::Rw, Some(RClr), Some(WoSet)) => W1src,
+        (AccessType::Rw, Some(RSet), Some(WoClr)) => W1crs,
+        (AccessType::Rw, Some(RClr), Some(Wzs)) => W0src,
+        (AccessType::Rw, Some(RSet), Some(Wzc)) => W0crs,
+        (_, Some(onread), _) if matches!(sw, AccessType::W | AccessType::W1 | AccessType::Na) => {
+            bail!("onread = {onread} needs software read access, but sw = {sw}")
+        }
+        (_, _, Some(onwrite)) if matches!(sw, AccessType::R | AccessType::Na) => {
+            bail!("onwrite = {onwrite} needs software write access, but sw = {sw}")
+        }
+        (_, onread, onwrite) => {
+            let mut effects = Vec::new();
+            effects.extend(onread.map(|onread| format!("onread = {onread}")));
+            effects.extend(onwrite.map(|onwrite| format!("onwrite = {onwrite}")));
+            bail!(
+                "no access policy has {} with sw = {sw}",
+                effects.join(" and ")
+            )
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_policies() {
+        let policy =
+            |sw, onread, onwrite| sw_access_policy(sw, onread, onwrite).map_err(|e| e.to_string());
+        assert_eq!(policy(AccessType::R, None, None), Ok(SwAccessPolicy::Ro));
+        assert_eq!(
+            policy(
+                AccessType::Rw,
+                Some(OnReadType::RClr),
+                Some(OnWriteType::WoSet)
+            ),
+            Ok(SwAccessPolicy::W1src)
+        );
+        assert_eq!(
+            policy(AccessType::W, Some(OnReadType::RClr), None),
+            Err("onread = rclr needs software read access, but sw = w".into())
+        );
+        assert_eq!(
+            policy(AccessType::R, None, Some(OnWriteType::WoClr)),
+            Err("onwrite = woclr needs software write access, but sw = r".into())
+        );
+        assert_eq!(
+            policy(
+                AccessType::Rw,
+                Some(OnReadType::RClr),
+                Some(OnWriteType::WoClr)
+            ),
+            Err("no access policy has onread = rclr and onwrite = woclr with sw = rw".into())
+        );
+
+        let w1c = SwAccessPolicy::W1c;
+        assert_eq!(w1c.to_string(), "W1C");
+        assert_eq!(w1c.access(), Some("read-write"));
+        assert_eq!(w1c.modified_write_value(), Some("oneToClear"));
+        assert_eq!(w1c.read_action(), None);
+        assert_eq!(SwAccessPolicy::Rc.access(), Some("read-only"));
+        assert_eq!(SwAccessPolicy::Rc.read_action(), Some("clear"));
+        assert_eq!(SwAccessPolicy::NoAccess.access(), None);
+    }
+}
@@ -2,9 +2,12 @@
 
 //! Abstract Syntax Tree (AST) for SystemRDL parser.
 
-use crate::parser::{root, tokens};
-use crate::{Bits, FileSource, Token, TokenKind, Tokens, token_iter::TokenIter};
+use crate::lexer::LineTracker;
+use crate::parser::{constant_expr, root, tokens};
+use crate::token_iter::{ParseOptions, TokenIter};
+use crate::{Bits, FileSource, SourceLocation, Token, TokenKind, Tokens};
 use std::path::Path;
+use std::sync::Arc;
 use winnow::Parser;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -116,7 +119,7 @@ impl From<&str> for AddressingType {
     }
 }
 
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub enum InterruptType {
     #[default]
     Level,
@@ -173,18 +176,50 @@ pub enum Description {
 
 impl Root {
     pub fn from_file(file_source: &dyn FileSource, name: &Path) -> Result<Self, anyhow::Error> {
+        Self::from_file_with_options(file_source, name, &ParseOptions::default())
+    }
+
+    pub fn from_file_with_options(
+        file_source: &dyn FileSource,
+        name: &Path,
+        options: &ParseOptions,
+    ) -> Result<Self, anyhow::Error> {
         let mut tokens = vec![];
-        let mut iter = TokenIter::from_path(file_source, name)?;
+        let mut iter = TokenIter::from_path(file_source, name)?.with_options(options);
+        // one for each file read, as includes switch between them
+        let mut files: Vec<(LineTracker, Arc<Path>)> = Vec::new();
         loop {
             let t = iter.next();
+            if let Some(e) = iter.take_error() {
+                return Err(e);
+            }
             if t == TokenKind::EndOfFile {
                 break;
             }
             let span = iter.last_span();
+            let contents = iter.current_file_contents();
+            let index = match files
+                .iter()
+                .position(|(lines, _)| std::ptr::eq(lines.input(), contents))
+            {
+                Some(index) => index,
+                None => {
+                    let path = Arc::from(iter.current_file_path());
+                    files.push((LineTracker::new(contents), path));
+                    files.len() - 1
+                }
+            };
+            let (lines, file) = &mut files[index];
+            let (line, column) = lines.line_column(span.start);
             // TODO: this span could refer to the previous file if the fifo was not empty; we should return the correct string in that case
             tokens.push(Token {
                 kind: t,
-                raw: &(iter.current_file_contents()[span.start..span.end]),
+                raw: &contents[span.start..span.end],
+                location: SourceLocation {
+                    file: Some(file.clone()),
+                    line,
+                    column,
+                },
             });
         }
         let tokens = Tokens::new(&tokens);
@@ -395,7 +430,7 @@ pub enum ComponentDef {
     Anon(ComponentType, ComponentBody),
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ComponentInstType {
     Internal,
     External,
@@ -547,6 +582,25 @@ pub enum ConstantExpr {
     ),
 }
 
+impl std::str::FromStr for ConstantExpr {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let tokens = tokens
+            .parse(input.trim_end())
+            .map_err(|e| anyhow::format_err!("{e}"))?;
+        let tokens = Tokens::new(&tokens);
+        constant_expr
+            .parse(tokens)
+            .map_err(|e| match e.input().first() {
+                Some(t) => {
+                    anyhow::format_err!("Error parsing input at: `{}` token: {:?}", t.raw, t.kind)
+                }
+                None => anyhow::format_err!("Error parsing input: unexpected end of input"),
+            })
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum ConstantExprContinue {
     BinaryOp(
@@ -567,7 +621,7 @@ pub enum ArrayOrRange {
     Range(Range),
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct ComponentInst {
     pub id: String,
     pub array_or_range: Option<ArrayOrRange>,
@@ -575,6 +629,20 @@ pub struct ComponentInst {
     pub at: Option<ConstantExpr>,
     pub plus_equals: Option<ConstantExpr>,
     pub percent_equals: Option<ConstantExpr>,
+    /// Where the instance is named in the source, if it was parsed.
+    pub location: Option<SourceLocation>,
+}
+
+/// Instances are equal if they are written the same, wherever they are.
+impl PartialEq for ComponentInst {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.array_or_range == other.array_or_range
+            && self.equals == other.equals
+            && self.at == other.at
+            && self.plus_equals == other.plus_equals
+            && self.percent_equals == other.percent_equals
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -0,0 +1,117 @@
+// Licensed under the Apache-2.0 license
+
+//! Formats SystemRDL source files.
+
+use anyhow::{Context, bail};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use systemrdl::PrintOptions;
+
+const USAGE: &str = "\
+Usage: rdlfmt [OPTIONS] [FILE]...
+
+Formats SystemRDL files. With no files, formats standard input to standard
+output.
+
+Options:
+  -i, --in-place     Rewrite each file with its formatted contents
+      --check        Do not write anything; list the files that are not
+                     formatted and exit with status 1 if there are any
+      --indent <N>   Number of spaces per indentation level (default 4)
+  -h, --help         Print this help
+";
+
+#[derive(Default)]
+struct Args {
+    in_place: bool,
+    check: bool,
+    options: PrintOptions,
+    files: Vec<PathBuf>,
+}
+
+fn parse_args() -> Result<Option<Args>, anyhow::Error> {
+    let mut args = Args::default();
+    let mut iter = std::env::args_os().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.to_str() {
+            Some("-h" | "--help") => return Ok(None),
+            Some("-i" | "--in-place") => args.in_place = true,
+            Some("--check") => args.check = true,
+            Some("--indent") => {
+                let n = iter.next().context("--indent requires a value")?;
+                let n: usize = n
+                    .to_str()
+                    .and_then(|n| n.parse().ok())
+                    .context("--indent requires a number")?;
+                args.options.indent = " ".repeat(n);
+            }
+            Some(option) if option.starts_with('-') => {
+                bail!("unknown option {option}")
+            }
+            _ => args.files.push(arg.into()),
+        }
+    }
+    if args.in_place && args.files.is_empty() {
+        bail!("--in-place requires at least one file");
+    }
+    Ok(Some(args))
+}
+
+/// Returns whether every input was already formatted.
+fn run(args: &Args) -> Result<bool, anyhow::Error> {
+    if args.files.is_empty() {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        let output = systemrdl::format(&input, &args.options).context("<stdin>")?;
+        if args.check {
+            return Ok(output == input);
+        }
+        std::io::stdout().write_all(output.as_bytes())?;
+        return Ok(true);
+    }
+
+    let mut formatted = true;
+    for path in &args.files {
+        let input = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        let output =
+            systemrdl::format(&input, &args.options).with_context(|| path.display().to_string())?;
+        if args.check {
+            if output != input {
+                println!("{}", path.display());
+                formatted = false;
+            }
+        } else if args.in_place {
+            if output != input {
+                std::fs::write(path, output)
+                    .with_context(|| format!("could not write {}", path.display()))?;
+            }
+        } else {
+            std::io::stdout().write_all(output.as_bytes())?;
+        }
+    }
+    Ok(formatted)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,237 @@
+// Licensed under the Apache-2.0 license
+
+//! Output formats for elaborated designs.
+
+use anyhow::bail;
+use std::fmt::Write;
+use systemrdl::{Instance, Listener, Node, NodeContent, PropertyValue, RootNamespace, Walker};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+impl std::str::FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => bail!("unknown format {s}, expected json or csv"),
+        }
+    }
+}
+
+impl Format {
+    pub fn export(self, namespace: &RootNamespace) -> String {
+        match self {
+            Format::Json => json(namespace),
+            Format::Csv => csv(namespace),
+        }
+    }
+}
+
+fn kind(node: &Node) -> &'static str {
+    match node.content() {
+        NodeContent::Addrmap(_) => "addrmap",
+        NodeContent::RegFile(_) => "regfile",
+        NodeContent::Register(_) => "reg",
+        NodeContent::Field(_) => "field",
+        NodeContent::Signal(_) => "signal",
+        NodeContent::Mem(_) => "mem",
+    }
+}
+
+/// The name of a node followed by its array dimensions, e.g. `regs[2][4]`.
+fn name_with_dims(node: &Node) -> String {
+    let mut name = node.name().to_string();
+    for dim in node.array().map(|array| array.dims()).unwrap_or_default() {
+        write!(name, "[{dim}]").unwrap();
+    }
+    name
+}
+
+/// An indented outline of the hierarchy, for people to read.
+pub fn tree(namespace: &RootNamespace) -> String {
+    let mut out = String::new();
+    for root in namespace.roots() {
+        for node in namespace.subtree(root.id()) {
+            let depth = namespace.ancestors(node.id()).count();
+            let indent = "    ".repeat(depth);
+            write!(out, "{indent}{} {}", kind(node), name_with_dims(node)).unwrap();
+            match node.content() {
+                NodeContent::Field(field) => write!(out, " [{}:{}]", field.msb(), field.lsb()),
+                NodeContent::Signal(signal) => write!(out, " [{}]", signal.width()),
+                _ => write!(out, " @ {:#x}", node.offset().unwrap_or_default()),
+            }
+            .unwrap();
+            if let Some(array) = node.array() {
+                write!(out, " += {:#x}", array.stride()).unwrap();
+            }
+            if let Some(size) = node.size() {
+                write!(out, " ({size:#x} bytes)").unwrap();
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// One row per register and field, with arrays unrolled.
+fn csv(namespace: &RootNamespace) -> String {
+    struct Rows(String);
+
+    impl Listener for Rows {
+        fn enter_reg(&mut self, instance: &Instance) -> systemrdl::WalkerAction {
+            let node = instance.node();
+            writeln!(
+                self.0,
+                "reg,{},{:#x},{},,",
+                instance.path(),
+                instance.address().unwrap_or_default(),
+                node.size().unwrap_or_default(),
+            )
+            .unwrap();
+            Default::default()
+        }
+
+        fn enter_field(&mut self, instance: &Instance) -> systemrdl::WalkerAction {
+            if let NodeContent::Field(field) = instance.node().content() {
+                writeln!(
+                    self.0,
+                    "field,{},,,{},{}",
+                    instance.path(),
+                    field.lsb(),
+                    field.msb()
+                )
+                .unwrap();
+            }
+            Default::default()
+        }
+    }
+
+    let mut rows = Rows("kind,path,address,size,lsb,msb\n".into());
+    Walker::new().unroll(true).walk(namespace, &mut rows);
+    rows.0
+}
+
+/// The hierarchy as a JSON document, with arrays described rather than
+/// unrolled.
+fn json(namespace: &RootNamespace) -> String {
+    let mut out = String::new();
+    let roots: Vec<_> = namespace.roots().collect();
+    out.push('[');
+    for (i, root) in roots.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        json_node(namespace, root, 1, &mut out);
+    }
+    out.push_str("\n]\n");
+    out
+}
+
+fn json_node(namespace: &RootNamespace, node: &Node, level: usize, out: &mut String) {
+    let indent = "  ".repeat(level);
+    write!(out, "\n{indent}{{").unwrap();
+    let mut members = vec![
+        ("kind", json_string(kind(node))),
+        ("name", json_string(node.name())),
+        ("path", json_string(&namespace.path(node.id()))),
+    ];
+    if let Some(offset) = node.offset() {
+        members.push(("offset", offset.to_string()));
+    }
+    if let Some(size) = node.size() {
+        members.push(("size", size.to_string()));
+    }
+    if let Some(array) = node.array() {
+        let dims: Vec<_> = array.dims().iter().map(u64::to_string).collect();
+        members.push(("dims", format!("[{}]", dims.join(", "))));
+        members.push(("stride", array.stride().to_string()));
+    }
+    if let NodeContent::Field(field) = node.content() {
+        members.push(("lsb", field.lsb().to_string()));
+        members.push(("msb", field.msb().to_string()));
+        if let Ok(policy) = field.sw_access_policy() {
+            members.push(("access", json_string(policy.name())));
+        }
+    }
+    if let NodeContent::Signal(signal) = node.content() {
+        members.push(("width", signal.width().to_string()));
+    }
+    if node.is_external() {
+        members.push(("external", "true".into()));
+    }
+    if let NodeContent::Register(register) = node.content()
+        && let Some(primary) = register.alias_of()
+    {
+        members.push(("alias_of", json_string(&namespace.path(primary))));
+    }
+    let mut properties: Vec<_> = node.properties().iter().collect();
+    properties.sort_by_key(|(name, _)| *name);
+    let properties: Vec<_> = properties
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                PropertyValue::Literal(literal) => match literal {
+                    systemrdl::ast::PrimaryLiteral::Number(n) => n.to_string(),
+                    systemrdl::ast::PrimaryLiteral::Bits(bits) => bits.val().to_string(),
+                    systemrdl::ast::PrimaryLiteral::BooleanLiteral(b) => b.to_string(),
+                    systemrdl::ast::PrimaryLiteral::StringLiteral(s) => json_string(&unquote(s)),
+                    literal => json_string(&literal.to_string()),
+                },
+                PropertyValue::Reference(id) => json_string(&namespace.path(*id)),
+                PropertyValue::PropertyReference(id, prop) => {
+                    json_string(&format!("{}->{prop}", namespace.path(*id)))
+                }
+            };
+            format!("{}: {value}", json_string(name))
+        })
+        .collect();
+    members.push(("properties", format!("{{{}}}", properties.join(", "))));
+
+    for (name, value) in members {
+        write!(out, "\n{indent}  \"{name}\": {value},").unwrap();
+    }
+    write!(out, "\n{indent}  \"children\": [").unwrap();
+    let children: Vec<_> = namespace.children(node.id()).collect();
+    for (i, child) in children.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        json_node(namespace, child, level + 2, out);
+    }
+    if !children.is_empty() {
+        write!(out, "\n{indent}  ").unwrap();
+    }
+    write!(out, "]\n{indent}}}").unwrap();
+}
+
+/// The contents of an RDL string literal.
+fn unquote(s: &str) -> String {
+    let s = s
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s);
+    s.replace("\\\"", "\"").replace("\\\\", "\\")
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => write!(out, "\\u{:04x}", ch as u32).unwrap(),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
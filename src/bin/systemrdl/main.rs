@@ -0,0 +1,249 @@
+// Licensed under the Apache-2.0 license
+
+//! Command-line front end for the SystemRDL parser and elaborator.
+
+mod export;
+
+use anyhow::{Context, bail};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use systemrdl::{Compiler, ElaborateOptions, ParseOptions};
+
+const USAGE: &str = "\
+Usage: systemrdl <COMMAND> [OPTIONS] <FILE>...
+
+Compiles the files in order, so later files can use the definitions made in
+earlier ones.
+
+Commands:
+  check       Parse, elaborate and validate the files, reporting any errors
+  dump-ast    Print the syntax tree of the files
+  elaborate   Print the elaborated hierarchy
+  export      Write the elaborated design in the format given by --format
+
+Options:
+  -I <DIR>              Search DIR for included files
+  -D <NAME>             Define NAME before preprocessing
+  -t, --top <NAME>      Elaborate the root addrmap NAME as the top level,
+                        rather than the last one defined
+  -P <NAME>=<VALUE>     Override a parameter of the top-level addrmap
+  -f, --format <FORMAT> Export format: json or csv
+  -o, --output <FILE>   Write to FILE instead of standard output
+      --depfile <FILE>  Also write a Makefile rule to FILE listing the files
+                        that the output depends on; requires --output
+  -h, --help            Print this help
+
+Exit status is 0 on success, 1 if the input is not valid SystemRDL, 2 for
+usage errors or files that cannot be read or written, and 3 for an internal
+error, which is a bug in systemrdl rather than in the input.
+";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Command {
+    Check,
+    DumpAst,
+    Elaborate,
+    Export,
+}
+
+#[derive(Default)]
+struct Args {
+    command: Option<Command>,
+    files: Vec<PathBuf>,
+    parse_options: ParseOptions,
+    elaborate_options: ElaborateOptions,
+    format: Option<export::Format>,
+    output: Option<PathBuf>,
+    depfile: Option<PathBuf>,
+}
+
+/// Why a command failed, which determines the exit status.
+enum Failure {
+    /// The input is not valid SystemRDL.
+    Invalid(anyhow::Error),
+    /// The command line was wrong, or a file could not be read or written.
+    Fatal(anyhow::Error),
+}
+
+impl From<anyhow::Error> for Failure {
+    fn from(e: anyhow::Error) -> Self {
+        Failure::Fatal(e)
+    }
+}
+
+impl From<std::io::Error> for Failure {
+    fn from(e: std::io::Error) -> Self {
+        Failure::Fatal(e.into())
+    }
+}
+
+/// The value of an option, either joined to it as in `-Idir` or as the next
+/// argument.
+fn option_value(
+    arg: &str,
+    short: &str,
+    iter: &mut impl Iterator<Item = String>,
+) -> Result<String, anyhow::Error> {
+    match arg.strip_prefix(short) {
+        Some(value) if !value.is_empty() && short.len() == 2 => Ok(value.into()),
+        _ => iter
+            .next()
+            .with_context(|| format!("{arg} requires a value")),
+    }
+}
+
+fn parse_args() -> Result<Option<Args>, anyhow::Error> {
+    let mut args = Args::default();
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "check" if args.command.is_none() => args.command = Some(Command::Check),
+            "dump-ast" if args.command.is_none() => args.command = Some(Command::DumpAst),
+            "elaborate" if args.command.is_none() => args.command = Some(Command::Elaborate),
+            "export" if args.command.is_none() => args.command = Some(Command::Export),
+            _ if arg.starts_with("-I") => {
+                let dir = option_value(&arg, "-I", &mut iter)?;
+                args.parse_options.include_paths.push(dir.into());
+            }
+            _ if arg.starts_with("-D") => {
+                let name = option_value(&arg, "-D", &mut iter)?;
+                if name.contains('=') {
+                    bail!("-D {name}: defines cannot have a value");
+                }
+                args.parse_options.defines.push(name);
+            }
+            _ if arg.starts_with("-P") => {
+                let param = option_value(&arg, "-P", &mut iter)?;
+                let (name, value) = param
+                    .split_once('=')
+                    .with_context(|| format!("-P {param}: expected NAME=VALUE"))?;
+                let value = value
+                    .parse()
+                    .with_context(|| format!("-P {param}: invalid value"))?;
+                args.elaborate_options.params.insert(name.into(), value);
+            }
+            "-t" | "--top" => {
+                args.elaborate_options.top = Some(option_value(&arg, "--top", &mut iter)?)
+            }
+            "-f" | "--format" => {
+                let format = option_value(&arg, "--format", &mut iter)?;
+                args.format = Some(format.parse()?);
+            }
+            "-o" | "--output" => {
+                args.output = Some(option_value(&arg, "--output", &mut iter)?.into())
+            }
+            "--depfile" => args.depfile = Some(option_value(&arg, "--depfile", &mut iter)?.into()),
+            _ if arg.starts_with('-') => bail!("unknown option {arg}"),
+            _ if args.command.is_none() => bail!("unknown command {arg}"),
+            _ => args.files.push(arg.into()),
+        }
+    }
+    let Some(command) = args.command else {
+        bail!("no command given");
+    };
+    if args.files.is_empty() {
+        bail!("no files given");
+    }
+    if args.depfile.is_some() && args.output.is_none() {
+        bail!("--depfile requires --output");
+    }
+    match (command, args.format) {
+        (Command::Export, None) => bail!("export requires --format"),
+        (Command::Export, _) | (_, None) => {}
+        (_, Some(_)) => bail!("--format can only be used with export"),
+    }
+    Ok(Some(args))
+}
+
+/// Parse each file in turn into one compilation session.
+fn parse(args: &Args) -> Result<Compiler, Failure> {
+    let mut compiler = Compiler::new().parse_options(args.parse_options.clone());
+    for file in &args.files {
+        if !file.is_file() {
+            return Err(Failure::Fatal(anyhow::format_err!(
+                "could not read {}",
+                file.display()
+            )));
+        }
+        compiler.add_file(file).map_err(Failure::Invalid)?;
+    }
+    Ok(compiler)
+}
+
+fn run(args: &Args) -> Result<(), Failure> {
+    let compiler = parse(args)?;
+    if let (Some(depfile), Some(output)) = (&args.depfile, &args.output) {
+        std::fs::write(depfile, compiler.dependencies().depfile(output))
+            .with_context(|| format!("could not write {}", depfile.display()))?;
+    }
+    let mut out = String::new();
+    let elaborate = |compiler: Compiler| {
+        let namespace = compiler
+            .elaborate(&args.elaborate_options)
+            .map_err(Failure::Invalid)?;
+        let violations = systemrdl::validate(&namespace);
+        for violation in &violations {
+            eprintln!("error: {violation}");
+        }
+        match violations.len() {
+            0 => Ok(namespace),
+            1 => Err(Failure::Invalid(anyhow::format_err!("found 1 error"))),
+            n => Err(Failure::Invalid(anyhow::format_err!("found {n} errors"))),
+        }
+    };
+    match args.command.unwrap() {
+        Command::Check => {
+            elaborate(compiler)?;
+        }
+        Command::DumpAst => {
+            out = format!("{:#?}\n", compiler.root());
+        }
+        Command::Elaborate => {
+            let namespace = elaborate(compiler)?;
+            out = export::tree(&namespace);
+        }
+        Command::Export => {
+            let namespace = elaborate(compiler)?;
+            out = args.format.unwrap().export(&namespace);
+        }
+    }
+    match &args.output {
+        Some(path) => std::fs::write(path, out)
+            .with_context(|| format!("could not write {}", path.display()))?,
+        None => std::io::stdout().write_all(out.as_bytes())?,
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e:#}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    // a panic is a bug in the compiler, not in the input; the panic hook has
+    // already printed where it happened, so only say what it means
+    let Ok(result) = std::panic::catch_unwind(|| run(&args)) else {
+        eprintln!("error: internal error; this is a bug in systemrdl, not in the input");
+        return ExitCode::from(3);
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure::Invalid(e)) => {
+            eprintln!("error: {e:#}");
+            ExitCode::FAILURE
+        }
+        Err(Failure::Fatal(e)) => {
+            eprintln!("error: {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,284 @@
+// Licensed under the Apache-2.0 license
+
+use crate::ast::{self, Description};
+use crate::visit_mut::{VisitMut, walk_component_inst_mut};
+use crate::{
+    Dependencies, DependencyTracker, ElaborateOptions, FileSource, FsFileSource, ParseOptions,
+    RootNamespace, elaborate_with_options,
+};
+use anyhow::{Context, bail};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A compilation session in which several files are parsed in order and then
+/// elaborated together.
+///
+/// Definitions made in the root scope of one file can be used by the files
+/// added after it, as if the files had been concatenated.
+///
+/// ```no_run
+/// # fn main() -> Result<(), anyhow::Error> {
+/// let mut compiler = systemrdl::Compiler::new();
+/// compiler.add_file("types.rdl".as_ref())?;
+/// compiler.add_file("top.rdl".as_ref())?;
+/// let namespace = compiler.elaborate(&Default::default())?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Compiler {
+    file_source: DependencyTracker<Box<dyn FileSource + Send + Sync>>,
+    parse_options: ParseOptions,
+    root: ast::Root,
+    /// The source in which each type was defined.
+    types: HashMap<String, String>,
+    /// The source in which each user-defined property was defined.
+    properties: HashMap<String, String>,
+    /// The source in which each root-scope instance was made.
+    instances: HashMap<String, String>,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Compiler::new()
+    }
+}
+
+impl Compiler {
+    /// A session that reads files from the filesystem.
+    pub fn new() -> Self {
+        Compiler::with_file_source(FsFileSource::new())
+    }
+
+    pub fn with_file_source(file_source: impl FileSource + Send + Sync + 'static) -> Self {
+        Compiler {
+            file_source: DependencyTracker::new(Box::new(file_source)),
+            parse_options: ParseOptions::default(),
+            root: ast::Root {
+                descriptions: Vec::new(),
+            },
+            types: HashMap::new(),
+            properties: HashMap::new(),
+            instances: HashMap::new(),
+        }
+    }
+
+    /// Set the preprocessor options used for files added after this.
+    pub fn parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse_options = options;
+        self
+    }
+
+    /// Parse a file and add its descriptions to the session.
+    pub fn add_file(&mut self, path: &Path) -> Result<(), anyhow::Error> {
+        let root = ast::Root::from_file_with_options(&self.file_source, path, &self.parse_options)
+            .with_context(|| path.display().to_string())?;
+        self.add_root(&path.display().to_string(), root)
+    }
+
+    /// Parse SystemRDL source text and add its descriptions to the session.
+    /// `name` identifies the source in error messages.
+    pub fn add_source(&mut self, name: &str, source: &str) -> Result<(), anyhow::Error> {
+        let mut root: ast::Root = source.parse().with_context(|| name.to_string())?;
+        SetFile(Arc::from(Path::new(name))).visit_root_mut(&mut root);
+        self.add_root(name, root)
+    }
+
+    /// Add the descriptions of an already parsed file to the session.
+    ///
+    /// Fails without adding anything if the file defines a name that is
+    /// already defined in the root scope.
+    pub fn add_root(&mut self, name: &str, root: ast::Root) -> Result<(), anyhow::Error> {
+        let mut types = Vec::new();
+        let mut properties = Vec::new();
+        let mut instances = Vec::new();
+        for desc in &root.descriptions {
+            match desc {
+                Description::ComponentDef(component) => {
+                    if let ast::ComponentDef::Named(_, id, _, _) = &component.def {
+                        types.push(("component", id));
+                    }
+                    if let Some(insts) = &component.insts {
+                        instances.extend(insts.component_insts.iter().map(|inst| &inst.id));
+                    }
+                }
+                Description::EnumDef(enum_def) => types.push(("enum", &enum_def.id)),
+                Description::StructDef(struct_def) => types.push(("struct", &struct_def.id)),
+                Description::ConstraintDef(ast::ConstraintDef::Exp(id, _, insts)) => {
+                    types.push(("constraint", id));
+                    instances.extend(insts);
+                }
+                Description::ConstraintDef(ast::ConstraintDef::Anon(_, insts)) => {
+                    instances.extend(insts)
+                }
+                Description::PropertyDefinition(property) => properties.push(&property.id),
+                Description::ExplicitComponentInst(explicit) => instances.extend(
+                    explicit
+                        .component_insts
+                        .component_insts
+                        .iter()
+                        .map(|inst| &inst.id),
+                ),
+                Description::PropertyAssignment(_) => {}
+            }
+        }
+
+        let mut new_types = HashMap::new();
+        for (kind, id) in types {
+            check_unique(&self.types, &new_types, name, kind, id)?;
+            new_types.insert(id.clone(), name.to_string());
+        }
+        let mut new_properties = HashMap::new();
+        for id in properties {
+            check_unique(&self.properties, &new_properties, name, "property", id)?;
+            new_properties.insert(id.clone(), name.to_string());
+        }
+        let mut new_instances = HashMap::new();
+        for id in instances {
+            check_unique(&self.instances, &new_instances, name, "instance", id)?;
+            new_instances.insert(id.clone(), name.to_string());
+        }
+
+        self.types.extend(new_types);
+        self.properties.extend(new_properties);
+        self.instances.extend(new_instances);
+        self.root.descriptions.extend(root.descriptions);
+        Ok(())
+    }
+
+    /// The descriptions of every source added so far, in order.
+    pub fn root(&self) -> &ast::Root {
+        &self.root
+    }
+
+    /// Every file read by [`Compiler::add_file`] so far, including the files
+    /// they include.
+    pub fn dependencies(&self) -> Dependencies {
+        self.file_source.dependencies()
+    }
+
+    /// Elaborate everything that has been added to the session.
+    pub fn elaborate(self, options: &ElaborateOptions) -> Result<RootNamespace, anyhow::Error> {
+        elaborate_with_options(self.root, options)
+    }
+}
+
+/// Gives the source locations of a tree parsed from a string the name of
+/// the source.
+struct SetFile(Arc<Path>);
+
+impl VisitMut for SetFile {
+    fn visit_component_inst_mut(&mut self, node: &mut ast::ComponentInst) {
+        if let Some(location) = &mut node.location {
+            location.file = Some(self.0.clone());
+        }
+        walk_component_inst_mut(self, node);
+    }
+}
+
+fn check_unique(
+    existing: &HashMap<String, String>,
+    new: &HashMap<String, String>,
+    name: &str,
+    kind: &str,
+    id: &str,
+) -> Result<(), anyhow::Error> {
+    if let Some(first) = existing.get(id).or_else(|| new.get(id)) {
+        bail!("{name}: duplicate definition of {kind} {id}, first defined in {first}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::file_source::MemFileSource;
+
+    fn compiler() -> Compiler {
+        Compiler::with_file_source(MemFileSource::from_entries(&[
+            (
+                "types.rdl".into(),
+                "reg ctrl_t { field {} EN; };\naddrmap block_t { ctrl_t CTRL; };".into(),
+            ),
+            (
+                "top.rdl".into(),
+                "addrmap top { block_t a; block_t b @ 0x10; };".into(),
+            ),
+            (
+                "dup.rdl".into(),
+                "enum mode_t { A; };\nreg ctrl_t { field {} x; };".into(),
+            ),
+        ]))
+    }
+
+    #[test]
+    fn test_multiple_files() {
+        let mut compiler = compiler();
+        compiler.add_file(Path::new("types.rdl")).unwrap();
+        compiler.add_file(Path::new("top.rdl")).unwrap();
+        assert_eq!(compiler.root().descriptions.len(), 3);
+        assert_eq!(
+            compiler.dependencies().paths(),
+            [Path::new("types.rdl"), Path::new("top.rdl")]
+        );
+        let namespace = compiler.elaborate(&Default::default()).unwrap();
+        let names: Vec<_> = namespace.roots().map(|node| node.name()).collect();
+        assert_eq!(names, ["top"]);
+        assert!(namespace.find("top.b.CTRL.EN").is_some());
+    }
+
+    #[test]
+    fn test_type_definitions() {
+        let mut compiler = compiler();
+        compiler
+            .add_source("enums.rdl", "enum mode_t { A; B; };")
+            .unwrap();
+        compiler.add_file(Path::new("types.rdl")).unwrap();
+        compiler.add_file(Path::new("top.rdl")).unwrap();
+        let namespace = compiler.elaborate(&Default::default()).unwrap();
+        assert!(namespace.find("top.a.CTRL.EN").is_some());
+    }
+
+    #[test]
+    fn test_source_locations() {
+        let mut compiler = compiler();
+        compiler.add_file(Path::new("types.rdl")).unwrap();
+        compiler.add_file(Path::new("top.rdl")).unwrap();
+        compiler
+            .add_source("inline.rdl", "addrmap {\n    block_t c;\n} extra;")
+            .unwrap();
+        let namespace = compiler.elaborate(&Default::default()).unwrap();
+        let location = |path: &str| {
+            namespace
+                .find(path)
+                .unwrap()
+                .location()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(location("extra.c"), "inline.rdl:2:13");
+        assert_eq!(location("extra.c.CTRL"), "types.rdl:2:26");
+        assert_eq!(location("extra.c.CTRL.EN"), "types.rdl:1:23");
+    }
+
+    #[test]
+    fn test_duplicate_definitions() {
+        let mut compiler = compiler();
+        compiler.add_file(Path::new("types.rdl")).unwrap();
+        let err = compiler.add_file(Path::new("dup.rdl")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "dup.rdl: duplicate definition of component ctrl_t, first defined in types.rdl"
+        );
+        // nothing from the failed file is added
+        assert_eq!(compiler.root().descriptions.len(), 2);
+
+        let err = compiler
+            .add_source("<inline>", "addrmap { } a; addrmap { } a;")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "<inline>: duplicate definition of instance a, first defined in <inline>"
+        );
+    }
+}
@@ -0,0 +1,313 @@
+// Licensed under the Apache-2.0 license
+
+use crate::ast::PrimaryLiteral;
+use crate::elaborator::as_u64;
+use crate::{Field, NodeId, PropertyValue};
+use anyhow::{bail, ensure};
+
+/// A counter parameter that is either fixed or taken from another field or
+/// signal.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CounterValue {
+    Constant(u64),
+    Reference(NodeId),
+}
+
+/// The behavior of a field with the `counter` property (SystemRDL 2.0
+/// section 9.8).
+///
+/// See [`Field::counter`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CounterSpec {
+    /// What increments the counter, if not its implied `incr` input.
+    pub incr: Option<NodeId>,
+    /// How much each increment adds, unless it is given by an input
+    /// `incrwidth` bits wide.
+    pub incrvalue: Option<CounterValue>,
+    /// The width of the input giving how much each increment adds.
+    pub incrwidth: Option<u64>,
+    /// What decrements the counter, if not its implied `decr` input.
+    pub decr: Option<NodeId>,
+    /// How much each decrement subtracts, unless it is given by an input
+    /// `decrwidth` bits wide.
+    pub decrvalue: Option<CounterValue>,
+    /// The width of the input giving how much each decrement subtracts.
+    pub decrwidth: Option<u64>,
+    /// The value the counter stops at when counting up, or `None` if it
+    /// wraps.
+    pub incrsaturate: Option<CounterValue>,
+    /// The value the counter stops at when counting down, or `None` if it
+    /// wraps.
+    pub decrsaturate: Option<CounterValue>,
+    /// The value at or above which the counter flags that it crossed its
+    /// threshold when counting up.
+    pub incrthreshold: Option<CounterValue>,
+    /// The value at or below which the counter flags that it crossed its
+    /// threshold when counting down.
+    pub decrthreshold: Option<CounterValue>,
+    up: bool,
+    down: bool,
+}
+
+impl CounterSpec {
+    /// Whether the counter counts up, which it does unless only `decr`
+    /// properties are given.
+    pub fn counts_up(&self) -> bool {
+        self.up
+    }
+
+    /// Whether the counter counts down, which it does if any `decr` property
+    /// is given.
+    pub fn counts_down(&self) -> bool {
+        self.down
+    }
+
+    /// Whether the counter has the output named by a property reference such
+    /// as `cnt->overflow`.
+    pub fn has_output(&self, property: &str) -> bool {
+        match property {
+            "overflow" => self.up,
+            "underflow" => self.down,
+            "incr" => self.up,
+            "decr" => self.down,
+            "incrsaturate" | "saturate" => self.incrsaturate.is_some(),
+            "decrsaturate" => self.decrsaturate.is_some(),
+            "incrthreshold" | "threshold" => self.incrthreshold.is_some(),
+            "decrthreshold" => self.decrthreshold.is_some(),
+            _ => true,
+        }
+    }
+
+    /// Every node the counter refers to, with the property that names it.
+    pub fn references(&self) -> impl Iterator<Item = (&'static str, NodeId)> {
+        let value = |value: Option<CounterValue>| match value {
+            Some(CounterValue::Reference(id)) => Some(id),
+            _ => None,
+        };
+        [
+            ("incr", self.incr),
+            ("incrvalue", value(self.incrvalue)),
+            ("decr", self.decr),
+            ("decrvalue", value(self.decrvalue)),
+            ("incrsaturate", value(self.incrsaturate)),
+            ("decrsaturate", value(self.decrsaturate)),
+            ("incrthreshold", value(self.incrthreshold)),
+            ("decrthreshold", value(self.decrthreshold)),
+        ]
+        .into_iter()
+        .filter_map(|(name, id)| Some((name, id?)))
+    }
+}
+
+const INCR_PROPERTIES: [&str; 7] = [
+    "incr",
+    "incrvalue",
+    "incrwidth",
+    "incrsaturate",
+    "incrthreshold",
+    "saturate",
+    "threshold",
+];
+
+const DECR_PROPERTIES: [&str; 5] = [
+    "decr",
+    "decrvalue",
+    "decrwidth",
+    "decrsaturate",
+    "decrthreshold",
+];
+
+impl Field {
+    /// The counter behavior of the field, or `None` if it is not a counter.
+    ///
+    /// `saturate` and `threshold` are taken as the older names of
+    /// `incrsaturate` and `incrthreshold`. A saturate or threshold of `true`
+    /// means the largest value of the field when counting up, and 0 when
+    /// counting down.
+    ///
+    /// Fails for counter properties on a field that is not a counter, for a
+    /// value given both as a constant and as a width, and for constants that
+    /// do not fit in the field.
+    pub fn counter(&self) -> Result<Option<CounterSpec>, anyhow::Error> {
+        if !self.is_set("counter") {
+            let mut properties = INCR_PROPERTIES.iter().chain(&DECR_PROPERTIES);
+            if let Some(property) = properties.find(|p| self.property(p).is_some()) {
+                bail!("{property} is only allowed on counter fields");
+            }
+            return Ok(None);
+        }
+        let max = crate::Bits::new(self.width(), u64::MAX).val();
+        let down = DECR_PROPERTIES.iter().any(|p| self.property(p).is_some());
+        let up = !down || INCR_PROPERTIES.iter().any(|p| self.property(p).is_some());
+        let counter = CounterSpec {
+            incr: self.counter_reference("incr")?,
+            incrvalue: self.counter_value("incrvalue", None)?,
+            incrwidth: self.counter_width("incrwidth")?,
+            decr: self.counter_reference("decr")?,
+            decrvalue: self.counter_value("decrvalue", None)?,
+            decrwidth: self.counter_width("decrwidth")?,
+            incrsaturate: self.counter_alias("incrsaturate", "saturate", max)?,
+            decrsaturate: self.counter_value("decrsaturate", Some(0))?,
+            incrthreshold: self.counter_alias("incrthreshold", "threshold", max)?,
+            decrthreshold: self.counter_value("decrthreshold", Some(0))?,
+            up,
+            down,
+        };
+        for (value, width) in [("incrvalue", "incrwidth"), ("decrvalue", "decrwidth")] {
+            ensure!(
+                self.property(value).is_none() || self.property(width).is_none(),
+                "{value} and {width} cannot both be set"
+            );
+        }
+        let constants = [
+            ("incrvalue", counter.incrvalue),
+            ("decrvalue", counter.decrvalue),
+            ("incrsaturate", counter.incrsaturate),
+            ("decrsaturate", counter.decrsaturate),
+            ("incrthreshold", counter.incrthreshold),
+            ("decrthreshold", counter.decrthreshold),
+        ];
+        for (name, value) in constants {
+            if let Some(CounterValue::Constant(value)) = value {
+                ensure!(
+                    value <= max,
+                    "{name} value {value:#x} does not fit in {} bits",
+                    self.width()
+                );
+            }
+        }
+        for (name, width) in [
+            ("incrwidth", counter.incrwidth),
+            ("decrwidth", counter.decrwidth),
+        ] {
+            if let Some(width) = width {
+                ensure!(
+                    (1..=self.width()).contains(&width),
+                    "{name} must be from 1 to the field width of {}, got {width}",
+                    self.width()
+                );
+            }
+        }
+        Ok(Some(counter))
+    }
+
+    fn counter_reference(&self, name: &str) -> Result<Option<NodeId>, anyhow::Error> {
+        match self.property(name) {
+            Some(PropertyValue::Reference(id)) => Ok(Some(*id)),
+            Some(other) => bail!("{name} must be a reference, got {other:?}"),
+            None => Ok(None),
+        }
+    }
+
+    fn counter_width(&self, name: &str) -> Result<Option<u64>, anyhow::Error> {
+        match self.property(name) {
+            Some(PropertyValue::Literal(literal)) => Ok(Some(as_u64(literal)?)),
+            Some(other) => bail!("{name} must be a number, got {other:?}"),
+            None => Ok(None),
+        }
+    }
+
+    /// A value that can be a number or a reference, or a boolean meaning
+    /// `default` when true and no value when false.
+    fn counter_value(
+        &self,
+        name: &str,
+        default: Option<u64>,
+    ) -> Result<Option<CounterValue>, anyhow::Error> {
+        match (self.property(name), default) {
+            (Some(PropertyValue::Literal(PrimaryLiteral::BooleanLiteral(set))), Some(default)) => {
+                Ok(set.then_some(CounterValue::Constant(default)))
+            }
+            (Some(PropertyValue::Literal(literal)), _) => {
+                Ok(Some(CounterValue::Constant(as_u64(literal)?)))
+            }
+            (Some(PropertyValue::Reference(id)), _) => Ok(Some(CounterValue::Reference(*id))),
+            (Some(other), _) => bail!("{name} must be a number or a reference, got {other:?}"),
+            (None, _) => Ok(None),
+        }
+    }
+
+    /// [`Field::counter_value`] for a property that also has an older name.
+    fn counter_alias(
+        &self,
+        name: &str,
+        alias: &str,
+        default: u64,
+    ) -> Result<Option<CounterValue>, anyhow::Error> {
+        match (self.property(name), self.property(alias)) {
+            (Some(_), Some(_)) => bail!("{alias} conflicts with {name}"),
+            (None, Some(_)) => self.counter_value(alias, Some(default)),
+            _ => self.counter_value(name, Some(default)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::NodeContent;
+
+    fn counter(body: &str) -> Result<Option<CounterSpec>, String> {
+        let input =
+            format!("addrmap {{ reg {{ field {{}} en[4]; field {{ {body} }} f[8]; }} r0; }} top;");
+        let namespace = crate::elaborate(crate::parse(&input).unwrap()).unwrap();
+        match namespace.find("top.r0.f").unwrap().content() {
+            NodeContent::Field(field) => field.counter().map_err(|e| e.to_string()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_counter() {
+        assert_eq!(counter("sw = r;"), Ok(None));
+
+        let up = counter("counter; incrsaturate; threshold = 0x80;")
+            .unwrap()
+            .unwrap();
+        assert!(up.counts_up());
+        assert!(!up.counts_down());
+        assert_eq!(up.incrsaturate, Some(CounterValue::Constant(0xff)));
+        assert_eq!(up.incrthreshold, Some(CounterValue::Constant(0x80)));
+        assert_eq!(up.decrsaturate, None);
+        assert!(up.has_output("overflow"));
+        assert!(!up.has_output("underflow"));
+
+        let updown = counter("counter; incrwidth = 2; decrvalue = en; decrsaturate;")
+            .unwrap()
+            .unwrap();
+        assert!(updown.counts_up());
+        assert!(updown.counts_down());
+        assert_eq!(updown.incrwidth, Some(2));
+        assert!(matches!(updown.decrvalue, Some(CounterValue::Reference(_))));
+        assert_eq!(updown.decrsaturate, Some(CounterValue::Constant(0)));
+        assert_eq!(updown.references().count(), 1);
+
+        let down = counter("counter; decrvalue = 3;").unwrap().unwrap();
+        assert!(!down.counts_up());
+        assert!(down.counts_down());
+    }
+
+    #[test]
+    fn test_counter_errors() {
+        assert_eq!(
+            counter("incrvalue = 2;"),
+            Err("incrvalue is only allowed on counter fields".into())
+        );
+        assert_eq!(
+            counter("counter; incrsaturate = 0x100;"),
+            Err("incrsaturate value 0x100 does not fit in 8 bits".into())
+        );
+        assert_eq!(
+            counter("counter; incrvalue = 2; incrwidth = 2;"),
+            Err("incrvalue and incrwidth cannot both be set".into())
+        );
+        assert_eq!(
+            counter("counter; incrwidth = 9;"),
+            Err("incrwidth must be from 1 to the field width of 8, got 9".into())
+        );
+        assert_eq!(
+            counter("counter; saturate; incrsaturate = 4;"),
+            Err("saturate conflicts with incrsaturate".into())
+        );
+    }
+}
@@ -0,0 +1,147 @@
+// Licensed under the Apache-2.0 license
+
+use crate::FileSource;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A [`FileSource`] that records the path of every file successfully read
+/// through it, including files pulled in by `` `include ``.
+///
+/// ```no_run
+/// # fn main() -> Result<(), anyhow::Error> {
+/// use systemrdl::{DependencyTracker, FsFileSource};
+/// let file_source = DependencyTracker::new(FsFileSource::new());
+/// let root = systemrdl::ast::Root::from_file(&file_source, "top.rdl".as_ref())?;
+/// print!("{}", file_source.dependencies().rerun_if_changed());
+/// # Ok(())
+/// # }
+/// ```
+pub struct DependencyTracker<S> {
+    inner: S,
+    paths: Mutex<Vec<PathBuf>>,
+}
+
+impl<S: FileSource> DependencyTracker<S> {
+    pub fn new(inner: S) -> Self {
+        DependencyTracker {
+            inner,
+            paths: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The files read so far.
+    pub fn dependencies(&self) -> Dependencies {
+        Dependencies {
+            paths: self.paths.lock().unwrap().clone(),
+        }
+    }
+}
+
+impl<S: FileSource> FileSource for DependencyTracker<S> {
+    fn read_to_string(&self, path: &Path) -> std::io::Result<&str> {
+        let contents = self.inner.read_to_string(path)?;
+        let mut paths = self.paths.lock().unwrap();
+        if !paths.iter().any(|p| p == path) {
+            paths.push(path.to_path_buf());
+        }
+        Ok(contents)
+    }
+
+    fn original_location(&self, path: &Path, offset: usize) -> Option<(usize, usize)> {
+        self.inner.original_location(path, offset)
+    }
+}
+
+/// The files a design was parsed from, in the order they were first read.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Dependencies {
+    paths: Vec<PathBuf>,
+}
+
+impl Dependencies {
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// One `cargo:rerun-if-changed` line per file, for printing from a build
+    /// script.
+    pub fn rerun_if_changed(&self) -> String {
+        let mut out = String::new();
+        for path in &self.paths {
+            writeln!(out, "cargo:rerun-if-changed={}", path.display()).unwrap();
+        }
+        out
+    }
+
+    /// A Makefile rule saying that `target` depends on every file, in the
+    /// style of `cc -MD -MP`: each file also gets an empty rule so that make
+    /// does not fail when one of them is deleted.
+    pub fn depfile(&self, target: &Path) -> String {
+        let mut out = make_escape(target);
+        out.push(':');
+        for path in &self.paths {
+            write!(out, " \\\n  {}", make_escape(path)).unwrap();
+        }
+        out.push('\n');
+        for path in &self.paths {
+            write!(out, "\n{}:\n", make_escape(path)).unwrap();
+        }
+        out
+    }
+}
+
+/// Quote a path for use as a make target or prerequisite.
+fn make_escape(path: &Path) -> String {
+    let mut out = String::new();
+    for ch in path.display().to_string().chars() {
+        match ch {
+            ' ' | '#' | '\\' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            '$' => out.push_str("$$"),
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ParseOptions;
+    use crate::ast::Root;
+    use crate::file_source::MemFileSource;
+
+    #[test]
+    fn test_dependencies() {
+        let file_source = DependencyTracker::new(MemFileSource::from_entries(&[
+            (
+                "top.rdl".into(),
+                "`include \"ctrl.rdl\"\naddrmap top { ctrl_t ctrl; };".into(),
+            ),
+            ("inc/ctrl.rdl".into(), "reg ctrl_t { field {} EN; };".into()),
+        ]));
+        let options = ParseOptions {
+            include_paths: vec!["inc".into()],
+            ..Default::default()
+        };
+        Root::from_file_with_options(&file_source, Path::new("top.rdl"), &options).unwrap();
+
+        // the failed attempt to read ctrl.rdl next to top.rdl is not recorded
+        let deps = file_source.dependencies();
+        assert_eq!(
+            deps.paths(),
+            [PathBuf::from("top.rdl"), PathBuf::from("inc/ctrl.rdl")]
+        );
+        assert_eq!(
+            deps.rerun_if_changed(),
+            "cargo:rerun-if-changed=top.rdl\ncargo:rerun-if-changed=inc/ctrl.rdl\n"
+        );
+        assert_eq!(
+            deps.depfile(Path::new("out dir/regs.rs")),
+            "out\\ dir/regs.rs: \\\n  top.rdl \\\n  inc/ctrl.rdl\n\ntop.rdl:\n\ninc/ctrl.rdl:\n"
+        );
+    }
+}
@@ -1,294 +1,3624 @@
 #![allow(unused)]
 
-use std::{collections::HashMap, hash::Hash};
+use std::collections::{HashMap, HashSet};
+use std::ops::Index;
 
-use crate::ast::{self, PrimaryLiteral};
+use anyhow::{bail, ensure};
 
+use crate::ast::{self, AddressingType, PrimaryLiteral};
+use crate::{Bits, SourceLocation, SwAccessPolicy, properties};
+
+/// Handle to a [`Node`] within a [`RootNamespace`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NodeId(usize);
+
+/// Handle to a [`TypeDef`] within a [`RootNamespace`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct TypeId(usize);
+
+/// The elaborated form of a SystemRDL description.
+///
+/// Nodes are stored in an arena and refer to their parent, their children and
+/// any other nodes named in their properties by [`NodeId`]. Ids remain valid
+/// for as long as the namespace exists, so can be stored by downstream tools.
 #[derive(Debug, Clone)]
 pub struct RootNamespace {
     nodes: Vec<Node>,
+    roots: Vec<NodeId>,
+    types: Vec<TypeDef>,
 }
 
+/// Elaborate every addrmap instantiated in the root scope, along with each
+/// one that is defined there but never instantiated. Only addrmaps can be
+/// instantiated in the root scope.
+///
+/// Use [`elaborate_with_options`] to elaborate a single top-level addrmap.
 pub fn elaborate(ast: ast::Root) -> Result<RootNamespace, anyhow::Error> {
-    let mut root = RootNamespace { nodes: Vec::new() };
+    let mut elaborator = Elaborator::default();
+    let mut roots = Vec::new();
+    let mut scope = Scope::default();
+    let mut uninstantiated = Vec::new();
 
-    for desc in ast.descriptions {
+    for desc in &ast.descriptions {
         match desc {
             ast::Description::ComponentDef(component) => {
-                root.nodes.append(&mut elaborate_component(&component));
+                let nodes = elaborator.elaborate_component(component, &mut scope)?;
+                if let (None, ast::ComponentDef::Named(ast::ComponentType::AddrMap, name, _, _)) =
+                    (&component.insts, &component.def)
+                {
+                    uninstantiated.push(name);
+                }
+                for node in nodes {
+                    roots.push(elaborator.place_top_level(node)?);
+                }
+            }
+            // these define types and properties, but nothing is instantiated
+            // from them, so there is nothing to elaborate
+            ast::Description::EnumDef(_)
+            | ast::Description::PropertyDefinition(_)
+            | ast::Description::StructDef(_)
+            | ast::Description::ConstraintDef(_) => {}
+            ast::Description::ExplicitComponentInst(explicit_component_inst) => {
+                ensure!(
+                    explicit_component_inst.component_inst_alias.is_none(),
+                    "alias registers must be instantiated within a regfile or addrmap"
+                );
+                let nodes = elaborator
+                    .elaborate_explicit_component_inst(explicit_component_inst, &scope)?;
+                for node in nodes {
+                    roots.push(elaborator.place_top_level(node)?);
+                }
+            }
+            ast::Description::PropertyAssignment(property_assignment) => {
+                assign_root_property(property_assignment, &mut scope)?;
             }
-            ast::Description::EnumDef(enum_def) => todo!(),
-            ast::Description::PropertyDefinition(property_definition) => todo!(),
-            ast::Description::StructDef(struct_def) => todo!(),
-            ast::Description::ConstraintDef(constraint_def) => todo!(),
-            ast::Description::ExplicitComponentInst(explicit_component_inst) => todo!(),
-            ast::Description::PropertyAssignment(property_assignment) => todo!(),
         }
     }
 
-    Ok(root)
+    // A named addrmap that is defined in the root scope but never instantiated
+    // is a top-level component in its own right. Definitions can only
+    // instantiate those that come before them, so working backwards means
+    // each one has been seen by the time we need to know if it was used.
+    let mut candidates = Vec::new();
+    for name in uninstantiated.into_iter().rev() {
+        let def = scope.lookup_type(name).unwrap();
+        let type_id = elaborator.type_id(def.def);
+        if elaborator
+            .nodes
+            .iter()
+            .any(|node| node.type_id == Some(type_id))
+        {
+            continue;
+        }
+        let inst = ast::ComponentInst {
+            id: name.clone(),
+            array_or_range: None,
+            equals: None,
+            at: None,
+            plus_equals: None,
+            percent_equals: None,
+            location: None,
+        };
+        let node = elaborator.elaborate_instance(def, &[], &scope, &inst, None)?;
+        candidates.push(elaborator.place_top_level(node)?);
+    }
+    roots.extend(candidates.into_iter().rev());
+
+    elaborator.resolve_references(&roots)?;
+    elaborator.propagate_external(&roots)?;
+    elaborator.summarize_registers();
+
+    Ok(RootNamespace {
+        nodes: elaborator.nodes,
+        roots,
+        types: elaborator.types,
+    })
 }
 
-fn elaborate_component(component: &ast::Component) -> Vec<Node> {
-    assert!(component.inst_type.is_none());
+/// Options controlling how a description is elaborated.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ElaborateOptions {
+    /// The root addrmap to elaborate as the top level, named either by its
+    /// definition or by an instance of it in the root scope. Defaults to the
+    /// last one in the description.
+    pub top: Option<String>,
+    /// Values for the parameters of the top-level addrmap, overriding the
+    /// defaults of its definition and any values given where it is
+    /// instantiated.
+    pub params: HashMap<String, ast::ConstantExpr>,
+}
 
-    if let Some(insts) = &component.insts {
-        assert!(insts.param_insts.is_empty());
+/// An addrmap in the root scope that could be elaborated as the top level.
+enum TopCandidate<'a> {
+    Definition(&'a ast::ComponentDef, String),
+    Instance(
+        &'a ast::ComponentDef,
+        &'a [ast::ParamElem],
+        &'a ast::ComponentInst,
+    ),
+}
 
-        let mut result = Vec::new();
+/// Elaborate a single root addrmap as the top level, as chosen by `options`.
+///
+/// Other components in the root scope are available as types but are not
+/// elaborated, so the namespace has exactly one root.
+pub fn elaborate_with_options(
+    ast: ast::Root,
+    options: &ElaborateOptions,
+) -> Result<RootNamespace, anyhow::Error> {
+    let mut elaborator = Elaborator::default();
+    let mut scope = Scope::default();
+    let mut candidates = Vec::new();
 
-        for inst in &insts.component_insts {
-            //assert!(inst.array_or_range.is_none());
-            let _at = inst.at.as_ref().map(evaluate_constants);
-            //assert!(inst.equals.is_none());
-            assert!(inst.percent_equals.is_none());
-            assert!(inst.plus_equals.is_none());
-
-            let node = match &component.def {
-                ast::ComponentDef::Named(component_type, _, param_def, component_body) => todo!(),
-                ast::ComponentDef::Anon(component_type, component_body) => {
-                    //
-                    match component_type {
-                        ast::ComponentType::Field => {
-                            elaborate_field(inst.id.clone(), component_body)
-                        }
-                        ast::ComponentType::Reg => elaborate_reg(inst.id.clone(), component_body),
-                        ast::ComponentType::RegFile => todo!(),
-                        ast::ComponentType::AddrMap => {
-                            elaborate_addrmap(inst.id.clone(), component_body)
-                        }
-                        ast::ComponentType::Signal => todo!(),
-                        ast::ComponentType::Enum => todo!(),
-                        ast::ComponentType::EnumVariant => todo!(),
-                        ast::ComponentType::Mem => todo!(),
-                        ast::ComponentType::Constraint => todo!(),
+    for desc in &ast.descriptions {
+        match desc {
+            ast::Description::ComponentDef(component) => {
+                elaborator.define_component(component, &mut scope)?;
+                let is_addrmap = matches!(
+                    component.def,
+                    ast::ComponentDef::Named(ast::ComponentType::AddrMap, _, _, _)
+                        | ast::ComponentDef::Anon(ast::ComponentType::AddrMap, _)
+                );
+                if !is_addrmap {
+                    continue;
+                }
+                if let ast::ComponentDef::Named(_, name, _, _) = &component.def {
+                    candidates.push(TopCandidate::Definition(&component.def, name.clone()));
+                }
+                if let Some(insts) = &component.insts {
+                    for inst in &insts.component_insts {
+                        candidates.push(TopCandidate::Instance(
+                            &component.def,
+                            &insts.param_insts,
+                            inst,
+                        ));
                     }
                 }
+            }
+            ast::Description::ExplicitComponentInst(explicit_component_inst) => {
+                let type_name = &explicit_component_inst.id;
+                // the root scope has no parent to look in
+                let Some(&def) = scope.types.get(type_name) else {
+                    bail!("unknown component type {type_name}");
+                };
+                if !matches!(
+                    def,
+                    ast::ComponentDef::Named(ast::ComponentType::AddrMap, _, _, _)
+                ) {
+                    continue;
+                }
+                let insts = &explicit_component_inst.component_insts;
+                for inst in &insts.component_insts {
+                    candidates.push(TopCandidate::Instance(def, &insts.param_insts, inst));
+                }
+            }
+            ast::Description::PropertyAssignment(property_assignment) => {
+                assign_root_property(property_assignment, &mut scope)?;
+            }
+            ast::Description::EnumDef(_)
+            | ast::Description::PropertyDefinition(_)
+            | ast::Description::StructDef(_)
+            | ast::Description::ConstraintDef(_) => {}
+        }
+    }
+
+    let top = match &options.top {
+        Some(top) => candidates
+            .iter()
+            .rev()
+            .find(|candidate| match candidate {
+                TopCandidate::Definition(_, name) => name == top,
+                TopCandidate::Instance(_, _, inst) => inst.id == *top,
+            })
+            .ok_or_else(|| anyhow::format_err!("no root addrmap named {top}"))?,
+        None => candidates
+            .last()
+            .ok_or_else(|| anyhow::format_err!("no root addrmap to elaborate"))?,
+    };
+
+    let definition_inst;
+    let (def, param_insts, inst) = match top {
+        TopCandidate::Definition(def, name) => {
+            definition_inst = ast::ComponentInst {
+                id: name.clone(),
+                array_or_range: None,
+                equals: None,
+                at: None,
+                plus_equals: None,
+                percent_equals: None,
+                location: None,
             };
-            result.push(node);
-        }
-        result
-    } else {
-        let node = match &component.def {
-            ast::ComponentDef::Named(component_type, name, param_def, component_body) => {
-                match component_type {
-                    ast::ComponentType::Field => todo!(),
-                    ast::ComponentType::Reg => todo!(),
-                    ast::ComponentType::RegFile => todo!(),
-                    ast::ComponentType::AddrMap => {
-                        assert!(param_def.is_none());
-                        elaborate_addrmap(name.clone(), component_body)
+            (*def, &[][..], &definition_inst)
+        }
+        TopCandidate::Instance(def, param_insts, inst) => (*def, *param_insts, *inst),
+    };
+    let mut param_insts: Vec<ast::ParamElem> = param_insts
+        .iter()
+        .filter(|param| !options.params.contains_key(&param.id))
+        .cloned()
+        .collect();
+    let mut overrides: Vec<_> = options.params.iter().collect();
+    overrides.sort_by_key(|(id, _)| *id);
+    for (id, value) in overrides {
+        param_insts.push(ast::ParamElem {
+            id: id.clone(),
+            param_value: value.clone(),
+        });
+    }
+
+    let def = Definition { def, scope: &scope };
+    let node = elaborator.elaborate_instance(def, &param_insts, &scope, inst, None)?;
+    let roots = vec![elaborator.place_top_level(node)?];
+    elaborator.resolve_references(&roots)?;
+    elaborator.propagate_external(&roots)?;
+    elaborator.summarize_registers();
+
+    Ok(RootNamespace {
+        nodes: elaborator.nodes,
+        roots,
+        types: elaborator.types,
+    })
+}
+
+/// Apply a property assignment made in the root scope, which can only set
+/// defaults.
+fn assign_root_property<'a>(
+    property_assignment: &ast::PropertyAssignment,
+    scope: &mut Scope<'a>,
+) -> Result<(), anyhow::Error> {
+    let mut properties = HashMap::new();
+    let mut post_assignments = Vec::new();
+    assign_property(
+        property_assignment,
+        scope,
+        &mut properties,
+        &mut post_assignments,
+    )?;
+    ensure!(
+        properties.is_empty() && post_assignments.is_empty(),
+        "only default property assignments are allowed in the root scope"
+    );
+    Ok(())
+}
+
+/// A named component definition, along with the scope it was defined in.
+#[derive(Clone, Copy)]
+struct Definition<'a> {
+    def: &'a ast::ComponentDef,
+    scope: &'a Scope<'a>,
+}
+
+/// A lexical scope, holding the names and default property assignments that
+/// are visible to the components defined within it.
+#[derive(Default)]
+struct Scope<'a> {
+    parent: Option<&'a Scope<'a>>,
+    types: HashMap<String, &'a ast::ComponentDef>,
+    params: HashMap<String, PrimaryLiteral>,
+    defaults: HashMap<String, AssignedValue>,
+}
+
+impl<'a> Scope<'a> {
+    fn child(&'a self) -> Scope<'a> {
+        Scope {
+            parent: Some(self),
+            ..Default::default()
+        }
+    }
+
+    fn lookup_type(&'a self, name: &str) -> Option<Definition<'a>> {
+        match self.types.get(name) {
+            Some(&def) => Some(Definition { def, scope: self }),
+            None => self.parent?.lookup_type(name),
+        }
+    }
+
+    fn lookup_param(&self, name: &str) -> Option<&PrimaryLiteral> {
+        match self.params.get(name) {
+            Some(value) => Some(value),
+            None => self.parent?.lookup_param(name),
+        }
+    }
+
+    /// Default property values in effect for components defined in this
+    /// scope, with inner scopes taking precedence over outer ones.
+    fn effective_defaults(&self) -> HashMap<String, AssignedValue> {
+        let mut defaults = match self.parent {
+            Some(parent) => parent.effective_defaults(),
+            None => HashMap::new(),
+        };
+        defaults.extend(self.defaults.clone());
+        defaults
+    }
+}
+
+/// A property value as written in the source, before any instance it refers
+/// to has been resolved.
+#[derive(Clone, Debug)]
+enum AssignedValue {
+    Literal(PrimaryLiteral),
+    Reference(ast::InstanceRef, Option<String>),
+}
+
+/// A reference in a property value, waiting to be resolved once every node
+/// has been elaborated.
+struct PendingReference {
+    /// The node whose property holds the reference.
+    target: NodeId,
+    /// The node whose scope the reference is resolved from.
+    scope: NodeId,
+    property: String,
+    default: bool,
+    iref: ast::InstanceRef,
+    prop: Option<String>,
+}
+
+/// Where an instance was requested to be placed within its parent, before
+/// the parent has laid out its children.
+#[derive(Debug, Default)]
+struct Placement {
+    at: Option<u64>,
+    stride: Option<u64>,
+    align: Option<u64>,
+    /// The lsb of a field given an explicit bit range.
+    lsb: Option<u64>,
+}
+
+/// The parts of a component body common to every kind of component.
+#[derive(Default)]
+struct Body {
+    /// The properties assigned in the body, along with the defaults
+    /// inherited from enclosing scopes.
+    properties: HashMap<String, AssignedValue>,
+    /// The names of the properties assigned in the body itself.
+    assigned: HashSet<String>,
+    children: Vec<(NodeId, Placement)>,
+    /// References assigned to the properties of descendants from within the
+    /// body, e.g. `my_field->resetsignal = my_signal;`
+    post_references: Vec<(NodeId, String, ast::InstanceRef, Option<String>)>,
+}
+
+/// Builds up the arena of nodes as the AST is walked.
+#[derive(Default)]
+struct Elaborator {
+    nodes: Vec<Node>,
+    types: Vec<TypeDef>,
+    type_ids: HashMap<*const ast::ComponentDef, TypeId>,
+    pending: Vec<PendingReference>,
+}
+
+impl Elaborator {
+    fn type_id(&mut self, def: &ast::ComponentDef) -> TypeId {
+        let ast::ComponentDef::Named(component_type, name, _, _) = def else {
+            unreachable!("anonymous components do not have a type");
+        };
+        *self.type_ids.entry(def).or_insert_with(|| {
+            self.types.push(TypeDef {
+                name: name.clone(),
+                component_type: *component_type,
+                def: def.clone(),
+            });
+            TypeId(self.types.len() - 1)
+        })
+    }
+
+    /// Make a named component definition available as a type in `scope`.
+    fn define_component<'a>(
+        &mut self,
+        component: &'a ast::Component,
+        scope: &mut Scope<'a>,
+    ) -> Result<(), anyhow::Error> {
+        if let ast::ComponentDef::Named(_, name, _, _) = &component.def {
+            ensure!(
+                !scope.types.contains_key(name),
+                "duplicate definition of component {name}"
+            );
+            scope.types.insert(name.clone(), &component.def);
+        }
+        Ok(())
+    }
+
+    fn elaborate_component<'a>(
+        &mut self,
+        component: &'a ast::Component,
+        scope: &mut Scope<'a>,
+    ) -> Result<Vec<(NodeId, Placement)>, anyhow::Error> {
+        self.define_component(component, scope)?;
+
+        let mut result = Vec::new();
+        if let Some(insts) = &component.insts {
+            for inst in &insts.component_insts {
+                let def = Definition {
+                    def: &component.def,
+                    scope,
+                };
+                result.push(self.elaborate_instance(
+                    def,
+                    &insts.param_insts,
+                    scope,
+                    inst,
+                    component.inst_type,
+                )?);
+            }
+        }
+        Ok(result)
+    }
+
+    fn elaborate_explicit_component_inst(
+        &mut self,
+        explicit_component_inst: &ast::ExplicitComponentInst,
+        scope: &Scope,
+    ) -> Result<Vec<(NodeId, Placement)>, anyhow::Error> {
+        let type_name = &explicit_component_inst.id;
+        let Some(def) = scope.lookup_type(type_name) else {
+            bail!("unknown component type {type_name}");
+        };
+        let insts = &explicit_component_inst.component_insts;
+        insts
+            .component_insts
+            .iter()
+            .map(|inst| {
+                self.elaborate_instance(
+                    def,
+                    &insts.param_insts,
+                    scope,
+                    inst,
+                    explicit_component_inst.component_inst_type,
+                )
+            })
+            .collect()
+    }
+
+    fn elaborate_instance(
+        &mut self,
+        def: Definition,
+        param_insts: &[ast::ParamElem],
+        inst_scope: &Scope,
+        inst: &ast::ComponentInst,
+        inst_type: Option<ast::ComponentInstType>,
+    ) -> Result<(NodeId, Placement), anyhow::Error> {
+        let (component_type, type_id, param_def, body) = match def.def {
+            ast::ComponentDef::Named(component_type, _, param_def, body) => (
+                *component_type,
+                Some(self.type_id(def.def)),
+                param_def.as_ref(),
+                body,
+            ),
+            ast::ComponentDef::Anon(component_type, body) => (*component_type, None, None, body),
+        };
+
+        let mut scope = def.scope.child();
+        bind_params(&mut scope, param_def, param_insts, inst_scope)?;
+
+        let mut placement = Placement {
+            at: inst
+                .at
+                .as_ref()
+                .map(|expr| evaluate_number(expr, inst_scope))
+                .transpose()?,
+            stride: inst
+                .plus_equals
+                .as_ref()
+                .map(|expr| evaluate_number(expr, inst_scope))
+                .transpose()?,
+            align: inst
+                .percent_equals
+                .as_ref()
+                .map(|expr| evaluate_number(expr, inst_scope))
+                .transpose()?,
+            lsb: None,
+        };
+        ensure!(
+            placement.stride != Some(0),
+            "stride of {} must be non-zero",
+            inst.id
+        );
+
+        match (component_type, inst_type) {
+            (ast::ComponentType::Mem, Some(ast::ComponentInstType::Internal)) => {
+                bail!("mem {} is always external, so cannot be internal", inst.id)
+            }
+            (ast::ComponentType::Field | ast::ComponentType::Signal, Some(inst_type)) => {
+                bail!("{component_type} {} cannot be {inst_type}", inst.id)
+            }
+            _ => {}
+        }
+
+        let mut body = self.elaborate_body(body, &mut scope)?;
+        let children = std::mem::take(&mut body.children);
+        for (child, _) in &children {
+            let child = &self.nodes[child.0];
+            let child_type = child.content.component_type();
+            ensure!(
+                properties::can_contain(component_type, child_type),
+                "{child_type} {} cannot be instantiated within {component_type} {}",
+                child.name,
+                inst.id
+            );
+        }
+        let (mut content, children) = match component_type {
+            ast::ComponentType::Field => {
+                ensure!(
+                    placement.at.is_none()
+                        && placement.stride.is_none()
+                        && placement.align.is_none(),
+                    "field {} cannot be given an address",
+                    inst.id
+                );
+                let (field, lsb) =
+                    elaborate_field(&mut body.properties, &body.assigned, inst, inst_scope)?;
+                placement.lsb = lsb;
+                (NodeContent::Field(field), Vec::new())
+            }
+            ast::ComponentType::Reg => {
+                let register = elaborate_reg(&body.properties, inst, inst_scope)?;
+                let children = self.layout_fields(children)?;
+                (NodeContent::Register(register), children)
+            }
+            ast::ComponentType::RegFile => {
+                let children = self.layout_addressable(children, AddressingType::RegAlign)?;
+                let regfile = elaborate_regfile(&self.nodes, &children, inst, inst_scope)?;
+                (NodeContent::RegFile(regfile), children)
+            }
+            ast::ComponentType::AddrMap => {
+                let addressing = match literal(&body.properties, "addressing")? {
+                    Some(PrimaryLiteral::AddressingTypeLiteral(addressing)) => *addressing,
+                    Some(other) => bail!("invalid addressing mode {other:?}"),
+                    None => AddressingType::RegAlign,
+                };
+                let children = self.layout_addressable(children, addressing)?;
+                let addrmap = elaborate_addrmap(&self.nodes, &children, inst, inst_scope)?;
+                (NodeContent::Addrmap(addrmap), children)
+            }
+            ast::ComponentType::Signal => {
+                ensure!(
+                    placement.at.is_none()
+                        && placement.stride.is_none()
+                        && placement.align.is_none(),
+                    "signal {} cannot be given an address",
+                    inst.id
+                );
+                let signal = elaborate_signal(&body.properties, inst, inst_scope)?;
+                (NodeContent::Signal(signal), Vec::new())
+            }
+            ast::ComponentType::Mem => {
+                // the registers of a mem are virtual: views of its entries
+                for (id, _) in &children {
+                    if let NodeContent::Register(register) = &mut self.nodes[id.0].content {
+                        register.is_virtual = true;
                     }
-                    ast::ComponentType::Signal => todo!(),
-                    ast::ComponentType::Enum => todo!(),
-                    ast::ComponentType::EnumVariant => todo!(),
-                    ast::ComponentType::Mem => todo!(),
-                    ast::ComponentType::Constraint => todo!(),
                 }
+                let children = self.layout_addressable(children, AddressingType::RegAlign)?;
+                let mem = elaborate_mem(&body.properties, inst, inst_scope)?;
+                (NodeContent::Mem(mem), children)
+            }
+            ast::ComponentType::Enum
+            | ast::ComponentType::EnumVariant
+            | ast::ComponentType::Constraint => {
+                bail!("{component_type:?} cannot be instantiated as a component")
             }
-            ast::ComponentDef::Anon(component_type, component_body) => todo!(),
         };
-        vec![node]
+
+        let id = NodeId(self.nodes.len());
+        let mut references = Vec::new();
+        for (property, value) in body.properties {
+            // defaults only apply to the components that have the property
+            if !properties::applies_to(&property, component_type) {
+                ensure!(
+                    !body.assigned.contains(&property),
+                    "{property} cannot be assigned to {component_type} {}",
+                    inst.id
+                );
+                continue;
+            }
+            match value {
+                AssignedValue::Literal(value) => {
+                    content
+                        .properties_mut()
+                        .insert(property, PropertyValue::Literal(value));
+                }
+                AssignedValue::Reference(iref, prop) => references.push((property, iref, prop)),
+            }
+        }
+        for (property, value) in &scope.defaults {
+            match value {
+                AssignedValue::Literal(value) => {
+                    content
+                        .default_properties_mut()
+                        .insert(property.clone(), PropertyValue::Literal(value.clone()));
+                }
+                AssignedValue::Reference(iref, prop) => self.pending.push(PendingReference {
+                    target: id,
+                    scope: id,
+                    property: property.clone(),
+                    default: true,
+                    iref: iref.clone(),
+                    prop: prop.clone(),
+                }),
+            }
+        }
+        for (property, iref, prop) in references {
+            self.pending.push(PendingReference {
+                target: id,
+                scope: id,
+                property,
+                default: false,
+                iref,
+                prop,
+            });
+        }
+        for (target, property, iref, prop) in body.post_references {
+            self.pending.push(PendingReference {
+                target,
+                scope: id,
+                property,
+                default: false,
+                iref,
+                prop,
+            });
+        }
+
+        for child in &children {
+            self.nodes[child.0].parent = Some(id);
+        }
+        self.nodes.push(Node {
+            id,
+            name: inst.id.clone(),
+            type_id,
+            parent: None,
+            children,
+            content,
+            inst_type,
+            external: false,
+            location: inst.location.clone(),
+        });
+        Ok((id, placement))
     }
-}
 
-fn elaborate_addrmap(name: String, body: &ast::ComponentBody, properties: Vec<&HashMap<String, PrimaryLiteral>) -> Node {
-    let mut addrmap = AddrMap {
-        name,
-        properties: HashMap::new(),
-        default_properties: HashMap::new(),
-    };
-    let mut children = Vec::new();
-
-    for elem in &body.elements {
-        match elem {
-            ast::ComponentBodyElem::ComponentDef(component) => {
-                let mut child = elaborate_component(component);
-                children.append(&mut child);
-            }
-            ast::ComponentBodyElem::EnumDef(enum_def) => todo!(),
-            ast::ComponentBodyElem::StructDef(struct_def) => todo!(),
-            ast::ComponentBodyElem::ConstraintDef(constraint_def) => todo!(),
-            ast::ComponentBodyElem::ExplicitComponentInst(explicit_component_inst) => todo!(),
-            ast::ComponentBodyElem::PropertyAssignment(property_assignment) => {
-                match property_assignment {
-                    ast::PropertyAssignment::ExplicitOrDefaultPropAssignment(
-                        ast::ExplicitOrDefaultPropAssignment::ExplicitPropModifier(
-                            default_keyword,
-                            explicit_prop_modifier,
-                        ),
-                    ) => todo!("property modifiers"),
-                    ast::PropertyAssignment::ExplicitOrDefaultPropAssignment(
-                        ast::ExplicitOrDefaultPropAssignment::ExplicitPropAssignment(
-                            default_keyword,
-                            explicit_property_assignment,
-                        ),
-                    ) => match explicit_property_assignment {
-                        ast::ExplicitPropertyAssignment::Assignment(
-                            identity_or_prop_keyword,
-                            prop_assignment_rhs,
-                        ) => {
-                            let property_namespace = if default_keyword.is_some() {
-                                &mut addrmap.default_properties
-                            } else {
-                                &mut addrmap.properties
-                            };
-
-                            let prop_id = match identity_or_prop_keyword {
-                                ast::IdentityOrPropKeyword::Id(prop_id) => prop_id.clone(),
-                                ast::IdentityOrPropKeyword::PropKeyword(prop_keyword) => {
-                                    // treat as a string
-                                    format!("{prop_keyword:?}").to_lowercase()
-                                }
-                            };
-
-                            match prop_assignment_rhs {
-                                Some(ast::PropAssignmentRhs::ConstantExpr(constant_expr)) => {
-                                    let value = evaluate_constants(constant_expr);
-                                    if property_namespace.contains_key(&prop_id) {
-                                        panic!("duplicate property {prop_id}");
-                                    }
-                                    property_namespace.insert(prop_id, value);
-                                }
-                                Some(ast::PropAssignmentRhs::PrecedenceType(precedence_type)) => {
-                                    todo!()
-                                }
-                                None => todo!(),
-                            }
-                        }
-                        ast::ExplicitPropertyAssignment::EncodeAssignment(_) => {
-                            todo!("property encode assignment")
-                        }
-                    },
-                    ast::PropertyAssignment::PostPropAssignment(
-                        ast::PostPropAssignment::PropRef(prop_ref, prop_assignment_rhs),
-                    ) => {
-                        todo!("property references")
+    fn elaborate_body<'a>(
+        &mut self,
+        body: &'a ast::ComponentBody,
+        scope: &mut Scope<'a>,
+    ) -> Result<Body, anyhow::Error> {
+        let mut properties = HashMap::new();
+        let mut post_assignments = Vec::new();
+        let mut children = Vec::new();
+        let mut aliases = Vec::new();
+
+        for elem in &body.elements {
+            match elem {
+                ast::ComponentBodyElem::ComponentDef(component) => {
+                    let mut child = self.elaborate_component(component, scope)?;
+                    children.append(&mut child);
+                }
+                ast::ComponentBodyElem::EnumDef(_)
+                | ast::ComponentBodyElem::StructDef(_)
+                | ast::ComponentBodyElem::ConstraintDef(_) => {}
+                ast::ComponentBodyElem::ExplicitComponentInst(explicit_component_inst) => {
+                    let mut child =
+                        self.elaborate_explicit_component_inst(explicit_component_inst, scope)?;
+                    if let Some(alias) = &explicit_component_inst.component_inst_alias {
+                        aliases.extend(child.iter().map(|(id, _)| (*id, alias.id.as_str())));
+                    }
+                    children.append(&mut child);
+                }
+                ast::ComponentBodyElem::PropertyAssignment(property_assignment) => {
+                    assign_property(
+                        property_assignment,
+                        scope,
+                        &mut properties,
+                        &mut post_assignments,
+                    )?;
+                }
+            }
+        }
+
+        self.link_aliases(&children, &aliases)?;
+
+        // Assignments made from within this body to the properties of its
+        // descendants, e.g. `my_reg.my_field->reset = 1;`
+        let mut post_references = Vec::new();
+        for (path, prop_id, value) in post_assignments {
+            let mut candidates: Vec<NodeId> = children.iter().map(|(id, _)| *id).collect();
+            let mut target = None;
+            for (i, element) in path.iter().enumerate() {
+                let Some(&found) = candidates
+                    .iter()
+                    .find(|id| self.nodes[id.0].name == *element)
+                else {
+                    bail!(
+                        "unknown instance {} in property reference",
+                        path[..=i].join(".")
+                    );
+                };
+                candidates = self.nodes[found.0].children.clone();
+                target = Some(found);
+            }
+            let Some(target) = target else {
+                continue;
+            };
+            let component_type = self.nodes[target.0].content.component_type();
+            ensure!(
+                properties::applies_to(&prop_id, component_type),
+                "{prop_id} cannot be assigned to {component_type} {}",
+                path.join(".")
+            );
+            if let NodeContent::Field(field) = &mut self.nodes[target.0].content {
+                field.assigned.insert(prop_id.clone());
+            }
+            match value {
+                AssignedValue::Literal(value) => {
+                    self.nodes[target.0]
+                        .content
+                        .properties_mut()
+                        .insert(prop_id, PropertyValue::Literal(value));
+                }
+                AssignedValue::Reference(iref, prop) => {
+                    post_references.push((target, prop_id, iref, prop));
+                }
+            }
+        }
+
+        let assigned = properties.keys().cloned().collect();
+        let mut effective = scope
+            .parent
+            .map(Scope::effective_defaults)
+            .unwrap_or_default();
+        effective.extend(properties);
+
+        Ok(Body {
+            properties: effective,
+            assigned,
+            children,
+            post_references,
+        })
+    }
+
+    /// Link each alias register to the primary register it names, which must
+    /// be one of its siblings.
+    fn link_aliases(
+        &mut self,
+        children: &[(NodeId, Placement)],
+        aliases: &[(NodeId, &str)],
+    ) -> Result<(), anyhow::Error> {
+        for &(alias, primary_name) in aliases {
+            let name = self.nodes[alias.0].name.clone();
+            let Some(primary) = children
+                .iter()
+                .map(|(id, _)| *id)
+                .find(|id| self.nodes[id.0].name == primary_name)
+            else {
+                bail!("unknown primary register {primary_name} for alias {name}");
+            };
+            ensure!(
+                !aliases.iter().any(|(id, _)| *id == primary),
+                "alias {name} cannot be an alias of {primary_name}, which is itself an alias"
+            );
+            let primary_type = self.nodes[primary.0].content.component_type();
+            ensure!(
+                primary_type == ast::ComponentType::Reg,
+                "alias {name} must be an alias of a register, but {primary_name} is a {primary_type}"
+            );
+            let NodeContent::Register(register) = &mut self.nodes[alias.0].content else {
+                bail!("alias {name} must be a register");
+            };
+            register.alias_of = Some(primary);
+        }
+        Ok(())
+    }
+
+    /// Place the fields of a register, packing fields without an explicit bit
+    /// range upwards from the lsb.
+    fn layout_fields(
+        &mut self,
+        children: Vec<(NodeId, Placement)>,
+    ) -> Result<Vec<NodeId>, anyhow::Error> {
+        // `None` once a field has taken the last bit position
+        let mut next_lsb = Some(0);
+        let mut fields = Vec::new();
+        for (id, placement) in children {
+            let node = &mut self.nodes[id.0];
+            // the only other components a register can contain are signals
+            let NodeContent::Field(field) = &mut node.content else {
+                fields.push(id);
+                continue;
+            };
+            let msb = placement
+                .lsb
+                .or(next_lsb)
+                .and_then(|lsb| lsb.checked_add(field.width - 1))
+                .ok_or_else(|| {
+                    anyhow::format_err!("field {} does not fit in the register", node.name)
+                })?;
+            field.lsb = msb - (field.width - 1);
+            next_lsb = msb.checked_add(1);
+            fields.push(id);
+        }
+        Ok(fields)
+    }
+
+    /// Assign addresses to the children of an address map according to its
+    /// addressing mode.
+    fn layout_addressable(
+        &mut self,
+        children: Vec<(NodeId, Placement)>,
+        addressing: AddressingType,
+    ) -> Result<Vec<NodeId>, anyhow::Error> {
+        let mut next_offset: u64 = 0;
+        let mut result = Vec::new();
+        for (id, placement) in children {
+            let node = &mut self.nodes[id.0];
+            // signals are wires, so take up no address space
+            if let NodeContent::Signal(_) = node.content {
+                result.push(id);
+                continue;
+            }
+            let Some(size) = node.size() else {
+                bail!("{} cannot be instantiated within an address map", node.name);
+            };
+            let accesswidth = match &node.content {
+                NodeContent::Register(register) => match register.properties.get("accesswidth") {
+                    Some(PropertyValue::Literal(value)) => as_u64(value)?,
+                    Some(other) => bail!("expected a number, got {other:?}"),
+                    None => register.width,
+                },
+                _ => 8,
+            };
+
+            let too_large = || anyhow::format_err!("{} is too large", node.name);
+            let stride = match placement.stride {
+                Some(stride) => stride,
+                None if addressing == AddressingType::Compact => size,
+                None => size.checked_next_power_of_two().ok_or_else(too_large)?,
+            };
+            let align = match placement.align {
+                Some(align) => align,
+                None => match addressing {
+                    AddressingType::Compact => accesswidth / 8,
+                    AddressingType::RegAlign => {
+                        size.checked_next_power_of_two().ok_or_else(too_large)?
+                    }
+                    AddressingType::FullAlign => {
+                        let count = node.array().map(Array::len).unwrap_or(1);
+                        stride
+                            .checked_mul(count - 1)
+                            .and_then(|span| span.checked_add(size))
+                            .and_then(u64::checked_next_power_of_two)
+                            .ok_or_else(too_large)?
+                    }
+                },
+            };
+            ensure!(
+                align.is_power_of_two(),
+                "alignment of {} must be a power of two",
+                node.name
+            );
+            let offset = match placement.at {
+                Some(at) => at,
+                None => next_offset.checked_next_multiple_of(align).ok_or_else(|| {
+                    anyhow::format_err!("{} does not fit in the address space", node.name)
+                })?,
+            };
+
+            node.set_offset(offset);
+            if let Some(array) = node.array_mut() {
+                array.stride = stride;
+            }
+            next_offset = node.end()?;
+            result.push(id);
+        }
+        Ok(result)
+    }
+
+    fn place_top_level(
+        &mut self,
+        (id, placement): (NodeId, Placement),
+    ) -> Result<NodeId, anyhow::Error> {
+        let node = &mut self.nodes[id.0];
+        let component_type = node.content.component_type();
+        ensure!(
+            component_type == ast::ComponentType::AddrMap,
+            "{component_type} {} cannot be instantiated at the root",
+            node.name
+        );
+        if let Some(at) = placement.at {
+            node.set_offset(at);
+        }
+        if let Some(size) = node.size()
+            && let Some(array) = node.array_mut()
+        {
+            array.stride = placement.stride.unwrap_or(size);
+        }
+        if node.size().is_some() {
+            node.end()?;
+        }
+        Ok(id)
+    }
+
+    /// Resolve the instance references held in property values, now that
+    /// every node they could refer to exists.
+    ///
+    /// A reference is looked up amongst the children of the component whose
+    /// body made the assignment, and then amongst those of each of its
+    /// ancestors in turn.
+    fn resolve_references(&mut self, roots: &[NodeId]) -> Result<(), anyhow::Error> {
+        for pending in std::mem::take(&mut self.pending) {
+            let path: Vec<&str> = pending
+                .iref
+                .elements
+                .iter()
+                .map(|element| element.id.as_str())
+                .collect();
+            ensure!(
+                pending
+                    .iref
+                    .elements
+                    .iter()
+                    .all(|element| element.arrays.is_empty()),
+                "references to individual array elements are not supported: {}",
+                path.join(".")
+            );
+
+            let mut scope = Some(pending.scope);
+            let resolved = loop {
+                let candidates = match scope {
+                    Some(scope) => &self.nodes[scope.0].children,
+                    None => roots,
+                };
+                if let Some(found) = self.lookup_path(candidates, &path) {
+                    break found;
+                }
+                let Some(current) = scope else {
+                    bail!(
+                        "unable to resolve reference {} in property {}",
+                        path.join("."),
+                        pending.property
+                    );
+                };
+                scope = self.nodes[current.0].parent;
+            };
+
+            let value = match pending.prop {
+                Some(prop) => PropertyValue::PropertyReference(resolved, prop),
+                None => PropertyValue::Reference(resolved),
+            };
+            let content = &mut self.nodes[pending.target.0].content;
+            let properties = if pending.default {
+                content.default_properties_mut()
+            } else {
+                content.properties_mut()
+            };
+            properties.insert(pending.property, value);
+        }
+        Ok(())
+    }
+
+    /// Mark the nodes that are external: those instantiated as `external`,
+    /// every mem, and everything within them, which is decoded along with
+    /// the external block rather than by the enclosing one.
+    fn propagate_external(&mut self, roots: &[NodeId]) -> Result<(), anyhow::Error> {
+        let mut stack: Vec<(NodeId, bool)> = roots.iter().map(|id| (*id, false)).collect();
+        while let Some((id, parent_external)) = stack.pop() {
+            let node = &mut self.nodes[id.0];
+            if parent_external && node.inst_type == Some(ast::ComponentInstType::Internal) {
+                bail!(
+                    "{} cannot be internal, since it is within an external component",
+                    node.name
+                );
+            }
+            node.external = parent_external
+                || node.inst_type == Some(ast::ComponentInstType::External)
+                || matches!(node.content, NodeContent::Mem(_));
+            stack.extend(node.children.iter().map(|child| (*child, node.external)));
+        }
+        Ok(())
+    }
+
+    /// Combine the fields of each register into its [`RegisterMasks`] and
+    /// its `intr` and `halt` properties, once every property, including those
+    /// assigned from enclosing scopes, has its final value.
+    ///
+    /// A register is `intr` if any of its fields is an interrupt, and `halt`
+    /// if any of those has a `haltenable` or `haltmask`.
+    fn summarize_registers(&mut self) {
+        for i in 0..self.nodes.len() {
+            let NodeContent::Register(register) = &self.nodes[i].content else {
+                continue;
+            };
+            let fields: Vec<&Field> = self.nodes[i]
+                .children
+                .iter()
+                .filter_map(|child| match &self.nodes[child.0].content {
+                    NodeContent::Field(field) => Some(field),
+                    _ => None,
+                })
+                .collect();
+            let masks = (register.width <= 64).then(|| RegisterMasks::new(register.width, &fields));
+            let interrupts: Vec<_> = fields
+                .iter()
+                .filter_map(|field| field.interrupt().ok().flatten())
+                .collect();
+            let intr = !interrupts.is_empty();
+            let halt = interrupts.iter().any(|interrupt| interrupt.halt());
+            if let NodeContent::Register(register) = &mut self.nodes[i].content {
+                register.masks = masks;
+                for (name, set) in [("intr", intr), ("halt", halt)] {
+                    if set {
+                        register.properties.insert(
+                            name.into(),
+                            PropertyValue::Literal(PrimaryLiteral::BooleanLiteral(true)),
+                        );
                     }
-                    ast::PropertyAssignment::PostPropAssignment(
-                        ast::PostPropAssignment::PostEncodeAssignment(post_encode_assignment),
-                    ) => todo!("encode properties"),
                 }
             }
         }
     }
 
-    Node {
-        content: NodeContent::Addrmap(addrmap),
-        children,
+    fn lookup_path<'a>(&'a self, mut candidates: &'a [NodeId], path: &[&str]) -> Option<NodeId> {
+        let mut found = None;
+        for element in path {
+            let id = *candidates
+                .iter()
+                .find(|id| self.nodes[id.0].name == *element)?;
+            candidates = &self.nodes[id.0].children;
+            found = Some(id);
+        }
+        found
+    }
+}
+
+/// Bind the parameters of a component definition, taking values from the
+/// instantiation where given and from the definition's defaults otherwise.
+fn bind_params(
+    scope: &mut Scope,
+    param_def: Option<&ast::ParamDef>,
+    param_insts: &[ast::ParamElem],
+    inst_scope: &Scope,
+) -> Result<(), anyhow::Error> {
+    let params = match param_def {
+        Some(ast::ParamDef::Params(params)) => params.as_slice(),
+        None => &[],
+    };
+    for param_inst in param_insts {
+        ensure!(
+            params
+                .iter()
+                .any(|ast::ParamDefElem::ParamDefElem(_, id, _, _)| *id == param_inst.id),
+            "unknown parameter {}",
+            param_inst.id
+        );
+    }
+    for ast::ParamDefElem::ParamDefElem(_, id, _, default) in params {
+        let value = match param_insts.iter().find(|p| p.id == *id) {
+            Some(param_inst) => evaluate_constants(&param_inst.param_value, inst_scope)?,
+            None => match default {
+                Some(default) => evaluate_constants(default, scope)?,
+                None => bail!("no value given for parameter {id}"),
+            },
+        };
+        scope.params.insert(id.clone(), value);
+    }
+    Ok(())
+}
+
+/// Apply a property assignment found within a component body.
+///
+/// Default assignments are recorded in the scope so that they are inherited
+/// by the components defined within it, and assignments through a property
+/// reference are collected so that they can be applied once the referenced
+/// instance has been elaborated.
+fn assign_property(
+    property_assignment: &ast::PropertyAssignment,
+    scope: &mut Scope,
+    properties: &mut HashMap<String, AssignedValue>,
+    post_assignments: &mut Vec<(Vec<String>, String, AssignedValue)>,
+) -> Result<(), anyhow::Error> {
+    match property_assignment {
+        ast::PropertyAssignment::ExplicitOrDefaultPropAssignment(
+            ast::ExplicitOrDefaultPropAssignment::ExplicitPropModifier(default_keyword, modifier),
+        ) => {
+            // Modifiers only apply to intr, and set it as well as recording
+            // themselves under their own name, which, being a keyword, cannot
+            // clash with a user-defined property.
+            ensure!(
+                modifier.id == "intr",
+                "{} can only modify intr, not {}",
+                modifier.prop_mod,
+                modifier.id
+            );
+            let property_namespace = if default_keyword.is_some() {
+                &mut scope.defaults
+            } else {
+                properties
+            };
+            let name = modifier.prop_mod.to_string();
+            if property_namespace.contains_key(&name) {
+                bail!("duplicate property modifier {name}");
+            }
+            // A modifier assigns intr too, so it cannot follow a plain
+            // assignment of intr, just as a plain assignment cannot follow it.
+            if property_namespace.contains_key("intr")
+                && !PROPERTY_MODIFIERS
+                    .iter()
+                    .any(|modifier| property_namespace.contains_key(*modifier))
+            {
+                bail!("duplicate property intr");
+            }
+            property_namespace.insert(
+                name,
+                AssignedValue::Literal(PrimaryLiteral::BooleanLiteral(true)),
+            );
+            property_namespace
+                .entry("intr".into())
+                .or_insert(AssignedValue::Literal(PrimaryLiteral::BooleanLiteral(true)));
+        }
+        ast::PropertyAssignment::ExplicitOrDefaultPropAssignment(
+            ast::ExplicitOrDefaultPropAssignment::ExplicitPropAssignment(
+                default_keyword,
+                explicit_property_assignment,
+            ),
+        ) => match explicit_property_assignment {
+            ast::ExplicitPropertyAssignment::Assignment(
+                identity_or_prop_keyword,
+                prop_assignment_rhs,
+            ) => {
+                let prop_id = property_name(identity_or_prop_keyword);
+                let value = evaluate_prop_assignment_rhs(prop_assignment_rhs.as_ref(), scope)?;
+
+                let property_namespace = if default_keyword.is_some() {
+                    &mut scope.defaults
+                } else {
+                    properties
+                };
+                if property_namespace.contains_key(&prop_id) {
+                    bail!("duplicate property {prop_id}");
+                }
+                property_namespace.insert(prop_id, value);
+            }
+            ast::ExplicitPropertyAssignment::EncodeAssignment(_) => {
+                bail!("encode is not supported")
+            }
+        },
+        ast::PropertyAssignment::PostPropAssignment(ast::PostPropAssignment::PropRef(
+            prop_ref,
+            prop_assignment_rhs,
+        )) => {
+            let path = prop_ref
+                .iref
+                .elements
+                .iter()
+                .map(|element| element.id.clone())
+                .collect();
+            let prop_id = property_name(&prop_ref.id_or_prop);
+            let value = evaluate_prop_assignment_rhs(prop_assignment_rhs.as_ref(), scope)?;
+            post_assignments.push((path, prop_id, value));
+        }
+        ast::PropertyAssignment::PostPropAssignment(
+            ast::PostPropAssignment::PostEncodeAssignment(_),
+        ) => bail!("encode is not supported"),
     }
+    Ok(())
 }
 
-fn elaborate_reg(name: String, body: &ast::ComponentBody) -> Node {
-    // TODO: Do we need to distinguish between these?
-    elaborate_addrmap(name, body)
+/// The names under which property modifiers are recorded.
+const PROPERTY_MODIFIERS: [&str; 5] = ["posedge", "negedge", "bothedge", "level", "nonsticky"];
+
+fn property_name(identity_or_prop_keyword: &ast::IdentityOrPropKeyword) -> String {
+    match identity_or_prop_keyword {
+        ast::IdentityOrPropKeyword::Id(prop_id) => prop_id.clone(),
+        ast::IdentityOrPropKeyword::PropKeyword(prop_keyword) => {
+            // treat as a string
+            format!("{prop_keyword:?}").to_lowercase()
+        }
+    }
 }
 
-fn elaborate_field(name: String, body: &ast::ComponentBody) -> Node {
-    // TODO: Do we need to distinguish between these?
-    elaborate_addrmap(name, body)
+fn evaluate_prop_assignment_rhs(
+    prop_assignment_rhs: Option<&ast::PropAssignmentRhs>,
+    scope: &Scope,
+) -> Result<AssignedValue, anyhow::Error> {
+    match prop_assignment_rhs {
+        Some(ast::PropAssignmentRhs::ConstantExpr(constant_expr)) => {
+            evaluate_assigned_value(constant_expr, scope)
+        }
+        Some(ast::PropAssignmentRhs::PrecedenceType(_)) => {
+            bail!("precedence is not supported")
+        }
+        // a bare property name sets a boolean property
+        None => Ok(AssignedValue::Literal(PrimaryLiteral::BooleanLiteral(true))),
+    }
 }
 
-/// Evaluate a constant, resolving it down to a single value.
-fn evaluate_constants(constexpr: &ast::ConstantExpr) -> ast::PrimaryLiteral {
-    match constexpr {
-        ast::ConstantExpr::ConstantPrimary(constant_primary, constant_expr_continue) => {
-            match constant_primary {
-                ast::ConstantPrimary::Base(constant_primary_base) => match constant_primary_base {
-                    ast::ConstantPrimaryBase::PrimaryLiteral(primary_literal) => {
-                        match constant_expr_continue {
-                            Some(cont) => match &**cont {
-                                ast::ConstantExprContinue::BinaryOp(
-                                    binary_op,
-                                    constant_expr,
-                                    constant_expr_continue,
-                                ) => {
-                                    let lhs = match primary_literal {
-                                        PrimaryLiteral::Number(v) => *v,
-                                        _ => panic!(
-                                            "unexpected type {primary_literal:?} on lhs of {binary_op:?}"
-                                        ),
-                                    };
-                                    let rhs = evaluate_constants(constant_expr);
-                                    let rhs = match rhs {
-                                        PrimaryLiteral::Number(v) => v,
-                                        _ => panic!(
-                                            "unexpected type {rhs:?} on rhs of {binary_op:?}"
-                                        ),
-                                    };
-
-                                    let result = match binary_op {
-                                        ast::BinaryOp::LeftShift => lhs << rhs,
-                                        binary_op => todo!("constexpr binary op {binary_op:?}"),
-                                    };
-                                    PrimaryLiteral::Number(result)
-                                }
-                                ast::ConstantExprContinue::TernaryOp(
-                                    constant_expr,
-                                    constant_expr1,
-                                    constant_expr_continue,
-                                ) => todo!(),
-                            },
-                            None => primary_literal.clone(),
-                        }
-                    }
-                    ast::ConstantPrimaryBase::ConstantConcat(constant_exprs) => todo!(),
-                    ast::ConstantPrimaryBase::ConstantMultipleConcat(
-                        constant_expr,
-                        constant_exprs,
-                    ) => todo!(),
-                    ast::ConstantPrimaryBase::ConstantExpr(constant_expr) => todo!(),
-                    ast::ConstantPrimaryBase::SimpleTypeCast(integer_type, constant_expr) => {
-                        todo!()
-                    }
-                    ast::ConstantPrimaryBase::BooleanCast(constant_expr) => todo!(),
-                    ast::ConstantPrimaryBase::InstanceOrPropRef(instance_or_prop_ref) => todo!(),
-                    ast::ConstantPrimaryBase::StructLiteral(_, struct_literal_elements) => todo!(),
-                    ast::ConstantPrimaryBase::ArrayLiteral(constant_exprs) => todo!(),
-                },
-                ast::ConstantPrimary::Cast(constant_primary_base, constant_expr) => todo!(),
+/// Evaluate the right hand side of an assignment, which is either a constant
+/// or a reference to another instance (or one of its properties).
+fn evaluate_assigned_value(
+    constexpr: &ast::ConstantExpr,
+    scope: &Scope,
+) -> Result<AssignedValue, anyhow::Error> {
+    if let ast::ConstantExpr::ConstantPrimary(
+        ast::ConstantPrimary::Base(ast::ConstantPrimaryBase::InstanceOrPropRef(
+            instance_or_prop_ref,
+        )),
+        None,
+    ) = constexpr
+    {
+        let is_param = match instance_or_prop_ref.iref.elements.as_slice() {
+            [element] => {
+                instance_or_prop_ref.id_or_prop.is_none()
+                    && scope.lookup_param(&element.id).is_some()
             }
+            _ => false,
+        };
+        if !is_param {
+            return Ok(AssignedValue::Reference(
+                instance_or_prop_ref.iref.clone(),
+                instance_or_prop_ref.id_or_prop.as_ref().map(property_name),
+            ));
         }
-        ast::ConstantExpr::UnaryOp(unary_op, constant_expr, constant_expr_continue) => todo!(),
     }
+    evaluate_constants(constexpr, scope).map(AssignedValue::Literal)
 }
 
-#[derive(Debug, Clone)]
-pub struct AddrMap {
-    name: String,
-    /// local properties, should not be propogated
-    properties: HashMap<String, PrimaryLiteral>,
-    /// properties set at this level as default, should be propogated down
-    default_properties: HashMap<String, PrimaryLiteral>,
+/// Look up a property that must have a constant value.
+fn literal<'a>(
+    properties: &'a HashMap<String, AssignedValue>,
+    name: &str,
+) -> Result<Option<&'a PrimaryLiteral>, anyhow::Error> {
+    match properties.get(name) {
+        Some(AssignedValue::Literal(value)) => Ok(Some(value)),
+        Some(AssignedValue::Reference(..)) => bail!("property {name} must be a constant"),
+        None => Ok(None),
+    }
 }
 
-#[derive(Debug, Clone)]
-pub struct Register {
-    name: String,
-    /// local properties, should not be propogated
-    properties: HashMap<String, PrimaryLiteral>,
-    /// properties set at this level as default, should be propogated down
-    default_properties: HashMap<String, PrimaryLiteral>,
+fn elaborate_addrmap(
+    nodes: &[Node],
+    children: &[NodeId],
+    inst: &ast::ComponentInst,
+    inst_scope: &Scope,
+) -> Result<AddrMap, anyhow::Error> {
+    ensure!(
+        inst.equals.is_none(),
+        "addrmap {} cannot be assigned a value",
+        inst.id
+    );
+    Ok(AddrMap {
+        offset: 0,
+        size: extent(nodes, children),
+        array: array_dimensions(inst, inst_scope)?,
+        properties: HashMap::new(),
+        default_properties: HashMap::new(),
+    })
 }
 
-#[derive(Debug, Clone)]
-pub struct Field {
-    name: String,
-    /// local properties, should not be propogated
-    properties: HashMap<String, PrimaryLiteral>,
-    /// properties set at this level as default, should be propogated down
-    default_properties: HashMap<String, PrimaryLiteral>,
+fn elaborate_regfile(
+    nodes: &[Node],
+    children: &[NodeId],
+    inst: &ast::ComponentInst,
+    inst_scope: &Scope,
+) -> Result<RegFile, anyhow::Error> {
+    ensure!(
+        inst.equals.is_none(),
+        "regfile {} cannot be assigned a value",
+        inst.id
+    );
+    Ok(RegFile {
+        offset: 0,
+        size: extent(nodes, children),
+        array: array_dimensions(inst, inst_scope)?,
+        properties: HashMap::new(),
+        default_properties: HashMap::new(),
+    })
 }
 
-#[derive(Debug, Clone)]
-pub struct Node {
-    content: NodeContent,
-    children: Vec<Node>,
+/// The number of bytes from the start of a component to the end of the last
+/// of its `children`.
+fn extent(nodes: &[Node], children: &[NodeId]) -> u64 {
+    children
+        .iter()
+        .map(|child| &nodes[child.0])
+        .map(|child| child.offset().unwrap_or_default() + child.total_size().unwrap_or_default())
+        .max()
+        .unwrap_or_default()
 }
 
-#[derive(Debug, Clone)]
-pub enum NodeContent {
-    Addrmap(AddrMap),
-    Register(Register),
-    Field(Field),
+fn elaborate_reg(
+    properties: &HashMap<String, AssignedValue>,
+    inst: &ast::ComponentInst,
+    inst_scope: &Scope,
+) -> Result<Register, anyhow::Error> {
+    ensure!(
+        inst.equals.is_none(),
+        "register {} cannot be assigned a value",
+        inst.id
+    );
+    let width = match literal(properties, "regwidth")? {
+        Some(value) => as_u64(value)?,
+        None => 32,
+    };
+    ensure!(
+        width >= 8 && width.is_power_of_two(),
+        "regwidth of {} must be a power of two of at least 8, got {width}",
+        inst.id
+    );
+
+    Ok(Register {
+        offset: 0,
+        width,
+        masks: None,
+        is_virtual: false,
+        alias_of: None,
+        array: array_dimensions(inst, inst_scope)?,
+        properties: HashMap::new(),
+        default_properties: HashMap::new(),
+    })
+}
+
+fn elaborate_field(
+    properties: &mut HashMap<String, AssignedValue>,
+    assigned: &HashSet<String>,
+    inst: &ast::ComponentInst,
+    inst_scope: &Scope,
+) -> Result<(Field, Option<u64>), anyhow::Error> {
+    let (lsb, width) = match &inst.array_or_range {
+        Some(ast::ArrayOrRange::Range(ast::Range::Range(msb, lsb))) => {
+            let msb = evaluate_number(msb, inst_scope)?;
+            let lsb = evaluate_number(lsb, inst_scope)?;
+            (Some(lsb.min(msb)), msb.abs_diff(lsb).saturating_add(1))
+        }
+        Some(ast::ArrayOrRange::Array(dims)) => {
+            let [width] = dims.as_slice() else {
+                bail!("field {} cannot be an array", inst.id);
+            };
+            (None, evaluate_number(width, inst_scope)?)
+        }
+        None => match literal(properties, "fieldwidth")? {
+            Some(width) => (None, as_u64(width)?),
+            None => (None, 1),
+        },
+    };
+    ensure!(
+        (1..=64).contains(&width),
+        "field {} has an invalid width of {width}",
+        inst.id
+    );
+    let mut assigned = assigned.clone();
+    if let Some(reset) = &inst.equals {
+        properties.insert("reset".into(), evaluate_assigned_value(reset, inst_scope)?);
+        assigned.insert("reset".into());
+    }
+
+    let field = Field {
+        // placed when the register lays out its fields
+        lsb: 0,
+        width,
+        properties: HashMap::new(),
+        default_properties: HashMap::new(),
+        assigned,
+    };
+    Ok((field, lsb))
+}
+
+fn elaborate_mem(
+    properties: &HashMap<String, AssignedValue>,
+    inst: &ast::ComponentInst,
+    inst_scope: &Scope,
+) -> Result<Mem, anyhow::Error> {
+    ensure!(
+        inst.equals.is_none(),
+        "mem {} cannot be assigned a value",
+        inst.id
+    );
+    let Some(entries) = literal(properties, "mementries")? else {
+        bail!("mem {} must have mementries", inst.id);
+    };
+    let entries = as_u64(entries)?;
+    ensure!(entries > 0, "mem {} must have at least one entry", inst.id);
+    let width = match literal(properties, "memwidth")? {
+        Some(value) => as_u64(value)?,
+        None => 32,
+    };
+    ensure!(width > 0, "memwidth of {} must be non-zero", inst.id);
+    ensure!(
+        entries.checked_mul(width.div_ceil(8)).is_some(),
+        "mem {} is too large",
+        inst.id
+    );
+
+    Ok(Mem {
+        offset: 0,
+        entries,
+        width,
+        array: array_dimensions(inst, inst_scope)?,
+        properties: HashMap::new(),
+        default_properties: HashMap::new(),
+    })
+}
+
+fn elaborate_signal(
+    properties: &HashMap<String, AssignedValue>,
+    inst: &ast::ComponentInst,
+    inst_scope: &Scope,
+) -> Result<Signal, anyhow::Error> {
+    ensure!(
+        inst.equals.is_none(),
+        "signal {} cannot be assigned a value",
+        inst.id
+    );
+    let signalwidth = literal(properties, "signalwidth")?
+        .map(as_u64)
+        .transpose()?;
+    let width = match &inst.array_or_range {
+        Some(ast::ArrayOrRange::Array(dims)) => {
+            let [width] = dims.as_slice() else {
+                bail!("signal {} cannot be an array", inst.id);
+            };
+            let width = evaluate_number(width, inst_scope)?;
+            if let Some(signalwidth) = signalwidth {
+                ensure!(
+                    width == signalwidth,
+                    "signal {} is {width} bits wide, but has signalwidth = {signalwidth}",
+                    inst.id
+                );
+            }
+            width
+        }
+        Some(ast::ArrayOrRange::Range(_)) => {
+            bail!("signal {} cannot be declared with a bit range", inst.id)
+        }
+        None => signalwidth.unwrap_or(1),
+    };
+    ensure!(width > 0, "signal {} has an invalid width of 0", inst.id);
+    let flag = |name| -> Result<bool, anyhow::Error> {
+        Ok(literal(properties, name)?
+            .map(as_bool)
+            .transpose()?
+            .unwrap_or(false))
+    };
+    ensure!(
+        !(flag("sync")? && flag("async")?),
+        "signal {} cannot be both sync and async",
+        inst.id
+    );
+    ensure!(
+        !(flag("activehigh")? && flag("activelow")?),
+        "signal {} cannot be both activehigh and activelow",
+        inst.id
+    );
+
+    Ok(Signal {
+        width,
+        properties: HashMap::new(),
+        default_properties: HashMap::new(),
+    })
+}
+
+fn array_dimensions(
+    inst: &ast::ComponentInst,
+    scope: &Scope,
+) -> Result<Option<Array>, anyhow::Error> {
+    match &inst.array_or_range {
+        None => Ok(None),
+        Some(ast::ArrayOrRange::Array(dims)) => {
+            let dims = dims
+                .iter()
+                .map(|dim| evaluate_number(dim, scope))
+                .collect::<Result<Vec<_>, _>>()?;
+            ensure!(
+                !dims.contains(&0),
+                "array dimensions of {} must be non-zero",
+                inst.id
+            );
+            ensure!(
+                dims.iter()
+                    .try_fold(1u64, |len, dim| len.checked_mul(*dim))
+                    .is_some(),
+                "array {} has too many elements",
+                inst.id
+            );
+            Ok(Some(Array { dims, stride: 0 }))
+        }
+        Some(ast::ArrayOrRange::Range(_)) => {
+            bail!("{} cannot be declared with a bit range", inst.id)
+        }
+    }
+}
+
+pub(crate) fn as_u64(value: &PrimaryLiteral) -> Result<u64, anyhow::Error> {
+    match value {
+        PrimaryLiteral::Number(v) => Ok(*v),
+        PrimaryLiteral::Bits(bits) => Ok(bits.val()),
+        _ => bail!("expected a number, got {value:?}"),
+    }
+}
+
+pub(crate) fn as_bool(value: &PrimaryLiteral) -> Result<bool, anyhow::Error> {
+    match value {
+        PrimaryLiteral::BooleanLiteral(v) => Ok(*v),
+        PrimaryLiteral::Number(v) => Ok(*v != 0),
+        PrimaryLiteral::Bits(bits) => Ok(bits.val() != 0),
+        _ => bail!("expected a boolean, got {value:?}"),
+    }
+}
+
+fn evaluate_number(constexpr: &ast::ConstantExpr, scope: &Scope) -> Result<u64, anyhow::Error> {
+    as_u64(&evaluate_constants(constexpr, scope)?)
+}
+
+/// Evaluate a constant, resolving it down to a single value.
+fn evaluate_constants(
+    constexpr: &ast::ConstantExpr,
+    scope: &Scope,
+) -> Result<PrimaryLiteral, anyhow::Error> {
+    match constexpr {
+        ast::ConstantExpr::ConstantPrimary(constant_primary, constant_expr_continue) => {
+            let value = match constant_primary {
+                ast::ConstantPrimary::Base(constant_primary_base) => {
+                    evaluate_constant_primary_base(constant_primary_base, scope)?
+                }
+                ast::ConstantPrimary::Cast(width, constant_expr) => {
+                    // a width cast, e.g. `4'(x)`
+                    let width = as_u64(&evaluate_constant_primary_base(width, scope)?)?;
+                    ensure!(
+                        (1..=64).contains(&width),
+                        "cast width must be from 1 to 64, got {width}"
+                    );
+                    let value = as_u64(&evaluate_constants(constant_expr, scope)?)?;
+                    PrimaryLiteral::Bits(crate::Bits::new(width, value))
+                }
+            };
+            evaluate_constant_expr_continue(value, constant_expr_continue.as_deref(), scope)
+        }
+        ast::ConstantExpr::UnaryOp(unary_op, constant_expr, constant_expr_continue) => {
+            let value = evaluate_constants(constant_expr, scope)?;
+            let value = match (unary_op, value) {
+                (ast::UnaryOp::And, PrimaryLiteral::Bits(bits)) => PrimaryLiteral::Bits(
+                    crate::Bits::new(1, (bits == crate::Bits::new(bits.w(), u64::MAX)).into()),
+                ),
+                (ast::UnaryOp::Or, PrimaryLiteral::Bits(bits)) => {
+                    PrimaryLiteral::Bits(crate::Bits::new(1, (bits.val() != 0).into()))
+                }
+                (unary_op, value) => {
+                    bail!("unsupported unary operator {unary_op:?} on {value:?}")
+                }
+            };
+            evaluate_constant_expr_continue(value, constant_expr_continue.as_deref(), scope)
+        }
+    }
+}
+
+fn evaluate_constant_primary_base(
+    constant_primary_base: &ast::ConstantPrimaryBase,
+    scope: &Scope,
+) -> Result<PrimaryLiteral, anyhow::Error> {
+    match constant_primary_base {
+        ast::ConstantPrimaryBase::PrimaryLiteral(primary_literal) => Ok(primary_literal.clone()),
+        ast::ConstantPrimaryBase::ConstantConcat(constant_exprs) => {
+            evaluate_concat(constant_exprs, 1, scope)
+        }
+        ast::ConstantPrimaryBase::ConstantMultipleConcat(count, constant_exprs) => {
+            let count = as_u64(&evaluate_constants(count, scope)?)?;
+            evaluate_concat(constant_exprs, count, scope)
+        }
+        ast::ConstantPrimaryBase::ConstantExpr(constant_expr) => {
+            evaluate_constants(constant_expr, scope)
+        }
+        ast::ConstantPrimaryBase::SimpleTypeCast(integer_type, constant_expr) => {
+            let value = as_u64(&evaluate_constants(constant_expr, scope)?)?;
+            Ok(match integer_type {
+                ast::IntegerType::Bit => PrimaryLiteral::Bits(crate::Bits::new(1, value)),
+                ast::IntegerType::Longint => PrimaryLiteral::Number(value),
+            })
+        }
+        ast::ConstantPrimaryBase::BooleanCast(constant_expr) => Ok(PrimaryLiteral::BooleanLiteral(
+            as_bool(&evaluate_constants(constant_expr, scope)?)?,
+        )),
+        ast::ConstantPrimaryBase::InstanceOrPropRef(instance_or_prop_ref) => {
+            if let (None, [element]) = (
+                &instance_or_prop_ref.id_or_prop,
+                instance_or_prop_ref.iref.elements.as_slice(),
+            ) && element.arrays.is_empty()
+                && let Some(value) = scope.lookup_param(&element.id)
+            {
+                return Ok(value.clone());
+            }
+            bail!("instance references cannot be used within an expression")
+        }
+        ast::ConstantPrimaryBase::StructLiteral(..) => bail!("struct literals are not supported"),
+        ast::ConstantPrimaryBase::ArrayLiteral(_) => bail!("array literals are not supported"),
+    }
+}
+
+/// Concatenate sized values, the first being the most significant, `count`
+/// times over.
+fn evaluate_concat(
+    constant_exprs: &[ast::ConstantExpr],
+    count: u64,
+    scope: &Scope,
+) -> Result<PrimaryLiteral, anyhow::Error> {
+    let mut parts = Vec::new();
+    for constant_expr in constant_exprs {
+        match evaluate_constants(constant_expr, scope)? {
+            PrimaryLiteral::Bits(bits) => parts.push(bits),
+            other => bail!("only sized values can be concatenated, got {other}"),
+        }
+    }
+    let mut width = 0u64;
+    let mut value = 0u64;
+    for _ in 0..count {
+        for bits in &parts {
+            width += bits.w();
+            ensure!(width <= 64, "concatenation is wider than 64 bits");
+            value = value.checked_shl(bits.w() as u32).unwrap_or_default() | bits.val();
+        }
+    }
+    ensure!(width > 0, "concatenation is empty");
+    Ok(PrimaryLiteral::Bits(crate::Bits::new(width, value)))
+}
+
+fn evaluate_constant_expr_continue(
+    lhs: PrimaryLiteral,
+    constant_expr_continue: Option<&ast::ConstantExprContinue>,
+    scope: &Scope,
+) -> Result<PrimaryLiteral, anyhow::Error> {
+    let Some(cont) = constant_expr_continue else {
+        return Ok(lhs);
+    };
+    let (result, constant_expr_continue) = match cont {
+        ast::ConstantExprContinue::BinaryOp(binary_op, constant_expr, constant_expr_continue) => {
+            let rhs = evaluate_constants(constant_expr, scope)?;
+            (
+                evaluate_binary_op(binary_op, lhs, rhs)?,
+                constant_expr_continue,
+            )
+        }
+        ast::ConstantExprContinue::TernaryOp(
+            constant_expr,
+            constant_expr1,
+            constant_expr_continue,
+        ) => {
+            let result = if as_bool(&lhs)? {
+                evaluate_constants(constant_expr, scope)?
+            } else {
+                evaluate_constants(constant_expr1, scope)?
+            };
+            (result, constant_expr_continue)
+        }
+    };
+    evaluate_constant_expr_continue(result, constant_expr_continue.as_deref(), scope)
+}
+
+fn evaluate_binary_op(
+    binary_op: &ast::BinaryOp,
+    lhs: PrimaryLiteral,
+    rhs: PrimaryLiteral,
+) -> Result<PrimaryLiteral, anyhow::Error> {
+    let result = match binary_op {
+        ast::BinaryOp::EqualsEquals => PrimaryLiteral::BooleanLiteral(lhs == rhs),
+        ast::BinaryOp::NotEquals => PrimaryLiteral::BooleanLiteral(lhs != rhs),
+        ast::BinaryOp::AndAnd => PrimaryLiteral::BooleanLiteral(as_bool(&lhs)? && as_bool(&rhs)?),
+        ast::BinaryOp::OrOr => PrimaryLiteral::BooleanLiteral(as_bool(&lhs)? || as_bool(&rhs)?),
+        ast::BinaryOp::LessThan
+        | ast::BinaryOp::GreaterThan
+        | ast::BinaryOp::LessThanOrEqual
+        | ast::BinaryOp::GreaterThanOrEqual => {
+            let (l, r) = (as_u64(&lhs)?, as_u64(&rhs)?);
+            PrimaryLiteral::BooleanLiteral(match binary_op {
+                ast::BinaryOp::LessThan => l < r,
+                ast::BinaryOp::GreaterThan => l > r,
+                ast::BinaryOp::LessThanOrEqual => l <= r,
+                _ => l >= r,
+            })
+        }
+        binary_op => {
+            let (l, r) = (as_u64(&lhs)?, as_u64(&rhs)?);
+            let result = match binary_op {
+                ast::BinaryOp::LeftShift => l.checked_shl(r.try_into()?).unwrap_or_default(),
+                ast::BinaryOp::RightShift => l.checked_shr(r.try_into()?).unwrap_or_default(),
+                ast::BinaryOp::And => l & r,
+                ast::BinaryOp::Or => l | r,
+                ast::BinaryOp::Xor => l ^ r,
+                ast::BinaryOp::Xnor => !(l ^ r),
+                ast::BinaryOp::Times => l.wrapping_mul(r),
+                ast::BinaryOp::Divide => l
+                    .checked_div(r)
+                    .ok_or_else(|| anyhow::format_err!("division by zero"))?,
+                ast::BinaryOp::Modulus => l
+                    .checked_rem(r)
+                    .ok_or_else(|| anyhow::format_err!("division by zero"))?,
+                ast::BinaryOp::Plus => l.wrapping_add(r),
+                ast::BinaryOp::Minus => l.wrapping_sub(r),
+                ast::BinaryOp::Power => l.wrapping_pow(r.try_into()?),
+                _ => unreachable!("handled above"),
+            };
+            match (lhs, rhs) {
+                (PrimaryLiteral::Bits(a), PrimaryLiteral::Bits(b)) => {
+                    PrimaryLiteral::Bits(crate::Bits::new(a.w().max(b.w()), result))
+                }
+                (PrimaryLiteral::Bits(a), _) | (_, PrimaryLiteral::Bits(a)) => {
+                    PrimaryLiteral::Bits(crate::Bits::new(a.w(), result))
+                }
+                _ => PrimaryLiteral::Number(result),
+            }
+        }
+    };
+    Ok(result)
+}
+
+/// A component definition that one or more nodes were instantiated from.
+#[derive(Debug, Clone)]
+pub struct TypeDef {
+    name: String,
+    component_type: ast::ComponentType,
+    def: ast::ComponentDef,
+}
+
+impl TypeDef {
+    /// The name the component was defined with.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn component_type(&self) -> ast::ComponentType {
+        self.component_type
+    }
+
+    /// The definition as it appears in the AST.
+    pub fn definition(&self) -> &ast::ComponentDef {
+        &self.def
+    }
+}
+
+/// The value of a property of an elaborated node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Literal(PrimaryLiteral),
+    /// A reference to another node, e.g. `resetsignal = rst_n;`
+    Reference(NodeId),
+    /// A reference to a property of another node, e.g. `next = cnt->incr;`
+    PropertyReference(NodeId, String),
+}
+
+impl PropertyValue {
+    pub fn as_literal(&self) -> Option<&PrimaryLiteral> {
+        match self {
+            PropertyValue::Literal(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The node this value refers to, if it is a reference.
+    pub fn as_reference(&self) -> Option<NodeId> {
+        match self {
+            PropertyValue::Reference(id) | PropertyValue::PropertyReference(id, _) => Some(*id),
+            PropertyValue::Literal(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AddrMap {
+    offset: u64,
+    size: u64,
+    array: Option<Array>,
+    /// local properties, should not be propogated
+    properties: HashMap<String, PropertyValue>,
+    /// properties set at this level as default, should be propogated down
+    default_properties: HashMap<String, PropertyValue>,
+}
+
+impl AddrMap {
+    /// Byte offset of the (first element of the) address map within its parent.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Size of a single element of the address map in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RegFile {
+    offset: u64,
+    size: u64,
+    array: Option<Array>,
+    /// local properties, should not be propogated
+    properties: HashMap<String, PropertyValue>,
+    /// properties set at this level as default, should be propogated down
+    default_properties: HashMap<String, PropertyValue>,
+}
+
+impl RegFile {
+    /// Byte offset of the register file within its parent.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Size of a single element of the register file in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Register {
+    offset: u64,
+    width: u64,
+    masks: Option<RegisterMasks>,
+    is_virtual: bool,
+    alias_of: Option<NodeId>,
+    array: Option<Array>,
+    /// local properties, should not be propogated
+    properties: HashMap<String, PropertyValue>,
+    /// properties set at this level as default, should be propogated down
+    default_properties: HashMap<String, PropertyValue>,
+}
+
+impl Register {
+    /// Byte offset of the (first element of the) register within its parent.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Width of the register in bits.
+    pub fn width(&self) -> u64 {
+        self.width
+    }
+
+    /// Size of a single element of the register in bytes.
+    pub fn size(&self) -> u64 {
+        self.width / 8
+    }
+
+    /// Whether the register is a virtual register: one instantiated within a
+    /// mem, which describes the layout of its entries.
+    pub fn is_virtual(&self) -> bool {
+        self.is_virtual
+    }
+
+    /// The primary register, if this register is an alias of it: another
+    /// view, at its own address, of the same storage.
+    pub fn alias_of(&self) -> Option<NodeId> {
+        self.alias_of
+    }
+
+    /// Values combined from the fields of the register, or `None` for
+    /// registers wider than 64 bits.
+    pub fn masks(&self) -> Option<&RegisterMasks> {
+        self.masks.as_ref()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Mem {
+    offset: u64,
+    entries: u64,
+    width: u64,
+    array: Option<Array>,
+    /// local properties, should not be propogated
+    properties: HashMap<String, PropertyValue>,
+    /// properties set at this level as default, should be propogated down
+    default_properties: HashMap<String, PropertyValue>,
+}
+
+impl Mem {
+    /// Byte offset of the mem within its parent.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Number of entries in the mem, given by `mementries`.
+    pub fn entries(&self) -> u64 {
+        self.entries
+    }
+
+    /// Width of each entry in bits, given by `memwidth`.
+    pub fn width(&self) -> u64 {
+        self.width
+    }
+
+    /// Size of a single element of the mem in bytes, with each entry taking
+    /// a whole number of bytes.
+    pub fn size(&self) -> u64 {
+        self.entries * self.width.div_ceil(8)
+    }
+
+    /// The software access of the mem: `r`, `w` or `rw`, which is the
+    /// default.
+    pub fn sw(&self) -> Result<ast::AccessType, anyhow::Error> {
+        use ast::AccessType::*;
+        match self.properties.get("sw") {
+            Some(PropertyValue::Literal(PrimaryLiteral::AccessTypeLiteral(sw @ (R | W | Rw)))) => {
+                Ok(*sw)
+            }
+            Some(PropertyValue::Literal(PrimaryLiteral::AccessTypeLiteral(sw))) => {
+                bail!("sw of a mem must be r, w or rw, got {sw}")
+            }
+            Some(other) => bail!("sw must be an access type, got {other:?}"),
+            None => Ok(Rw),
+        }
+    }
+}
+
+/// Values of a register combined from its fields, each as wide as the
+/// register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterMasks {
+    /// The reset value of every field with a constant reset, and 0 elsewhere,
+    /// including in fields reset to the value of another field or a signal.
+    pub reset: Bits,
+    /// The bits of fields that have a reset, whether to a constant or to the
+    /// value of another field or a signal.
+    pub reset_mask: Bits,
+    /// The bits of fields that software can read.
+    pub readable: Bits,
+    /// The bits of fields that software can write.
+    pub writable: Bits,
+    /// The bits of fields whose value can change other than by a software
+    /// write: those hardware can write, counters, and those with side effects
+    /// on read.
+    pub volatile: Bits,
+    /// The bits not occupied by any field.
+    pub reserved: Bits,
+}
+
+impl RegisterMasks {
+    /// Fields with an invalid `sw`, `hw` or `reset` are left out of the masks
+    /// they affect, as are fields that do not fit in the register;
+    /// [`validate`](crate::validate) reports them.
+    fn new(width: u64, fields: &[&Field]) -> Self {
+        use ast::AccessType::*;
+        let mut masks = [0u64; 5];
+        let [reset, reset_mask, readable, writable, volatile] = &mut masks;
+        let mut used = 0;
+        for field in fields {
+            if field.lsb >= width || field.width > width - field.lsb {
+                continue;
+            }
+            let mask = Bits::new(field.width, u64::MAX).val() << field.lsb;
+            used |= mask;
+            match field.properties.get("reset") {
+                Some(PropertyValue::Literal(value)) => {
+                    if let Ok(value) = as_u64(value) {
+                        *reset |= (value << field.lsb) & mask;
+                        *reset_mask |= mask;
+                    }
+                }
+                // a reference gives a reset value that is not constant
+                Some(PropertyValue::Reference(_) | PropertyValue::PropertyReference(..)) => {
+                    *reset_mask |= mask;
+                }
+                None => {}
+            }
+            let sw = field.sw();
+            if matches!(sw, Ok(R | Rw | Rw1)) {
+                *readable |= mask;
+            }
+            if matches!(sw, Ok(W | Rw | W1 | Rw1)) {
+                *writable |= mask;
+            }
+            let side_effect = field.sw_access_policy().is_ok_and(|policy| {
+                policy.read_action().is_some() || policy == SwAccessPolicy::User
+            });
+            if matches!(field.hw(), Ok(W | Rw))
+                || side_effect
+                || ["hwset", "hwclr", "counter"]
+                    .iter()
+                    .any(|p| field.is_set(p))
+            {
+                *volatile |= mask;
+            }
+        }
+        let bits = |value| Bits::new(width, value);
+        RegisterMasks {
+            reset: bits(*reset),
+            reset_mask: bits(*reset_mask),
+            readable: bits(*readable),
+            writable: bits(*writable),
+            volatile: bits(*volatile),
+            reserved: bits(!used),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+    lsb: u64,
+    width: u64,
+    /// local properties, should not be propogated
+    properties: HashMap<String, PropertyValue>,
+    /// properties set at this level as default, should be propogated down
+    default_properties: HashMap<String, PropertyValue>,
+    /// names of the properties assigned to the field itself, rather than
+    /// inherited from a default
+    assigned: HashSet<String>,
+}
+
+impl Field {
+    /// Index of the least significant bit of the field within its register.
+    pub fn lsb(&self) -> u64 {
+        self.lsb
+    }
+
+    /// Index of the most significant bit of the field within its register.
+    pub fn msb(&self) -> u64 {
+        // layout keeps the msb within range, even for a field ending at bit 2^64-1
+        self.lsb + (self.width - 1)
+    }
+
+    /// Width of the field in bits.
+    pub fn width(&self) -> u64 {
+        self.width
+    }
+
+    /// The software access of the field, `rw` unless assigned.
+    pub fn sw(&self) -> Result<ast::AccessType, anyhow::Error> {
+        self.access("sw")
+    }
+
+    /// The hardware access of the field, `rw` unless assigned.
+    pub fn hw(&self) -> Result<ast::AccessType, anyhow::Error> {
+        match self.access("hw")? {
+            ast::AccessType::Rw1 | ast::AccessType::W1 => bail!("hw cannot be rw1 or w1"),
+            hw => Ok(hw),
+        }
+    }
+
+    pub(crate) fn property(&self, name: &str) -> Option<&PropertyValue> {
+        self.properties.get(name)
+    }
+
+    fn access(&self, name: &str) -> Result<ast::AccessType, anyhow::Error> {
+        match self.properties.get(name) {
+            Some(PropertyValue::Literal(PrimaryLiteral::AccessTypeLiteral(access))) => Ok(*access),
+            Some(other) => bail!("{name} must be an access type, got {other:?}"),
+            None => Ok(ast::AccessType::Rw),
+        }
+    }
+
+    /// Whether a boolean property is set, or a property with any other type
+    /// of value is assigned.
+    pub(crate) fn is_set(&self, name: &str) -> bool {
+        is_set(&self.properties, name)
+    }
+
+    /// Like [`Field::is_set`], but only for a property assigned to the field
+    /// itself, not one inherited from a default in an enclosing scope.
+    pub(crate) fn is_assigned(&self, name: &str) -> bool {
+        self.assigned.contains(name) && self.is_set(name)
+    }
+
+    /// How the field is implemented in hardware, as determined by its `sw`
+    /// and `hw` access (SystemRDL 2.0 section 9.4.1, Table 12).
+    ///
+    /// Side effects on read, hardware set and clear, counters and interrupts
+    /// all need the value to be held, so imply storage whatever the access.
+    /// Only the software side effects need software to have access; hardware
+    /// alone can use storage that software cannot see.
+    ///
+    /// Fails for combinations that the spec does not allow, such as a field
+    /// that neither software nor hardware can read, and for properties that
+    /// have no meaning for the field's implementation, such as a reset value
+    /// on a wire. Only the reset assigned to the field itself counts, not a
+    /// default inherited from an enclosing scope.
+    pub fn implementation(&self) -> Result<FieldImplementation, anyhow::Error> {
+        use ast::AccessType::*;
+        let (sw, hw) = (self.sw()?, self.hw()?);
+        let implementation = match (sw, hw) {
+            (Rw | Rw1, _) | (_, Rw) => FieldImplementation::Storage,
+            (W | W1, R) => FieldImplementation::Storage,
+            (R, W) => FieldImplementation::Wire,
+            (R | Na, R) | (R, Na) => FieldImplementation::Constant,
+            (W | W1 | Na, W | Na) => {
+                bail!("sw = {sw} and hw = {hw} leave the field with no reader")
+            }
+            (_, Rw1 | W1) => unreachable!("rejected by Field::hw"),
+        };
+        const SW_STORAGE_PROPERTIES: [&str; 6] =
+            ["onread", "rclr", "rset", "onwrite", "woclr", "woset"];
+        const HW_STORAGE_PROPERTIES: [&str; 6] = ["hwset", "hwclr", "counter", "intr", "we", "wel"];
+        if let Some(property) = SW_STORAGE_PROPERTIES.iter().find(|p| self.is_set(p)) {
+            ensure!(sw != Na, "{property} needs software access, but sw = na");
+            return Ok(FieldImplementation::Storage);
+        }
+        if HW_STORAGE_PROPERTIES.iter().any(|p| self.is_set(p)) {
+            return Ok(FieldImplementation::Storage);
+        }
+        match implementation {
+            FieldImplementation::Wire => {
+                for property in ["reset", "resetsignal"] {
+                    ensure!(
+                        !self.assigned.contains(property),
+                        "a wire (sw = r, hw = w) cannot have a {property}"
+                    );
+                }
+            }
+            FieldImplementation::Constant => ensure!(
+                self.assigned.contains("reset"),
+                "a constant (sw = {sw}, hw = {hw}) must have a reset value"
+            ),
+            FieldImplementation::Storage => {}
+        }
+        Ok(implementation)
+    }
+}
+
+impl Field {
+    /// The access policy that software sees, from `sw` and the side effects
+    /// given by `onread` and `onwrite` or their shorthands `rclr`, `rset`,
+    /// `woclr` and `woset`.
+    ///
+    /// `singlepulse` does not change the policy, since it is hardware that
+    /// clears the field again.
+    pub fn sw_access_policy(&self) -> Result<SwAccessPolicy, anyhow::Error> {
+        let onread = self.side_effect(
+            "onread",
+            |value| match value {
+                PrimaryLiteral::OnReadTypeLiteral(onread) => Some(*onread),
+                _ => None,
+            },
+            &[
+                ("rclr", ast::OnReadType::RClr),
+                ("rset", ast::OnReadType::RSet),
+            ],
+        )?;
+        let onwrite = self.side_effect(
+            "onwrite",
+            |value| match value {
+                PrimaryLiteral::OnWriteTypeLiteral(onwrite) => Some(*onwrite),
+                _ => None,
+            },
+            &[
+                ("woclr", ast::OnWriteType::WoClr),
+                ("woset", ast::OnWriteType::WoSet),
+            ],
+        )?;
+        crate::access::sw_access_policy(self.sw()?, onread, onwrite)
+    }
+
+    /// The value of `onread` or `onwrite`, which can also be set with one of
+    /// the boolean `shorthands`.
+    fn side_effect<T: Copy + Eq + std::fmt::Display>(
+        &self,
+        name: &str,
+        value: impl Fn(&PrimaryLiteral) -> Option<T>,
+        shorthands: &[(&str, T)],
+    ) -> Result<Option<T>, anyhow::Error> {
+        let mut result = match self.properties.get(name) {
+            Some(PropertyValue::Literal(literal)) => match value(literal) {
+                Some(value) => Some((name, value)),
+                None => bail!("invalid value for {name}: {literal}"),
+            },
+            Some(other) => bail!("{name} must be a constant, got {other:?}"),
+            None => None,
+        };
+        for (shorthand, value) in shorthands {
+            if !self.is_set(shorthand) {
+                continue;
+            }
+            if let Some((first, other)) = result
+                && other != *value
+            {
+                bail!("{shorthand} conflicts with {first} = {other}");
+            }
+            result = Some((shorthand, *value));
+        }
+        Ok(result.map(|(_, value)| value))
+    }
+}
+
+/// Whether a boolean property is set, or a property with any other type of
+/// value is assigned.
+fn is_set(properties: &HashMap<String, PropertyValue>, name: &str) -> bool {
+    match properties.get(name) {
+        Some(PropertyValue::Literal(value)) => as_bool(value).unwrap_or(true),
+        Some(_) => true,
+        None => false,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Signal {
+    width: u64,
+    /// local properties, should not be propogated
+    properties: HashMap<String, PropertyValue>,
+    /// properties set at this level as default, should be propogated down
+    default_properties: HashMap<String, PropertyValue>,
+}
+
+impl Signal {
+    /// Width of the signal in bits.
+    pub fn width(&self) -> u64 {
+        self.width
+    }
+
+    /// Whether the signal is `async`; signals are synchronous by default.
+    pub fn is_async(&self) -> bool {
+        is_set(&self.properties, "async")
+    }
+
+    /// Whether the signal is `activelow`; signals are active high by default.
+    pub fn is_active_low(&self) -> bool {
+        is_set(&self.properties, "activelow")
+    }
+
+    /// Whether the signal is the default reset of the fields in its parent's
+    /// subtree that have no `resetsignal`.
+    pub fn is_field_reset(&self) -> bool {
+        is_set(&self.properties, "field_reset")
+    }
+
+    /// Whether the signal is the reset of the CPU interface of its parent.
+    pub fn is_cpuif_reset(&self) -> bool {
+        is_set(&self.properties, "cpuif_reset")
+    }
+}
+
+/// How a field is implemented in hardware; see [`Field::implementation`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FieldImplementation {
+    /// A flip-flop that holds the value written by software or hardware.
+    Storage,
+    /// A value driven by hardware and sampled when software reads it.
+    Wire,
+    /// A fixed value, given by the reset value.
+    Constant,
+}
+
+/// The dimensions of an instance array.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Array {
+    dims: Vec<u64>,
+    stride: u64,
+}
+
+impl Array {
+    /// Size of each dimension, outermost first.
+    pub fn dims(&self) -> &[u64] {
+        &self.dims
+    }
+
+    /// Distance in bytes between the start of consecutive elements.
+    pub fn stride(&self) -> u64 {
+        self.stride
+    }
+
+    /// Total number of elements in the array.
+    pub fn len(&self) -> u64 {
+        self.dims.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Split a flat element index into an index per dimension.
+    pub(crate) fn unflatten(&self, mut index: u64) -> Vec<u64> {
+        let mut indices = vec![0; self.dims.len()];
+        for (i, dim) in self.dims.iter().enumerate().rev() {
+            indices[i] = index % dim;
+            index /= dim;
+        }
+        indices
+    }
+
+    /// Combine an index per dimension into a flat element index.
+    fn flatten(&self, indices: &[u64]) -> Option<u64> {
+        if indices.len() != self.dims.len() {
+            return None;
+        }
+        let mut index = 0;
+        for (i, dim) in indices.iter().zip(&self.dims) {
+            if i >= dim {
+                return None;
+            }
+            index = index * dim + i;
+        }
+        Some(index)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    id: NodeId,
+    name: String,
+    type_id: Option<TypeId>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    content: NodeContent,
+    inst_type: Option<ast::ComponentInstType>,
+    external: bool,
+    location: Option<SourceLocation>,
+}
+
+#[derive(Debug, Clone)]
+pub enum NodeContent {
+    Addrmap(AddrMap),
+    RegFile(RegFile),
+    Register(Register),
+    Field(Field),
+    Signal(Signal),
+    Mem(Mem),
+}
+
+impl NodeContent {
+    /// The type of component the node is an instance of.
+    pub fn component_type(&self) -> ast::ComponentType {
+        match self {
+            NodeContent::Addrmap(_) => ast::ComponentType::AddrMap,
+            NodeContent::RegFile(_) => ast::ComponentType::RegFile,
+            NodeContent::Register(_) => ast::ComponentType::Reg,
+            NodeContent::Field(_) => ast::ComponentType::Field,
+            NodeContent::Signal(_) => ast::ComponentType::Signal,
+            NodeContent::Mem(_) => ast::ComponentType::Mem,
+        }
+    }
+
+    fn properties(&self) -> &HashMap<String, PropertyValue> {
+        match self {
+            NodeContent::Addrmap(addrmap) => &addrmap.properties,
+            NodeContent::RegFile(regfile) => &regfile.properties,
+            NodeContent::Register(register) => &register.properties,
+            NodeContent::Field(field) => &field.properties,
+            NodeContent::Signal(signal) => &signal.properties,
+            NodeContent::Mem(mem) => &mem.properties,
+        }
+    }
+
+    fn properties_mut(&mut self) -> &mut HashMap<String, PropertyValue> {
+        match self {
+            NodeContent::Addrmap(addrmap) => &mut addrmap.properties,
+            NodeContent::RegFile(regfile) => &mut regfile.properties,
+            NodeContent::Register(register) => &mut register.properties,
+            NodeContent::Field(field) => &mut field.properties,
+            NodeContent::Signal(signal) => &mut signal.properties,
+            NodeContent::Mem(mem) => &mut mem.properties,
+        }
+    }
+
+    fn default_properties(&self) -> &HashMap<String, PropertyValue> {
+        match self {
+            NodeContent::Addrmap(addrmap) => &addrmap.default_properties,
+            NodeContent::RegFile(regfile) => &regfile.default_properties,
+            NodeContent::Register(register) => &register.default_properties,
+            NodeContent::Field(field) => &field.default_properties,
+            NodeContent::Signal(signal) => &signal.default_properties,
+            NodeContent::Mem(mem) => &mem.default_properties,
+        }
+    }
+
+    fn default_properties_mut(&mut self) -> &mut HashMap<String, PropertyValue> {
+        match self {
+            NodeContent::Addrmap(addrmap) => &mut addrmap.default_properties,
+            NodeContent::RegFile(regfile) => &mut regfile.default_properties,
+            NodeContent::Register(register) => &mut register.default_properties,
+            NodeContent::Field(field) => &mut field.default_properties,
+            NodeContent::Signal(signal) => &mut signal.default_properties,
+            NodeContent::Mem(mem) => &mut mem.default_properties,
+        }
+    }
+}
+
+impl Node {
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// The instance name of this node.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The component definition this node was instantiated from, or `None`
+    /// if it was defined anonymously.
+    pub fn type_id(&self) -> Option<TypeId> {
+        self.type_id
+    }
+
+    /// The node this node was instantiated within, or `None` for top-level
+    /// nodes.
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    /// The components instantiated directly within this node.
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    pub fn content(&self) -> &NodeContent {
+        &self.content
+    }
+
+    /// Where the node was instantiated in the source, or `None` for a root
+    /// addrmap that was only defined.
+    pub fn location(&self) -> Option<&SourceLocation> {
+        self.location.as_ref()
+    }
+
+    /// Whether the node was instantiated as `external` or `internal`, or
+    /// `None` if neither was given.
+    pub fn inst_type(&self) -> Option<ast::ComponentInstType> {
+        self.inst_type
+    }
+
+    /// Whether the node is implemented outside the block that decodes its
+    /// parent: if it was instantiated as `external`, is a mem, or is within
+    /// an external node.
+    pub fn is_external(&self) -> bool {
+        self.external
+    }
+
+    /// The value of a property of this node, either assigned directly or
+    /// inherited from a default assignment in an enclosing scope.
+    pub fn property(&self, name: &str) -> Option<&PropertyValue> {
+        self.content.properties().get(name)
+    }
+
+    /// All properties of this node.
+    pub fn properties(&self) -> &HashMap<String, PropertyValue> {
+        self.content.properties()
+    }
+
+    /// The default property assignments made within this node, which apply
+    /// to the components defined inside it.
+    pub fn default_properties(&self) -> &HashMap<String, PropertyValue> {
+        self.content.default_properties()
+    }
+
+    /// The array dimensions of this node, if it was instantiated as an array.
+    pub fn array(&self) -> Option<&Array> {
+        match &self.content {
+            NodeContent::Addrmap(addrmap) => addrmap.array.as_ref(),
+            NodeContent::RegFile(regfile) => regfile.array.as_ref(),
+            NodeContent::Register(register) => register.array.as_ref(),
+            NodeContent::Mem(mem) => mem.array.as_ref(),
+            NodeContent::Field(_) | NodeContent::Signal(_) => None,
+        }
+    }
+
+    fn array_mut(&mut self) -> Option<&mut Array> {
+        match &mut self.content {
+            NodeContent::Addrmap(addrmap) => addrmap.array.as_mut(),
+            NodeContent::RegFile(regfile) => regfile.array.as_mut(),
+            NodeContent::Register(register) => register.array.as_mut(),
+            NodeContent::Mem(mem) => mem.array.as_mut(),
+            NodeContent::Field(_) | NodeContent::Signal(_) => None,
+        }
+    }
+
+    /// Byte offset of this node within its parent, or `None` for nodes that
+    /// do not occupy address space.
+    pub fn offset(&self) -> Option<u64> {
+        match &self.content {
+            NodeContent::Addrmap(addrmap) => Some(addrmap.offset),
+            NodeContent::RegFile(regfile) => Some(regfile.offset),
+            NodeContent::Register(register) => Some(register.offset),
+            NodeContent::Mem(mem) => Some(mem.offset),
+            NodeContent::Field(_) | NodeContent::Signal(_) => None,
+        }
+    }
+
+    fn set_offset(&mut self, offset: u64) {
+        match &mut self.content {
+            NodeContent::Addrmap(addrmap) => addrmap.offset = offset,
+            NodeContent::RegFile(regfile) => regfile.offset = offset,
+            NodeContent::Register(register) => register.offset = offset,
+            NodeContent::Mem(mem) => mem.offset = offset,
+            NodeContent::Field(_) | NodeContent::Signal(_) => {}
+        }
+    }
+
+    /// Size in bytes of a single element of this node.
+    pub fn size(&self) -> Option<u64> {
+        match &self.content {
+            NodeContent::Addrmap(addrmap) => Some(addrmap.size),
+            NodeContent::RegFile(regfile) => Some(regfile.size),
+            NodeContent::Register(register) => Some(register.size()),
+            NodeContent::Mem(mem) => Some(mem.size()),
+            NodeContent::Field(_) | NodeContent::Signal(_) => None,
+        }
+    }
+
+    /// Size in bytes of this node, including all elements if it is an array.
+    pub fn total_size(&self) -> Option<u64> {
+        let size = self.size()?;
+        match self.array() {
+            Some(array) if !array.is_empty() => {
+                array.stride.checked_mul(array.len() - 1)?.checked_add(size)
+            }
+            Some(_) => Some(0),
+            None => Some(size),
+        }
+    }
+
+    /// The address just past the node, relative to its parent, which
+    /// elaboration ensures fits in 64 bits.
+    fn end(&self) -> Result<u64, anyhow::Error> {
+        self.total_size()
+            .and_then(|size| size.checked_add(self.offset().unwrap_or_default()))
+            .ok_or_else(|| anyhow::format_err!("{} does not fit in the address space", self.name))
+    }
+}
+
+/// The result of looking up an address in a [`RootNamespace`].
+#[derive(Debug, Clone)]
+pub struct AddressMatch<'a> {
+    /// The register occupying the address.
+    pub register: &'a Node,
+    /// Absolute address of the (array element of the) register.
+    pub address: u64,
+    /// Hierarchical path to the register, including array indices.
+    pub path: String,
+    /// The fields of the register that occupy the addressed byte.
+    pub fields: Vec<&'a Node>,
+}
+
+impl Index<NodeId> for RootNamespace {
+    type Output = Node;
+
+    fn index(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+}
+
+impl Index<TypeId> for RootNamespace {
+    type Output = TypeDef;
+
+    fn index(&self, id: TypeId) -> &TypeDef {
+        &self.types[id.0]
+    }
+}
+
+impl RootNamespace {
+    /// The top-level nodes.
+    pub fn roots(&self) -> impl Iterator<Item = &Node> {
+        self.roots.iter().map(|&id| &self[id])
+    }
+
+    /// Every node in the namespace, in no particular order.
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.iter()
+    }
+
+    /// Every node in the namespace, in depth-first order.
+    pub fn descendants(&self) -> impl Iterator<Item = &Node> {
+        self.roots.iter().flat_map(|&id| self.subtree(id))
+    }
+
+    /// The node `id` and all of its descendants, in depth-first order.
+    pub fn subtree(&self, id: NodeId) -> impl Iterator<Item = &Node> {
+        let mut stack = vec![id];
+        std::iter::from_fn(move || {
+            let node = &self[stack.pop()?];
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+
+    /// The components instantiated directly within the node `id`.
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item = &Node> {
+        self[id].children.iter().map(|&child| &self[child])
+    }
+
+    /// Find a direct child of the node `id` by its instance name.
+    pub fn child(&self, id: NodeId, name: &str) -> Option<&Node> {
+        self.children(id).find(|child| child.name == name)
+    }
+
+    /// The node that `id` was instantiated within, or `None` for top-level
+    /// nodes.
+    pub fn parent(&self, id: NodeId) -> Option<&Node> {
+        Some(&self[self[id].parent?])
+    }
+
+    /// The nodes enclosing `id`, innermost first.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = &Node> {
+        std::iter::successors(self.parent(id), |node| self.parent(node.id))
+    }
+
+    /// The component definition the node `id` was instantiated from.
+    pub fn type_of(&self, id: NodeId) -> Option<&TypeDef> {
+        Some(&self[self[id].type_id?])
+    }
+
+    /// The hierarchical path of the node `id`, e.g. `top.uart0.CTRL.EN`.
+    pub fn path(&self, id: NodeId) -> String {
+        let mut path: Vec<&str> = self.ancestors(id).map(Node::name).collect();
+        path.reverse();
+        path.push(self[id].name());
+        path.join(".")
+    }
+
+    /// Find a node by its hierarchical path, e.g. `top.uart0.CTRL.EN`.
+    ///
+    /// Elements of arrays may be given with an index, e.g. `top.regs[2].EN`,
+    /// which must be in range; without an index the path names the array as
+    /// a whole.
+    pub fn find(&self, path: &str) -> Option<&Node> {
+        let mut candidates = self.roots.as_slice();
+        let mut found = None;
+        for element in path.split('.') {
+            let (name, indices) = parse_path_element(element)?;
+            let node = candidates
+                .iter()
+                .map(|&id| &self[id])
+                .find(|node| node.name == name)?;
+            if !indices.is_empty() {
+                node.array()?.flatten(&indices)?;
+            }
+            candidates = &node.children;
+            found = Some(node);
+        }
+        found
+    }
+
+    /// The alias registers of a primary register.
+    pub fn aliases(&self, id: NodeId) -> impl Iterator<Item = &Node> {
+        let siblings = match self[id].parent {
+            Some(parent) => self[parent].children(),
+            None => &[],
+        };
+        siblings.iter().map(|sibling| &self[*sibling]).filter(move |node| {
+            matches!(&node.content, NodeContent::Register(register) if register.alias_of == Some(id))
+        })
+    }
+
+    /// The signal that resets a field: its `resetsignal` if it has one, and
+    /// otherwise the `field_reset` signal of its nearest ancestor that has
+    /// one. `None` if the field is reset by the implied default reset.
+    pub fn reset_signal(&self, field: NodeId) -> Option<NodeId> {
+        match self[field].property("resetsignal") {
+            Some(PropertyValue::Reference(id)) => Some(*id),
+            _ => self.nearest_signal(field, Signal::is_field_reset),
+        }
+    }
+
+    /// The `cpuif_reset` signal of a node or of its nearest ancestor that has
+    /// one, which resets the CPU interface the node is accessed through.
+    pub fn cpuif_reset(&self, id: NodeId) -> Option<NodeId> {
+        std::iter::once(&self[id])
+            .chain(self.ancestors(id))
+            .find_map(|node| self.signal_child(node.id(), Signal::is_cpuif_reset))
+    }
+
+    fn nearest_signal(&self, id: NodeId, predicate: fn(&Signal) -> bool) -> Option<NodeId> {
+        self.ancestors(id)
+            .find_map(|node| self.signal_child(node.id(), predicate))
+    }
+
+    fn signal_child(&self, id: NodeId, predicate: fn(&Signal) -> bool) -> Option<NodeId> {
+        self.children(id).find_map(|child| match child.content() {
+            NodeContent::Signal(signal) if predicate(signal) => Some(child.id()),
+            _ => None,
+        })
+    }
+
+    /// Find the register occupying `address`, along with the fields that
+    /// occupy the addressed byte.
+    pub fn find_by_address(&self, address: u64) -> Option<AddressMatch<'_>> {
+        self.roots()
+            .find_map(|node| self.find_address_in(node, 0, address, ""))
+    }
+
+    fn find_address_in<'a>(
+        &'a self,
+        node: &'a Node,
+        base: u64,
+        address: u64,
+        path: &str,
+    ) -> Option<AddressMatch<'a>> {
+        let start = base + node.offset()?;
+        let size = node.size()?;
+        if address < start || address >= start + node.total_size()? {
+            return None;
+        }
+
+        let mut path = if path.is_empty() {
+            node.name.clone()
+        } else {
+            format!("{path}.{}", node.name)
+        };
+        let mut element_start = start;
+        if let Some(array) = node.array() {
+            let index = (address - start).checked_div(array.stride)?;
+            element_start = start + index * array.stride;
+            for i in array.unflatten(index) {
+                path += &format!("[{i}]");
+            }
+        }
+        if address >= element_start + size {
+            // in the gap between two array elements
+            return None;
+        }
+
+        match &node.content {
+            NodeContent::Register(_) => {
+                let byte = address - element_start;
+                let fields = self
+                    .children(node.id)
+                    .filter(|child| match &child.content {
+                        NodeContent::Field(field) => {
+                            field.lsb() < (byte + 1) * 8 && field.msb() >= byte * 8
+                        }
+                        _ => false,
+                    })
+                    .collect();
+                Some(AddressMatch {
+                    register: node,
+                    address: element_start,
+                    path,
+                    fields,
+                })
+            }
+            _ => self
+                .children(node.id)
+                .find_map(|child| self.find_address_in(child, element_start, address, &path)),
+        }
+    }
+}
+
+fn parse_path_element(element: &str) -> Option<(&str, Vec<u64>)> {
+    let (name, mut rest) = match element.find('[') {
+        Some(i) => element.split_at(i),
+        None => (element, ""),
+    };
+    let mut indices = Vec::new();
+    while !rest.is_empty() {
+        let end = rest.find(']')?;
+        indices.push(rest.strip_prefix('[')?[..end - 1].trim().parse().ok()?);
+        rest = &rest[end + 1..];
+    }
+    Some((name, indices))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn elaborate_str(input: &str) -> RootNamespace {
+        elaborate(crate::parse(input).unwrap()).unwrap()
+    }
+
+    fn literal(value: PrimaryLiteral) -> Option<PropertyValue> {
+        Some(PropertyValue::Literal(value))
+    }
+
+    const UART: &str = r#"
+        reg ctrl_t {
+            field { sw = rw; hw = r; } EN = 0;
+            field { sw = rw; hw = r; } MODE[3:1] = 2;
+            field { sw = r; hw = w; } BUSY[8];
+        };
+        addrmap uart_t {
+            ctrl_t CTRL;
+            reg { regwidth = 64; field {} DATA[64]; } DATA;
+            reg { field {} STATUS; } STATUS @ 0x20;
+        };
+        addrmap {
+            uart_t uart0;
+            uart_t uart1 @ 0x100;
+            reg { field {} VALUE[16:16]; } regs[4] @ 0x200 += 0x10;
+        } top;
+    "#;
+
+    #[test]
+    fn test_top_level_nodes() {
+        let root = elaborate_str(UART);
+        let names: Vec<_> = root.roots().map(Node::name).collect();
+        assert_eq!(names, ["top"]);
+        let top = root.roots().next().unwrap();
+        let children: Vec<_> = root.children(top.id()).map(Node::name).collect();
+        assert_eq!(children, ["uart0", "uart1", "regs"]);
+        let uart0 = root.child(top.id(), "uart0").unwrap();
+        assert_eq!(root.type_of(uart0.id()).unwrap().name(), "uart_t");
+        assert!(root.type_of(top.id()).is_none());
+    }
+
+    #[test]
+    fn test_find_and_path() {
+        let root = elaborate_str(UART);
+        let en = root.find("top.uart0.CTRL.EN").unwrap();
+        assert_eq!(root.path(en.id()), "top.uart0.CTRL.EN");
+        assert_eq!(root.parent(en.id()).unwrap().name(), "CTRL");
+        let ancestors: Vec<_> = root.ancestors(en.id()).map(Node::name).collect();
+        assert_eq!(ancestors, ["CTRL", "uart0", "top"]);
+        assert_eq!(
+            en.property("reset"),
+            literal(PrimaryLiteral::Number(0)).as_ref()
+        );
+
+        let NodeContent::Field(mode) = root.find("top.uart1.CTRL.MODE").unwrap().content() else {
+            panic!("expected a field");
+        };
+        assert_eq!((mode.lsb(), mode.msb()), (1, 3));
+        let NodeContent::Field(busy) = root.find("top.uart1.CTRL.BUSY").unwrap().content() else {
+            panic!("expected a field");
+        };
+        assert_eq!((busy.lsb(), busy.width()), (4, 8));
+
+        assert!(root.find("top.regs[3].VALUE").is_some());
+        assert!(root.find("top.regs[4]").is_none());
+        assert!(root.find("top.uart2").is_none());
+    }
+
+    #[test]
+    fn test_shared_types() {
+        let root = elaborate_str(UART);
+        let uart0 = root.find("top.uart0.CTRL").unwrap();
+        let uart1 = root.find("top.uart1.CTRL").unwrap();
+        assert_ne!(uart0.id(), uart1.id());
+        assert_eq!(uart0.type_id(), uart1.type_id());
+        assert_eq!(root.descendants().count(), root.nodes().count());
+    }
+
+    #[test]
+    fn test_addresses() {
+        let root = elaborate_str(UART);
+        let uart0 = root.find("top.uart0").unwrap();
+        assert_eq!(uart0.offset(), Some(0));
+        assert_eq!(uart0.size(), Some(0x24));
+        assert_eq!(root.find("top.uart0.DATA").unwrap().offset(), Some(8));
+        assert_eq!(root.find("top.uart1").unwrap().offset(), Some(0x100));
+
+        let m = root.find_by_address(0x120).unwrap();
+        assert_eq!(m.path, "top.uart1.STATUS");
+        assert_eq!(m.address, 0x120);
+
+        let m = root.find_by_address(0x101).unwrap();
+        assert_eq!(m.path, "top.uart1.CTRL");
+        let fields: Vec<_> = m.fields.iter().map(|f| f.name()).collect();
+        assert_eq!(fields, ["BUSY"]);
+
+        let m = root.find_by_address(0x232).unwrap();
+        assert_eq!(m.path, "top.regs[3]");
+        assert_eq!(m.address, 0x230);
+        assert_eq!(m.fields.len(), 1);
+
+        // between array elements
+        assert!(root.find_by_address(0x204).is_none());
+        assert!(root.find_by_address(0x1000).is_none());
+    }
+
+    #[test]
+    fn test_address_overflow() {
+        let error = |input: &str| {
+            elaborate(crate::parse(input).unwrap())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("addrmap { reg { field {} f; } r0 @ 0xFFFFFFFFFFFFFFFC; } top;"),
+            "r0 does not fit in the address space"
+        );
+        assert_eq!(
+            error("addrmap { reg { field {} f; } r0[0x4000000000000000]; } top;"),
+            "r0 does not fit in the address space"
+        );
+        assert_eq!(
+            error("addrmap { reg { field {} f; } r0[0x10000][0x10000][0x10000][0x10000]; } top;"),
+            "array r0 has too many elements"
+        );
+        assert_eq!(
+            error("addrmap { reg { field {} f; } r0[4] += 0; } top;"),
+            "stride of r0 must be non-zero"
+        );
+        assert_eq!(
+            error("addrmap { mem { mementries = 0x8000000000000000; } m; } top;"),
+            "mem m is too large"
+        );
+        assert_eq!(
+            error("addrmap { reg { field {} f; } r0; } top @ 0xFFFFFFFFFFFFFFFE;"),
+            "top does not fit in the address space"
+        );
+    }
+
+    #[test]
+    fn test_field_overflow() {
+        // a field may take the last bit position, where validation reports it
+        let root = elaborate_str(
+            "addrmap { reg { field {} a[0xFFFFFFFFFFFFFFFF:0xFFFFFFFFFFFFFFFF]; } r0; } top;",
+        );
+        let NodeContent::Field(a) = root.find("top.r0.a").unwrap().content() else {
+            panic!("expected a field");
+        };
+        assert_eq!((a.lsb(), a.msb()), (u64::MAX, u64::MAX));
+        assert_eq!(crate::validate(&root).len(), 1);
+
+        let error = |input: &str| {
+            elaborate(crate::parse(input).unwrap())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error(
+                "addrmap { reg { field {} a[0xFFFFFFFFFFFFFFFF:0xFFFFFFFFFFFFFFF0]; field {} b; } r0; } top;"
+            ),
+            "field b does not fit in the register"
+        );
+        assert_eq!(
+            error("addrmap { reg { field {} a[0:0xFFFFFFFFFFFFFFFF]; } r0; } top;"),
+            "field a has an invalid width of 18446744073709551615"
+        );
+    }
+
+    #[test]
+    fn test_defaults_and_params() {
+        let root = elaborate_str(
+            r#"
+            addrmap {
+                default regwidth = 16;
+                reg r_t #(longint unsigned WIDTH = 4) {
+                    field {} F[WIDTH];
+                };
+                r_t a;
+                r_t #(.WIDTH(8)) b;
+                b.F->reset = 1;
+            } top;
+            "#,
+        );
+        let a = root.find("top.a").unwrap();
+        assert_eq!(a.size(), Some(2));
+        let NodeContent::Field(f) = root.find("top.a.F").unwrap().content() else {
+            panic!("expected a field");
+        };
+        assert_eq!(f.width(), 4);
+        let b_f = root.find("top.b.F").unwrap();
+        let NodeContent::Field(f) = b_f.content() else {
+            panic!("expected a field");
+        };
+        assert_eq!(f.width(), 8);
+        assert_eq!(
+            b_f.property("reset"),
+            literal(PrimaryLiteral::Number(1)).as_ref()
+        );
+        assert_eq!(root.find("top.b").unwrap().offset(), Some(2));
+    }
+
+    #[test]
+    fn test_top_selection() {
+        const INPUT: &str = r#"
+            addrmap block_t #(longint unsigned WIDTH = 4) {
+                reg { field {} DATA[WIDTH]; } DATA;
+            };
+            addrmap {
+                block_t #(.WIDTH(2)) blk;
+            } chip;
+            addrmap test_t {
+                block_t blk;
+            };
+        "#;
+        let elaborate = |top: Option<&str>, params: &[(&str, &str)]| {
+            let options = ElaborateOptions {
+                top: top.map(String::from),
+                params: params
+                    .iter()
+                    .map(|(id, value)| (id.to_string(), value.parse().unwrap()))
+                    .collect(),
+            };
+            elaborate_with_options(crate::parse(INPUT).unwrap(), &options)
+        };
+        let width = |root: &RootNamespace, path: &str| match root.find(path).unwrap().content() {
+            NodeContent::Field(field) => field.width(),
+            _ => panic!("expected a field"),
+        };
+
+        let root = elaborate(None, &[]).unwrap();
+        let names: Vec<_> = root.roots().map(Node::name).collect();
+        assert_eq!(names, ["test_t"]);
+        assert_eq!(width(&root, "test_t.blk.DATA.DATA"), 4);
+
+        let root = elaborate(Some("chip"), &[]).unwrap();
+        assert_eq!(width(&root, "chip.blk.DATA.DATA"), 2);
+
+        let root = elaborate(Some("block_t"), &[("WIDTH", "8")]).unwrap();
+        assert_eq!(root.roots().count(), 1);
+        assert_eq!(width(&root, "block_t.DATA.DATA"), 8);
+
+        let err = elaborate(Some("nope"), &[]).unwrap_err();
+        assert_eq!(err.to_string(), "no root addrmap named nope");
+        let err = elaborate(Some("chip"), &[("DEPTH", "1")]).unwrap_err();
+        assert_eq!(err.to_string(), "unknown parameter DEPTH");
+    }
+
+    #[test]
+    fn test_constant_expressions() {
+        let root = elaborate_str(
+            r#"
+            addrmap {
+                reg {
+                    field {} a[8] = {4'h3, 4'ha};
+                    field {} b[4] = {2{2'b10}};
+                    field {} c = bit'(3);
+                    field {} d[8] = longint'(3);
+                } r0;
+            } top;
+            "#,
+        );
+        let reset = |path: &str| root.find(path).unwrap().property("reset").cloned();
+        assert_eq!(
+            reset("top.r0.a"),
+            literal(PrimaryLiteral::Bits(Bits::new(8, 0x3a)))
+        );
+        assert_eq!(
+            reset("top.r0.b"),
+            literal(PrimaryLiteral::Bits(Bits::new(4, 0b1010)))
+        );
+        assert_eq!(
+            reset("top.r0.c"),
+            literal(PrimaryLiteral::Bits(Bits::new(1, 1)))
+        );
+        assert_eq!(reset("top.r0.d"), literal(PrimaryLiteral::Number(3)));
+
+        let binary = |op: ast::BinaryOp, l: u64, r: u64| {
+            evaluate_binary_op(&op, PrimaryLiteral::Number(l), PrimaryLiteral::Number(r))
+                .map_err(|e| e.to_string())
+        };
+        assert_eq!(
+            binary(ast::BinaryOp::Minus, 15, 2),
+            Ok(PrimaryLiteral::Number(13))
+        );
+        assert_eq!(
+            binary(ast::BinaryOp::LessThanOrEqual, 2, 3),
+            Ok(PrimaryLiteral::BooleanLiteral(true))
+        );
+        assert_eq!(
+            binary(ast::BinaryOp::Power, 2, 10),
+            Ok(PrimaryLiteral::Number(1024))
+        );
+        assert_eq!(
+            binary(ast::BinaryOp::Modulus, 1, 0),
+            Err("division by zero".into())
+        );
+
+        let error = |input: &str| {
+            elaborate(crate::parse(input).unwrap())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("addrmap { reg { field {} f[8] = {1, 2}; } r0; } top;"),
+            "only sized values can be concatenated, got 1"
+        );
+        assert_eq!(
+            error("addrmap { reg { field { precedence = hw; } f; } r0; } top;"),
+            "precedence is not supported"
+        );
+    }
+
+    #[test]
+    fn test_type_definitions() {
+        const INPUT: &str = r#"
+            enum mode_t { A; B; };
+            property my_udp { type = boolean; component = reg; };
+            addrmap {
+                enum state_t { IDLE; BUSY; };
+                reg { my_udp; field {} f; } r0;
+            } top;
+        "#;
+        let root = elaborate_str(INPUT);
+        assert!(root.find("top.r0.f").is_some());
+        let root =
+            elaborate_with_options(crate::parse(INPUT).unwrap(), &Default::default()).unwrap();
+        assert!(root.find("top.r0.f").is_some());
+    }
+
+    #[test]
+    fn test_references() {
+        let root = elaborate_str(
+            r#"
+            addrmap {
+                reg {
+                    field {} rst;
+                    field {} incr;
+                } ctrl;
+                reg {
+                    field { resetsignal = ctrl.rst; } count[8];
+                } cnt;
+                cnt.count->next = ctrl.incr->anded;
+            } top;
+            "#,
+        );
+        let rst = root.find("top.ctrl.rst").unwrap();
+        let incr = root.find("top.ctrl.incr").unwrap();
+        let count = root.find("top.cnt.count").unwrap();
+        assert_eq!(
+            count.property("resetsignal"),
+            Some(&PropertyValue::Reference(rst.id()))
+        );
+        assert_eq!(
+            count.property("next"),
+            Some(&PropertyValue::PropertyReference(incr.id(), "anded".into()))
+        );
+
+        let err = elaborate(
+            crate::parse("addrmap { reg { field { resetsignal = nope; } EN; } CTRL; } top;")
+                .unwrap(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("nope"), "{err}");
+    }
+
+    #[test]
+    fn test_field_implementation() {
+        let root = elaborate_str(
+            r#"
+            addrmap {
+                reg {
+                    field {} plain;
+                    field { sw = r; hw = w; } status;
+                    field { sw = r; hw = r; } version = 3;
+                    field { sw = w; hw = r; } cmd;
+                    field { sw = r; hw = w; rclr; } event;
+                    field { sw = r; hw = r; } no_reset;
+                    field { sw = w; hw = w; } unread;
+                    field { sw = r; hw = w; resetsignal = plain; } wire_reset;
+                    field { hw = w1; } bad_hw;
+                    field { sw = na; hw = rw; we; } hw_we;
+                    field { sw = na; hw = r; hwset; } hw_set;
+                    field { sw = na; hw = r; rclr; } na_rclr;
+                } ctrl;
+                regfile {
+                    default reset = 0;
+                    reg {
+                        field { sw = r; hw = w; } status;
+                        field { sw = r; hw = r; } no_reset;
+                        field { sw = r; hw = r; } version = 1;
+                    } regs;
+                } block;
+            } top;
+            "#,
+        );
+        let implementation = |name: &str| {
+            let path = if name.contains('.') {
+                format!("top.{name}")
+            } else {
+                format!("top.ctrl.{name}")
+            };
+            let node = root.find(&path).unwrap();
+            let NodeContent::Field(field) = node.content() else {
+                panic!("expected a field");
+            };
+            field.implementation().map_err(|e| e.to_string())
+        };
+        assert_eq!(implementation("plain"), Ok(FieldImplementation::Storage));
+        assert_eq!(implementation("status"), Ok(FieldImplementation::Wire));
+        assert_eq!(implementation("version"), Ok(FieldImplementation::Constant));
+        assert_eq!(implementation("cmd"), Ok(FieldImplementation::Storage));
+        assert_eq!(implementation("event"), Ok(FieldImplementation::Storage));
+        assert_eq!(
+            implementation("no_reset"),
+            Err("a constant (sw = r, hw = r) must have a reset value".into())
+        );
+        assert_eq!(
+            implementation("unread"),
+            Err("sw = w and hw = w leave the field with no reader".into())
+        );
+        assert_eq!(
+            implementation("wire_reset"),
+            Err("a wire (sw = r, hw = w) cannot have a resetsignal".into())
+        );
+        assert_eq!(
+            implementation("bad_hw"),
+            Err("hw cannot be rw1 or w1".into())
+        );
+        assert_eq!(implementation("hw_we"), Ok(FieldImplementation::Storage));
+        assert_eq!(implementation("hw_set"), Ok(FieldImplementation::Storage));
+        assert_eq!(
+            implementation("na_rclr"),
+            Err("rclr needs software access, but sw = na".into())
+        );
+
+        // a default reset neither makes a wire invalid nor a constant valid
+        assert_eq!(
+            implementation("block.regs.status"),
+            Ok(FieldImplementation::Wire)
+        );
+        assert_eq!(
+            implementation("block.regs.no_reset"),
+            Err("a constant (sw = r, hw = r) must have a reset value".into())
+        );
+        assert_eq!(
+            implementation("block.regs.version"),
+            Ok(FieldImplementation::Constant)
+        );
+    }
+
+    #[test]
+    fn test_sw_access_policy() {
+        let root = elaborate_str(
+            r#"
+            addrmap {
+                reg {
+                    field {} plain;
+                    field { sw = r; rclr; } status;
+                    field { woclr; } pending;
+                    field { onread = rset; onwrite = wzc; } mixed;
+                    field { sw = w; singlepulse; } start;
+                    field { rclr; onread = rset; } conflict;
+                } ctrl;
+            } top;
+            "#,
+        );
+        let policy = |name: &str| {
+            let node = root.find(&format!("top.ctrl.{name}")).unwrap();
+            let NodeContent::Field(field) = node.content() else {
+                panic!("expected a field");
+            };
+            field.sw_access_policy().map_err(|e| e.to_string())
+        };
+        assert_eq!(policy("plain"), Ok(SwAccessPolicy::Rw));
+        assert_eq!(policy("status"), Ok(SwAccessPolicy::Rc));
+        assert_eq!(policy("pending"), Ok(SwAccessPolicy::W1c));
+        assert_eq!(policy("mixed"), Ok(SwAccessPolicy::W0crs));
+        assert_eq!(policy("start"), Ok(SwAccessPolicy::Wo));
+        assert_eq!(
+            policy("conflict"),
+            Err("rclr conflicts with onread = rset".into())
+        );
+    }
+
+    #[test]
+    fn test_register_masks() {
+        let root = elaborate_str(
+            r#"
+            addrmap {
+                reg {
+                    regwidth = 16;
+                    field { sw = rw; hw = r; } EN = 1;
+                    field { sw = r; hw = w; } STATUS[3:2];
+                    field { sw = w; hw = r; } CMD[7:4] = 4'ha;
+                    field { sw = r; hw = r; rclr; } EVENT[8:8] = 0;
+                    field { sw = rw; hw = r; } LOADED[11:10];
+                } ctrl;
+                ctrl.EN->reset = 0;
+                ctrl.LOADED->reset = ctrl.STATUS;
+            } top;
+            "#,
+        );
+        let NodeContent::Register(ctrl) = root.find("top.ctrl").unwrap().content() else {
+            panic!("expected a register");
+        };
+        let masks = ctrl.masks().unwrap();
+        let bits = |value| Bits::new(16, value);
+        assert_eq!(masks.reset, bits(0x00a0));
+        assert_eq!(masks.reset_mask, bits(0x0df1));
+        assert_eq!(masks.readable, bits(0x0d0d));
+        assert_eq!(masks.writable, bits(0x0cf1));
+        assert_eq!(masks.volatile, bits(0x010c));
+        assert_eq!(masks.reserved, bits(0xf202));
+
+        // fields beyond the register are left for validation to report
+        let root = elaborate_str(
+            r#"
+            addrmap {
+                reg { field {} f[70:70]; field {} g[0:0] = 1; } narrow;
+                reg { regwidth = 64; field {} a[64] = 0; field {} b = 1; } wide;
+            } top;
+            "#,
+        );
+        let masks = |path: &str| match root.find(path).unwrap().content() {
+            NodeContent::Register(register) => *register.masks().unwrap(),
+            _ => panic!("expected a register"),
+        };
+        let narrow = masks("top.narrow");
+        assert_eq!(narrow.reset, Bits::new(32, 1));
+        assert_eq!(narrow.reserved, Bits::new(32, 0xffff_fffe));
+        let wide = masks("top.wide");
+        assert_eq!(wide.reset_mask, Bits::new(64, u64::MAX));
+        assert_eq!(wide.reserved, Bits::new(64, 0));
+        assert_eq!(crate::validate(&root).len(), 2);
+    }
+
+    #[test]
+    fn test_signals() {
+        let root = elaborate_str(
+            r#"
+            addrmap {
+                signal { async; activelow; field_reset; } rst_n;
+                signal { cpuif_reset; } bus_rst;
+                signal { signalwidth = 4; } irq_bus[4];
+                reg {
+                    signal { sync; } soft_rst;
+                    field {} plain;
+                    field { resetsignal = soft_rst; } other;
+                } ctrl @ 0x8;
+                reg { field {} d; } data;
+            } top;
+            "#,
+        );
+        let signal = |path: &str| match root.find(path).unwrap().content() {
+            NodeContent::Signal(signal) => signal.clone(),
+            _ => panic!("{path} is not a signal"),
+        };
+        let rst_n = signal("top.rst_n");
+        assert!(rst_n.is_async());
+        assert!(rst_n.is_active_low());
+        assert!(rst_n.is_field_reset());
+        assert_eq!(rst_n.width(), 1);
+        assert_eq!(signal("top.irq_bus").width(), 4);
+        assert!(!signal("top.ctrl.soft_rst").is_async());
+
+        // signals take up no address space
+        let top = root.find("top").unwrap();
+        assert_eq!(root.find("top.rst_n").unwrap().offset(), None);
+        assert_eq!(root.find("top.data").unwrap().offset(), Some(0xc));
+        let NodeContent::Field(other) = root.find("top.ctrl.other").unwrap().content() else {
+            panic!("expected a field");
+        };
+        assert_eq!(other.lsb(), 1);
+
+        let id = |path: &str| root.find(path).unwrap().id();
+        assert_eq!(
+            root.reset_signal(id("top.ctrl.plain")),
+            Some(id("top.rst_n"))
+        );
+        assert_eq!(
+            root.reset_signal(id("top.ctrl.other")),
+            Some(id("top.ctrl.soft_rst"))
+        );
+        assert_eq!(root.cpuif_reset(id("top.data")), Some(id("top.bus_rst")));
+        assert_eq!(root.cpuif_reset(top.id()), Some(id("top.bus_rst")));
+
+        let error = |input: &str| {
+            elaborate(crate::parse(input).unwrap())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("addrmap { signal { sync; async; } s; } top;"),
+            "signal s cannot be both sync and async"
+        );
+        assert_eq!(
+            error("addrmap { signal { signalwidth = 2; } s[4]; } top;"),
+            "signal s is 4 bits wide, but has signalwidth = 2"
+        );
+        assert_eq!(
+            error("addrmap { signal {} s @ 0x4; } top;"),
+            "signal s cannot be given an address"
+        );
+    }
+
+    #[test]
+    fn test_mem() {
+        let root = elaborate_str(
+            r#"
+            addrmap {
+                reg { field {} a; } ctrl;
+                external mem {
+                    mementries = 16;
+                    memwidth = 32;
+                    sw = r;
+                    reg { field { sw = r; } entry[8]; } vregs[4];
+                } table;
+                mem { mementries = 5; memwidth = 12; } buffer;
+            } top;
+            "#,
+        );
+        let NodeContent::Mem(table) = root.find("top.table").unwrap().content() else {
+            panic!("expected a mem");
+        };
+        assert_eq!(table.entries(), 16);
+        assert_eq!(table.width(), 32);
+        assert_eq!(table.size(), 0x40);
+        assert_eq!(table.sw().unwrap(), ast::AccessType::R);
+        assert_eq!(root.find("top.table").unwrap().offset(), Some(0x40));
+        let NodeContent::Register(vregs) = root.find("top.table.vregs").unwrap().content() else {
+            panic!("expected a register");
+        };
+        assert!(vregs.is_virtual());
+        let NodeContent::Register(ctrl) = root.find("top.ctrl").unwrap().content() else {
+            panic!("expected a register");
+        };
+        assert!(!ctrl.is_virtual());
+        assert_eq!(root.find("top.buffer").unwrap().size(), Some(10));
+
+        let error = |input: &str| {
+            elaborate(crate::parse(input).unwrap())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("addrmap { internal mem { mementries = 4; } m; } top;"),
+            "mem m is always external, so cannot be internal"
+        );
+        assert_eq!(
+            error("addrmap { mem { memwidth = 8; } m; } top;"),
+            "mem m must have mementries"
+        );
+        assert_eq!(
+            error("addrmap { mem { mementries = 4; addrmap {} inner; } m; } top;"),
+            "addrmap inner cannot be instantiated within mem m"
+        );
+    }
+
+    #[test]
+    fn test_regfile() {
+        let root = elaborate_str(
+            r#"
+            addrmap {
+                default sw = r;
+                default regwidth = 16;
+                regfile {
+                    reg { field {} a; } ctrl;
+                    regfile { reg { field {} b; } status; } inner[2];
+                } block @ 0x10;
+                reg { field {} c; } after;
+            } top;
+            "#,
+        );
+        let block = root.find("top.block").unwrap();
+        let NodeContent::RegFile(regfile) = block.content() else {
+            panic!("expected a regfile");
+        };
+        assert_eq!(regfile.offset(), 0x10);
+        assert_eq!(regfile.size(), 0x6);
+        assert_eq!(root.find("top.block.inner").unwrap().offset(), Some(0x2));
+        assert_eq!(root.find("top.after").unwrap().offset(), Some(0x16));
+        let address = root.find_by_address(0x14).unwrap();
+        assert_eq!(address.register.name(), "status");
+
+        // defaults only reach the components that have the property
+        assert_eq!(block.property("sw"), None);
+        assert_eq!(block.property("regwidth"), None);
+        let NodeContent::Register(ctrl) = root.find("top.block.ctrl").unwrap().content() else {
+            panic!("expected a register");
+        };
+        assert_eq!(ctrl.width(), 16);
+        assert_eq!(root.find("top.block.ctrl").unwrap().property("sw"), None);
+        assert_eq!(
+            root.find("top.block.ctrl.a").unwrap().property("sw"),
+            literal(PrimaryLiteral::AccessTypeLiteral(ast::AccessType::R)).as_ref()
+        );
+
+        let error = |input: &str| {
+            elaborate(crate::parse(input).unwrap())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("addrmap { regfile { field {} f; } rf; } top;"),
+            "field f cannot be instantiated within regfile rf"
+        );
+        assert_eq!(
+            error("addrmap { regfile { addrmap {} inner; } rf; } top;"),
+            "addrmap inner cannot be instantiated within regfile rf"
+        );
+        assert_eq!(
+            error("addrmap { reg { reg { field {} f; } inner; } r0; } top;"),
+            "reg inner cannot be instantiated within reg r0"
+        );
+        assert_eq!(
+            error("field {} f;"),
+            "field f cannot be instantiated at the root"
+        );
+        assert_eq!(
+            error("reg { field {} f; } r0;"),
+            "reg r0 cannot be instantiated at the root"
+        );
+        assert_eq!(
+            error("regfile rf_t { reg { field {} f; } r0; }; rf_t rf;"),
+            "regfile rf cannot be instantiated at the root"
+        );
+        assert_eq!(
+            error("addrmap { regfile { regwidth = 16; } rf; } top;"),
+            "regwidth cannot be assigned to regfile rf"
+        );
+        assert_eq!(
+            error("addrmap { reg { field {} f; } r0; r0->sw = r; } top;"),
+            "sw cannot be assigned to reg r0"
+        );
+    }
+
+    #[test]
+    fn test_external() {
+        let root = elaborate_str(
+            r#"
+            addrmap {
+                reg { field {} a; } ctrl;
+                external regfile { reg { field {} b; } status; } ext;
+                addrmap {
+                    external reg { field {} c; } e;
+                    internal reg { field {} d; } i;
+                } block;
+                mem { mementries = 4; reg { field {} v; } vreg; } table;
+            } top;
+            "#,
+        );
+        let external = |path: &str| root.find(path).unwrap().is_external();
+        assert!(!external("top"));
+        assert!(!external("top.ctrl"));
+        assert!(external("top.ext"));
+        assert!(external("top.ext.status"));
+        assert!(external("top.ext.status.b"));
+        assert!(!external("top.block"));
+        assert!(external("top.block.e"));
+        assert!(!external("top.block.i"));
+        assert!(external("top.table.vreg"));
+        assert_eq!(
+            root.find("top.block.i").unwrap().inst_type(),
+            Some(ast::ComponentInstType::Internal)
+        );
+
+        let error = |input: &str| {
+            elaborate(crate::parse(input).unwrap())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("addrmap { external regfile { internal reg { field {} f; } r0; } rf; } top;"),
+            "r0 cannot be internal, since it is within an external component"
+        );
+        assert_eq!(
+            error("addrmap { reg { external field {} f; } r0; } top;"),
+            "field f cannot be external"
+        );
+    }
+
+    #[test]
+    fn test_alias() {
+        let root = elaborate_str(
+            r#"
+            addrmap {
+                reg status_t { field { sw = rw; woclr; } a; field {} b; };
+                reg { field {} x; } first;
+                status_t status[2];
+                alias status status_t status_alias[2];
+                reg { field {} y; } after;
+                alias status status_t status_hi[2] @ 0x100 += 0x10;
+            } top;
+            "#,
+        );
+        // an alias is laid out like any other register, at its own address
+        let status = root.find("top.status").unwrap();
+        let alias = root.find("top.status_alias").unwrap();
+        let NodeContent::Register(register) = alias.content() else {
+            panic!("expected a register");
+        };
+        assert_eq!(register.alias_of(), Some(status.id()));
+        assert_eq!(alias.offset(), Some(0xc));
+        assert_eq!(alias.array().unwrap().stride(), 0x4);
+        assert_eq!(root.find("top.after").unwrap().offset(), Some(0x14));
+        let status_hi = root.find("top.status_hi").unwrap();
+        assert_eq!(status_hi.offset(), Some(0x100));
+        assert_eq!(status_hi.array().unwrap().stride(), 0x10);
+        let aliases: Vec<_> = root.aliases(status.id()).map(Node::name).collect();
+        assert_eq!(aliases, ["status_alias", "status_hi"]);
+        assert_eq!(
+            root.find_by_address(0x8).unwrap().register.id(),
+            status.id()
+        );
+        let found = root.find_by_address(0x10).unwrap();
+        assert_eq!(found.register.id(), alias.id());
+        assert_eq!(found.path, "top.status_alias[1]");
+        assert_eq!(found.fields.len(), 2);
+        assert_eq!(
+            root.find_by_address(0x110).unwrap().path,
+            "top.status_hi[1]"
+        );
+
+        let error = |input: &str| {
+            elaborate(crate::parse(input).unwrap())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("addrmap { reg r_t { field {} f; }; alias missing r_t a; } top;"),
+            "unknown primary register missing for alias a"
+        );
+        assert_eq!(
+            error(
+                "addrmap { reg r_t { field {} f; }; r_t r0; alias r0 r_t a; alias a r_t b; } top;"
+            ),
+            "alias b cannot be an alias of a, which is itself an alias"
+        );
+        assert_eq!(
+            error(
+                "addrmap { regfile rf_t { reg { field {} f; } r0; }; rf_t rf; alias rf rf_t a; } top;"
+            ),
+            "alias a must be an alias of a register, but rf is a regfile"
+        );
+    }
 }
@@ -1,86 +1,400 @@
 // Licensed under the Apache-2.0 license
 
-use crate::string_arena::StringArena;
-use core::panic;
+use crate::interner::Interner;
+use crate::lexer::line_column;
+use regex::Regex;
 use same_file::is_same_file;
-use std::{cell::RefCell, path::Path};
-
-#[cfg(test)]
-use std::{
-    collections::HashMap,
-    io::{Error, ErrorKind},
-    path::PathBuf,
-};
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 pub trait FileSource {
     fn read_to_string(&self, path: &Path) -> std::io::Result<&str>;
+
+    /// The 1-based line and column, in the file as it is stored, of a byte
+    /// offset into the text returned by [`FileSource::read_to_string`].
+    ///
+    /// Returns `None` when the text is the file's contents unchanged, so that
+    /// the offset can be located in the text itself.
+    fn original_location(&self, _path: &Path, _offset: usize) -> Option<(usize, usize)> {
+        None
+    }
 }
 
-#[derive(Default)]
-pub struct FsFileSource {
-    arena: StringArena,
-    patches: RefCell<Vec<(String, String, String)>>,
+impl<S: FileSource + ?Sized> FileSource for Box<S> {
+    fn read_to_string(&self, path: &Path) -> std::io::Result<&str> {
+        (**self).read_to_string(path)
+    }
+
+    fn original_location(&self, path: &Path, offset: usize) -> Option<(usize, usize)> {
+        (**self).original_location(path, offset)
+    }
 }
 
 fn trim_lines(s: &str) -> String {
     s.lines().map(|l| l.trim()).collect::<Vec<_>>().join("\n")
 }
 
+/// Where a [`Patch`] applies.
+#[derive(Clone, Debug)]
+enum PatchTarget {
+    Text(String),
+    Lines(RangeInclusive<usize>),
+    Regex(Regex),
+}
+
+/// A change made to a file as it is read, for example to work around a
+/// construct that the parser does not support.
+///
+/// Patches to the same file are applied in the order they were added, each to
+/// the result of the ones before it.
+#[derive(Clone, Debug)]
+pub struct Patch {
+    path: PathBuf,
+    target: PatchTarget,
+    replacement: String,
+}
+
+impl Patch {
+    /// Replace every occurrence of `from` with `to`.
+    pub fn text(path: impl Into<PathBuf>, from: &str, to: &str) -> Self {
+        Patch {
+            path: path.into(),
+            target: PatchTarget::Text(from.into()),
+            replacement: to.into(),
+        }
+    }
+
+    /// Replace a range of lines, numbered from 1, with `to`. The newline
+    /// ending the last line in the range is kept.
+    pub fn lines(path: impl Into<PathBuf>, lines: RangeInclusive<usize>, to: &str) -> Self {
+        Patch {
+            path: path.into(),
+            target: PatchTarget::Lines(lines),
+            replacement: to.into(),
+        }
+    }
+
+    /// Replace every match of the regular expression `pattern` with `to`,
+    /// which may refer to capture groups as `$1` or `${name}`.
+    pub fn regex(path: impl Into<PathBuf>, pattern: &str, to: &str) -> Result<Self, anyhow::Error> {
+        Ok(Patch {
+            path: path.into(),
+            target: PatchTarget::Regex(Regex::new(pattern)?),
+            replacement: to.into(),
+        })
+    }
+
+    /// The byte ranges to replace in `contents`, and what to replace each
+    /// with.
+    fn edits(&self, contents: &str, trim: bool) -> Result<Vec<(Range<usize>, String)>, String> {
+        let edits: Vec<_> = match &self.target {
+            PatchTarget::Text(from) => {
+                let from = match trim {
+                    true => trim_lines(from),
+                    false => from.clone(),
+                };
+                if from.is_empty() {
+                    return Err("the text to replace is empty".into());
+                }
+                contents
+                    .match_indices(from.as_str())
+                    .map(|(i, _)| (i..i + from.len(), self.replacement.clone()))
+                    .collect()
+            }
+            PatchTarget::Lines(lines) => {
+                let starts: Vec<_> = std::iter::once(0)
+                    .chain(contents.match_indices('\n').map(|(i, _)| i + 1))
+                    .collect();
+                let (first, last) = (*lines.start(), *lines.end());
+                if first == 0 || first > last || last > starts.len() {
+                    return Err(format!(
+                        "lines {first}..={last} are not in the file, which has {} lines",
+                        starts.len()
+                    ));
+                }
+                let end = starts.get(last).map_or(contents.len(), |next| next - 1);
+                vec![(starts[first - 1]..end, self.replacement.clone())]
+            }
+            PatchTarget::Regex(regex) => regex
+                .captures_iter(contents)
+                .map(|captures| {
+                    let mut replacement = String::new();
+                    captures.expand(&self.replacement, &mut replacement);
+                    (captures.get_match().range(), replacement)
+                })
+                .collect(),
+        };
+        if edits.is_empty() {
+            return Err("no match found".into());
+        }
+        Ok(edits)
+    }
+
+    fn describe(&self) -> String {
+        match &self.target {
+            PatchTarget::Text(from) => format!("{from:?}"),
+            PatchTarget::Lines(lines) => format!("of lines {}..={}", lines.start(), lines.end()),
+            PatchTarget::Regex(regex) => format!("/{regex}/"),
+        }
+    }
+}
+
+/// A piece of the text returned for a file, and where it came from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Segment {
+    len: usize,
+    /// The offset in the original file of the start of the segment.
+    original: usize,
+    /// Whether the segment was copied from the file, rather than inserted by
+    /// a patch in place of the text at `original`.
+    copied: bool,
+}
+
+/// Maps offsets in the text of a patched file back to the file as it is
+/// stored.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SourceMap {
+    segments: Vec<Segment>,
+}
+
+impl SourceMap {
+    fn new(len: usize) -> Self {
+        SourceMap {
+            segments: vec![Segment {
+                len,
+                original: 0,
+                copied: true,
+            }],
+        }
+    }
+
+    /// The offset in the original file of `offset` in the patched text.
+    /// Offsets within text inserted by a patch map to the start of the text
+    /// it replaced.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        let mut start = 0;
+        for segment in &self.segments {
+            if offset < start + segment.len {
+                return match segment.copied {
+                    true => segment.original + (offset - start),
+                    false => segment.original,
+                };
+            }
+            start += segment.len;
+        }
+        self.segments
+            .last()
+            .map_or(0, |segment| match segment.copied {
+                true => segment.original + segment.len + (offset - start),
+                false => segment.original,
+            })
+    }
+
+    /// Record that `range` of the patched text was replaced by `len` bytes.
+    fn replace(&mut self, range: Range<usize>, len: usize) {
+        let original = self.original_offset(range.start);
+        let mut segments = Vec::new();
+        let mut start = 0;
+        let mut inserted = false;
+        for segment in &self.segments {
+            let end = start + segment.len;
+            // the part before the replaced range
+            if start < range.start {
+                segments.push(Segment {
+                    len: segment.len.min(range.start - start),
+                    ..*segment
+                });
+            }
+            if !inserted && end >= range.start {
+                if len > 0 {
+                    segments.push(Segment {
+                        len,
+                        original,
+                        copied: false,
+                    });
+                }
+                inserted = true;
+            }
+            // the part after the replaced range
+            if end > range.end {
+                let skip = range.end.saturating_sub(start);
+                segments.push(Segment {
+                    len: segment.len - skip,
+                    original: match segment.copied {
+                        true => segment.original + skip,
+                        false => segment.original,
+                    },
+                    copied: segment.copied,
+                });
+            }
+            start = end;
+        }
+        if !inserted && len > 0 {
+            segments.push(Segment {
+                len,
+                original,
+                copied: false,
+            });
+        }
+        self.segments = segments;
+    }
+}
+
+/// Reads files from the filesystem, applying any patches that have been added
+/// for them.
+#[derive(Default)]
+pub struct FsFileSource {
+    strings: Interner,
+    patches: Mutex<Vec<Patch>>,
+    trim_lines: bool,
+    /// The contents and source map of every patched file that has been read.
+    patched: Mutex<HashMap<PathBuf, (String, SourceMap)>>,
+}
+
 impl FsFileSource {
     pub fn new() -> Self {
-        FsFileSource {
-            arena: StringArena::new(),
-            patches: RefCell::new(Vec::new()),
-        }
+        Default::default()
     }
 
+    /// Remove the whitespace at the start and end of every line of a file,
+    /// and of the text that [`Patch::text`] patches look for, so that patches
+    /// do not depend on indentation.
+    pub fn trim_lines(mut self, trim: bool) -> Self {
+        self.trim_lines = trim;
+        self
+    }
+
+    /// Replace every occurrence of `from` in the file at `path` with `to`.
     pub fn add_patch(&self, path: &Path, from: &str, to: &str) {
-        self.patches.borrow_mut().push((
-            path.display().to_string(),
-            trim_lines(from),
-            trim_lines(to),
-        ));
+        self.patch(Patch::text(path, from, to));
+    }
+
+    pub fn patch(&self, patch: Patch) {
+        self.patches.lock().unwrap().push(patch);
+    }
+
+    /// The source map of a file that has been read with patches applied.
+    pub fn source_map(&self, path: &Path) -> Option<SourceMap> {
+        let patched = self.patched.lock().unwrap();
+        patched.get(path).map(|(_, map)| map.clone())
+    }
+
+    fn apply_patches(&self, path: &Path, original: &str) -> std::io::Result<(String, SourceMap)> {
+        let mut contents = original.to_string();
+        let mut map = SourceMap::new(original.len());
+        let mut apply = |contents: &mut String, edits: Vec<(Range<usize>, String)>| {
+            for (range, replacement) in edits.into_iter().rev() {
+                map.replace(range.clone(), replacement.len());
+                contents.replace_range(range, &replacement);
+            }
+        };
+        if self.trim_lines {
+            let mut edits = Vec::new();
+            let mut start = 0;
+            for line in contents.split_inclusive('\n') {
+                let body = line.strip_suffix('\n').unwrap_or(line);
+                let body = body.strip_suffix('\r').unwrap_or(body);
+                let leading = body.len() - body.trim_start().len();
+                let trailing = body.len() - body.trim_end().len();
+                if leading == body.len() {
+                    edits.push((start..start + body.len(), String::new()));
+                } else {
+                    edits.push((start..start + leading, String::new()));
+                    edits.push((
+                        start + body.len() - trailing..start + body.len(),
+                        String::new(),
+                    ));
+                }
+                start += line.len();
+            }
+            edits.retain(|(range, _)| !range.is_empty());
+            apply(&mut contents, edits);
+        }
+        for patch in self.patches.lock().unwrap().iter() {
+            if !(patch.path == path || is_same_file(path, &patch.path).unwrap_or_default()) {
+                continue;
+            }
+            let edits = patch.edits(&contents, self.trim_lines).map_err(|e| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "patch {} does not apply to {}: {e}",
+                        patch.describe(),
+                        path.display()
+                    ),
+                )
+            })?;
+            apply(&mut contents, edits);
+        }
+        Ok((contents, map))
     }
 }
 
 impl FileSource for FsFileSource {
     fn read_to_string(&self, path: &Path) -> std::io::Result<&str> {
-        let mut contents = trim_lines(&std::fs::read_to_string(path)?);
-        for (patch_path, from, to) in self.patches.borrow().iter() {
-            if is_same_file(path, patch_path).unwrap_or_default() {
-                if !contents.contains(from) {
-                    panic!("Patch {:?} not found in file: {}", from, path.display());
-                }
-                let before = contents.clone();
-                contents = contents.replace(from, to);
-                if before == contents {
-                    panic!("Patch {:?} did not change file: {}", from, path.display());
-                }
-            }
+        let original = std::fs::read_to_string(path)?;
+        let (contents, map) = self.apply_patches(path, &original)?;
+        if contents != original {
+            self.patched
+                .lock()
+                .unwrap()
+                .insert(path.into(), (original, map));
         }
-        Ok(self.arena.add(contents))
+        Ok(self.strings.add(contents))
+    }
+
+    fn original_location(&self, path: &Path, offset: usize) -> Option<(usize, usize)> {
+        let patched = self.patched.lock().unwrap();
+        let (original, map) = patched.get(path)?;
+        Some(line_column(original, map.original_offset(offset)))
     }
 }
 
-#[cfg(test)]
+/// Files held in memory, for embedding SystemRDL in a program or parsing
+/// generated sources.
+#[derive(Default)]
 pub struct MemFileSource {
-    arena: crate::string_arena::StringArena,
+    strings: Interner,
     map: HashMap<PathBuf, String>,
 }
-#[cfg(test)]
+
 impl MemFileSource {
-    #[allow(unused)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
     pub fn from_entries(entries: &[(PathBuf, String)]) -> Self {
         Self {
-            arena: StringArena::new(),
+            strings: Interner::new(),
             map: entries.iter().cloned().collect(),
         }
     }
+
+    /// Add a file, replacing any file with the same path. Text already
+    /// returned for the old contents stays valid.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, contents: impl Into<String>) {
+        self.map.insert(path.into(), contents.into());
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Option<String> {
+        self.map.remove(path)
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.map.contains_key(path)
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.map.keys().map(PathBuf::as_path)
+    }
 }
-#[cfg(test)]
+
 impl FileSource for MemFileSource {
     fn read_to_string(&self, path: &Path) -> std::io::Result<&str> {
-        Ok(self.arena.add(
+        Ok(self.strings.add(
             self.map
                 .get(path)
                 .ok_or(Error::new(ErrorKind::NotFound, path.to_string_lossy()))?
@@ -88,3 +402,226 @@ impl FileSource for MemFileSource {
         ))
     }
 }
+
+/// Files held in memory in front of another [`FileSource`], by default the
+/// filesystem, so that an editor can parse unsaved buffers that include files
+/// on disk.
+///
+/// A path is read from memory if it was added with the same name, or if it
+/// names the same file on disk as one that was added.
+///
+/// ```no_run
+/// # fn main() -> Result<(), anyhow::Error> {
+/// let mut files = systemrdl::OverlayFileSource::new();
+/// files.insert("src/top.rdl", "`include \"regs.rdl\"\naddrmap top { ctrl_t ctrl; };");
+/// // src/regs.rdl is read from disk
+/// let root = systemrdl::ast::Root::from_file(&files, "src/top.rdl".as_ref())?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct OverlayFileSource<S = FsFileSource> {
+    overlay: MemFileSource,
+    base: S,
+}
+
+impl OverlayFileSource {
+    pub fn new() -> Self {
+        Self::with_base(FsFileSource::new())
+    }
+}
+
+impl Default for OverlayFileSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: FileSource> OverlayFileSource<S> {
+    pub fn with_base(base: S) -> Self {
+        OverlayFileSource {
+            overlay: MemFileSource::new(),
+            base,
+        }
+    }
+
+    /// Add a file to the overlay, replacing any file with the same path.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, contents: impl Into<String>) {
+        self.overlay.insert(path, contents);
+    }
+
+    /// Remove a file from the overlay, so that it is read from the base
+    /// again.
+    pub fn remove(&mut self, path: &Path) -> Option<String> {
+        self.overlay.remove(path)
+    }
+
+    pub fn base(&self) -> &S {
+        &self.base
+    }
+
+    /// The path under which `path` is in the overlay, if it is.
+    fn overlay_path<'a>(&'a self, path: &'a Path) -> Option<&'a Path> {
+        if self.overlay.contains(path) {
+            return Some(path);
+        }
+        self.overlay
+            .paths()
+            .find(|p| is_same_file(p, path).unwrap_or_default())
+    }
+}
+
+impl<S: FileSource> FileSource for OverlayFileSource<S> {
+    fn read_to_string(&self, path: &Path) -> std::io::Result<&str> {
+        match self.overlay_path(path) {
+            Some(path) => self.overlay.read_to_string(path),
+            None => self.base.read_to_string(path),
+        }
+    }
+
+    fn original_location(&self, path: &Path, offset: usize) -> Option<(usize, usize)> {
+        match self.overlay_path(path) {
+            Some(_) => None,
+            None => self.base.original_location(path, offset),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("systemrdl-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_patches() {
+        let path = temp_file(
+            "patches.rdl",
+            "reg {\n    field {} a;\n\tfield {} b;\n} r;\n",
+        );
+        let files = FsFileSource::new();
+        assert_eq!(
+            files.read_to_string(&path).unwrap(),
+            "reg {\n    field {} a;\n\tfield {} b;\n} r;\n"
+        );
+        assert_eq!(files.source_map(&path), None);
+
+        let files = FsFileSource::new();
+        files.patch(Patch::lines(&path, 2..=2, "field {} c; field {} d;"));
+        files.patch(Patch::regex(&path, r"field \{\} (\w);", "field {} ${1}_x;").unwrap());
+        files.add_patch(&path, "} r;", "} regs;");
+        let contents = files.read_to_string(&path).unwrap();
+        assert_eq!(
+            contents,
+            "reg {\nfield {} c_x; field {} d_x;\n\tfield {} b_x;\n} regs;\n"
+        );
+        // copied text is where it was; inserted text maps to the start of
+        // the text it replaced
+        let tab = contents.find('\t').unwrap();
+        assert_eq!(files.original_location(&path, tab), Some((3, 1)));
+        let b = contents.find("b_x").unwrap();
+        assert_eq!(files.original_location(&path, b), Some((3, 2)));
+        let d = contents.find("d_x").unwrap();
+        assert_eq!(files.original_location(&path, d), Some((2, 1)));
+        let end = contents.find("regs").unwrap();
+        assert_eq!(files.original_location(&path, end + 5), Some((4, 5)));
+
+        let files = FsFileSource::new().trim_lines(true);
+        files.add_patch(&path, "  field {} a;\n    field {} b;", "field {} ab;");
+        let contents = files.read_to_string(&path).unwrap();
+        assert_eq!(contents, "reg {\nfield {} ab;\n} r;\n");
+        let r = contents.find("r;").unwrap();
+        assert_eq!(files.original_location(&path, r), Some((4, 3)));
+    }
+
+    #[test]
+    fn test_patch_errors() {
+        let path = temp_file("errors.rdl", "reg { field {} a; } r;\n");
+        for patch in [
+            Patch::text(&path, "field {} b;", ""),
+            Patch::lines(&path, 2..=3, ""),
+            Patch::regex(&path, "x+", "").unwrap(),
+        ] {
+            let files = FsFileSource::new();
+            files.patch(patch);
+            let err = files.read_to_string(&path).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+
+        let files = FsFileSource::new();
+        files.add_patch(&path, "field {} b;", "");
+        let err = files.read_to_string(&path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "patch \"field {{}} b;\" does not apply to {}: no match found",
+                path.display()
+            )
+        );
+        assert!(Patch::regex(&path, "(", "").is_err());
+    }
+
+    #[test]
+    fn test_overlay() {
+        let regs = temp_file("overlay_regs.rdl", "reg ctrl_t { field {} EN; };\n");
+        let top = regs.with_file_name("overlay_top.rdl");
+        let mut files = OverlayFileSource::new();
+        files.insert(
+            &top,
+            "`include \"overlay_regs.rdl\"\naddrmap top { ctrl_t ctrl; };",
+        );
+        let root = crate::ast::Root::from_file(&files, &top).unwrap();
+        assert_eq!(root.descriptions.len(), 2);
+
+        // an unsaved buffer takes the place of the file on disk, even when
+        // it is named differently
+        let dotted = regs.parent().unwrap().join(".").join("overlay_regs.rdl");
+        files.insert(&dotted, "reg ctrl_t { field {} EN; field {} BUSY; };");
+        assert_eq!(
+            files.read_to_string(&regs).unwrap(),
+            "reg ctrl_t { field {} EN; field {} BUSY; };"
+        );
+        files.remove(&dotted);
+        assert_eq!(
+            files.read_to_string(&regs).unwrap(),
+            "reg ctrl_t { field {} EN; };\n"
+        );
+        assert_eq!(
+            files
+                .read_to_string(Path::new("missing.rdl"))
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn test_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<FsFileSource>();
+        assert_send_sync::<MemFileSource>();
+        assert_send_sync::<OverlayFileSource>();
+        assert_send_sync::<crate::DependencyTracker<FsFileSource>>();
+
+        let files = FsFileSource::new();
+        let paths: Vec<_> = (0..4)
+            .map(|i| {
+                temp_file(
+                    &format!("thread{i}.rdl"),
+                    &format!("addrmap block{i} {{ }};"),
+                )
+            })
+            .collect();
+        std::thread::scope(|scope| {
+            for path in &paths {
+                let files = &files;
+                scope.spawn(move || crate::ast::Root::from_file(files, path).unwrap());
+            }
+        });
+    }
+}
@@ -0,0 +1,490 @@
+// Licensed under the Apache-2.0 license
+
+//! Source formatter for SystemRDL.
+//!
+//! Unlike the [`printer`](crate::ToRdl), which prints an AST, the formatter
+//! works on the [token tree](crate::token_tree) so that comments are kept.
+//! It indents blocks one level per brace, puts each statement on its own line,
+//! lines up the `@` of consecutive address assignments, keeps at most one blank
+//! line between statements, and writes number literals in lower case without
+//! leading zeros. Formatting already formatted source leaves it unchanged.
+
+use crate::PrintOptions;
+use crate::lexer::{Lexer, line_column};
+use crate::token::TokenKind;
+use crate::token_tree::{self, NodeKind, SyntaxElement, SyntaxNode, SyntaxToken};
+use anyhow::{bail, ensure};
+
+/// Format SystemRDL source text.
+///
+/// Fails if the input contains text that cannot be lexed. The input does not
+/// need to parse, and preprocessor directives are formatted in place without
+/// being expanded.
+pub fn format(input: &str, options: &PrintOptions) -> Result<String, anyhow::Error> {
+    let tree = token_tree::parse(input);
+    if let Some(token) = tree.tokens().find(|t| *t.kind() == TokenKind::Error) {
+        let (line, column) = line_column(input, token.span().start);
+        bail!(
+            "unexpected input at line {line}, column {column}: `{}`",
+            token.text()
+        );
+    }
+
+    let formatter = Formatter { options };
+    let mut out = formatter.statements(tree.children(), 0);
+    if !out.is_empty() {
+        out.push('\n');
+    }
+
+    // formatting only ever changes whitespace and the spelling of numbers
+    ensure!(
+        Lexer::new(input).eq(Lexer::new(&out)),
+        "formatting changed the tokens of the input"
+    );
+    Ok(out)
+}
+
+/// A formatted statement, before `@` alignment.
+struct Entry {
+    /// Whether the statement was preceded by a blank line.
+    blank_before: bool,
+    comments: Vec<String>,
+    body: String,
+    /// Offset within `body` of a top-level `@`, if the body is a single line.
+    at: Option<usize>,
+}
+
+/// A formatted run of tokens, which may span several lines.
+struct Atom<'s> {
+    text: String,
+    first: TokenKind<'s>,
+    last: TokenKind<'s>,
+}
+
+/// Where a run of tokens appears, which affects the spacing around `:`.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Context {
+    Statement,
+    Brackets,
+    Struct,
+}
+
+struct Formatter<'a> {
+    options: &'a PrintOptions,
+}
+
+impl Formatter<'_> {
+    fn indent(&self, level: usize) -> String {
+        self.options.indent.repeat(level)
+    }
+
+    /// Format the contents of a file or block, one line per statement or
+    /// comment, each indented to `level`.
+    fn statements(&self, children: &[SyntaxElement], level: usize) -> String {
+        let mut entries = vec![];
+        let mut trivia = vec![];
+        for child in children {
+            match child {
+                SyntaxElement::Token(token) => trivia.push(token),
+                SyntaxElement::Node(node) => {
+                    let mut entry = self.statement(node, level);
+                    // trivia left over from an unterminated statement
+                    let (blank_before, comments) = leading_comments(&trivia);
+                    trivia.clear();
+                    entry.blank_before |= blank_before;
+                    entry.comments.splice(0..0, comments);
+                    entries.push(entry);
+                }
+            }
+        }
+        // comments at the end of the block
+        let (blank_before, comments) = leading_comments(&trivia);
+        if !comments.is_empty() {
+            entries.push(Entry {
+                blank_before,
+                comments,
+                body: String::new(),
+                at: None,
+            });
+        }
+
+        align(&mut entries);
+
+        let indent = self.indent(level);
+        let mut lines: Vec<String> = vec![];
+        for entry in entries {
+            if entry.blank_before && !lines.is_empty() {
+                lines.push(String::new());
+            }
+            for comment in entry.comments {
+                lines.push(format!("{indent}{comment}"));
+            }
+            if !entry.body.is_empty() {
+                lines.push(format!("{indent}{}", entry.body));
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn statement(&self, node: &SyntaxNode, level: usize) -> Entry {
+        let children = node.children();
+        let start = children
+            .iter()
+            .position(|c| !c.as_token().is_some_and(|t| t.is_trivia()))
+            .unwrap_or(children.len());
+        let leading: Vec<_> = children[..start]
+            .iter()
+            .filter_map(SyntaxElement::as_token)
+            .collect();
+        let (blank_before, comments) = leading_comments(&leading);
+
+        let atoms = self.atoms(&children[start..], level, Context::Statement);
+        let (body, mut at) = self.join(&atoms, level, Context::Statement);
+        if body.contains('\n') {
+            at = None;
+        }
+        Entry {
+            blank_before,
+            comments,
+            body,
+            at,
+        }
+    }
+
+    /// Join formatted atoms, returning the text and the offset of the first
+    /// `@`.
+    fn join(&self, atoms: &[Atom], level: usize, context: Context) -> (String, Option<usize>) {
+        let mut text = String::new();
+        let mut at = None;
+        let mut unary = false;
+        for (i, atom) in atoms.iter().enumerate() {
+            if i > 0 {
+                text += &self.separator(&atoms[i - 1], atom, unary, level, context);
+            }
+            // a reduction operator follows an operator or starts an expression
+            unary = matches!(atom.first, TokenKind::And | TokenKind::Or)
+                && (i == 0 || is_operator(&atoms[i - 1].last));
+            if atom.first == TokenKind::At && at.is_none() {
+                at = Some(text.trim_end().len());
+            }
+            text += &atom.text;
+        }
+        (text, at)
+    }
+
+    /// The text between two adjacent atoms.
+    fn separator(
+        &self,
+        prev: &Atom,
+        next: &Atom,
+        prev_unary: bool,
+        level: usize,
+        context: Context,
+    ) -> String {
+        use TokenKind::*;
+        if prev.last == Comment && prev.text.starts_with("//") {
+            return format!("\n{}", self.indent(level + 1));
+        }
+        let space = match (&prev.last, &next.first) {
+            _ if prev_unary => false,
+            (_, Semicolon | Comma | BracketClose | ParenClose | Period | Pointer) => false,
+            (BracketOpen | ParenOpen | Period | Pointer | Hash | Quote, _) => false,
+            // the braces of a struct literal
+            (BraceOpen, _) | (_, BraceClose) => false,
+            // casts
+            (
+                Identifier(_) | Boolean | Unsigned | Longint | Bit | Number(_) | Bits(_)
+                | ParenClose,
+                Quote,
+            ) => false,
+            (Identifier(_) | BracketClose, BracketOpen) => false,
+            (Identifier(_), ParenOpen) => false,
+            (Colon, _) | (_, Colon) if context == Context::Brackets => false,
+            (_, Colon) if context == Context::Struct => false,
+            _ => true,
+        };
+        if space { " ".into() } else { "".into() }
+    }
+
+    fn atoms<'s>(
+        &self,
+        children: &[SyntaxElement<'s>],
+        level: usize,
+        context: Context,
+    ) -> Vec<Atom<'s>> {
+        let mut atoms = vec![];
+        for child in children {
+            match child {
+                SyntaxElement::Token(token) if *token.kind() == TokenKind::Whitespace => {}
+                SyntaxElement::Token(token) => atoms.push(Atom {
+                    text: token_text(token),
+                    first: token.kind().clone(),
+                    last: token.kind().clone(),
+                }),
+                SyntaxElement::Node(node) if node.kind() == NodeKind::Block => {
+                    atoms.push(self.block(node, level))
+                }
+                SyntaxElement::Node(node) => atoms.push(self.group(node, level, context)),
+            }
+        }
+        atoms
+    }
+
+    fn block<'s>(&self, node: &SyntaxNode<'s>, level: usize) -> Atom<'s> {
+        let children = node.children();
+        let has_close = children
+            .last()
+            .and_then(SyntaxElement::as_token)
+            .is_some_and(|t| *t.kind() == TokenKind::BraceClose);
+        let inner = &children[1..children.len() - has_close as usize];
+        let close = if has_close { "}" } else { "" };
+
+        // a concatenation such as `{a, b}` rather than a list of statements
+        let inline = inner.iter().all(|child| match child {
+            SyntaxElement::Token(token) => *token.kind() == TokenKind::Whitespace,
+            SyntaxElement::Node(node) => !node
+                .tokens()
+                .any(|t| matches!(t.kind(), TokenKind::Semicolon | TokenKind::Comment)),
+        });
+        let text = if inline {
+            let tokens: Vec<_> = inner
+                .iter()
+                .flat_map(|child| match child {
+                    SyntaxElement::Node(node) => node.children().to_vec(),
+                    SyntaxElement::Token(_) => vec![],
+                })
+                .collect();
+            let atoms = self.atoms(&tokens, level, Context::Statement);
+            let (text, _) = self.join(&atoms, level, Context::Statement);
+            format!("{{{text}{close}")
+        } else {
+            format!(
+                "{{\n{}\n{}{close}",
+                self.statements(inner, level + 1),
+                self.indent(level)
+            )
+        };
+        Atom {
+            text,
+            first: TokenKind::BraceOpen,
+            last: TokenKind::BraceClose,
+        }
+    }
+
+    fn group<'s>(&self, node: &SyntaxNode<'s>, level: usize, context: Context) -> Atom<'s> {
+        let children = node.children();
+        let first = children[0].as_token().map(|t| t.kind().clone());
+        let first = first.unwrap_or(TokenKind::Error);
+        let context = match first {
+            TokenKind::BracketOpen => Context::Brackets,
+            TokenKind::Quote => Context::Struct,
+            _ => context,
+        };
+        let atoms = self.atoms(children, level, context);
+        let (text, _) = self.join(&atoms, level, context);
+        Atom {
+            text,
+            first,
+            last: atoms
+                .into_iter()
+                .last()
+                .map_or(TokenKind::Error, |a| a.last),
+        }
+    }
+}
+
+fn is_operator(kind: &TokenKind) -> bool {
+    use TokenKind::*;
+    matches!(
+        kind,
+        Equals
+            | EqualsEquals
+            | NotEquals
+            | PlusEqual
+            | PercentEqual
+            | At
+            | Comma
+            | QuestionMark
+            | Colon
+            | And
+            | Or
+            | AndAnd
+            | OrOr
+            | LeftShift
+            | RightShift
+            | ParenOpen
+            | BracketOpen
+            | BraceOpen
+    )
+}
+
+/// The comments in a run of trivia, one per line, and whether the trivia
+/// starts with a blank line.
+fn leading_comments(trivia: &[&SyntaxToken]) -> (bool, Vec<String>) {
+    let blank_before = trivia
+        .first()
+        .is_some_and(|t| *t.kind() == TokenKind::Whitespace && t.text().matches('\n').count() > 1);
+    let mut comments = vec![];
+    for (i, token) in trivia.iter().enumerate() {
+        if *token.kind() != TokenKind::Comment {
+            continue;
+        }
+        // keep blank lines between comments
+        if i > 0 && !comments.is_empty() && trivia[i - 1].text().matches('\n').count() > 1 {
+            comments.push(String::new());
+        }
+        comments.push(token.text().trim_end().to_string());
+    }
+    (blank_before, comments)
+}
+
+/// Pad consecutive single-line statements so that their `@`s line up.
+fn align(entries: &mut [Entry]) {
+    let mut start = 0;
+    while start < entries.len() {
+        let mut end = start;
+        while end < entries.len()
+            && entries[end].at.is_some()
+            && (end == start || (!entries[end].blank_before && entries[end].comments.is_empty()))
+        {
+            end += 1;
+        }
+        let run = &mut entries[start..end.max(start + 1)];
+        let column = run.iter().filter_map(|e| e.at).max();
+        if let Some(column) = column {
+            for entry in run {
+                let at = entry.at.unwrap();
+                let padding = " ".repeat(column - at);
+                entry.body.insert_str(at, &padding);
+            }
+        }
+        start = end.max(start + 1);
+    }
+}
+
+fn token_text(token: &SyntaxToken) -> String {
+    match token.kind() {
+        TokenKind::Number(_) | TokenKind::Bits(_) => normalize_number(token.text()),
+        TokenKind::Comment => token.text().trim_end().to_string(),
+        _ => token.text().to_string(),
+    }
+}
+
+/// Lower case hex digits, and no leading zeros on the digits of a number,
+/// whether after `0x`, or on either side of the `'` of a Verilog-style
+/// literal such as `4'h0f`.
+fn normalize_number(text: &str) -> String {
+    let text = text.to_ascii_lowercase();
+    if let Some(digits) = text.strip_prefix("0x") {
+        return format!("0x{}", trim_leading_zeros(digits));
+    }
+    if let Some((width, value)) = text.split_once('\'')
+        && let Some(base) = value.chars().next()
+    {
+        let digits = &value[base.len_utf8()..];
+        return format!(
+            "{}'{base}{}",
+            trim_leading_zeros(width),
+            trim_leading_zeros(digits)
+        );
+    }
+    trim_leading_zeros(&text).into()
+}
+
+fn trim_leading_zeros(digits: &str) -> &str {
+    let trimmed = digits.trim_start_matches(['0', '_']);
+    if trimmed.is_empty() { "0" } else { trimmed }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fmt(input: &str) -> String {
+        format(input, &PrintOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_format() {
+        let input = r#"
+// Registers
+`include "types.rdl"
+addrmap   top{
+  default regwidth=32;  reg {field{sw=rw;hw=r;}EN[0:0]=1'b0;// enable
+    field {} MODE[ 4 : 1 ] = 4'hF; } CTRL@0x10 ;
+
+
+     /* status
+        register */
+  my_reg STATUS @ 0x0014;
+  my_reg DATA[4]@0x2A+=0x4;
+  my_reg #( .W(8)) small @ 0x40;
+  CTRL.EN->reset = 007 ? 1 : 0;
+  // end
+};
+"#;
+        let expected = r#"// Registers
+`include "types.rdl"
+addrmap top {
+    default regwidth = 32;
+    reg {
+        field {
+            sw = rw;
+            hw = r;
+        } EN[0:0] = 1'b0; // enable
+        field {} MODE[4:1] = 4'hf;
+    } CTRL @ 0x10;
+
+    /* status
+        register */
+    my_reg STATUS         @ 0x14;
+    my_reg DATA[4]        @ 0x2a += 0x4;
+    my_reg #(.W(8)) small @ 0x40;
+    CTRL.EN->reset = 7 ? 1 : 0;
+    // end
+};
+"#;
+        let formatted = fmt(input);
+        assert_eq!(formatted, expected);
+        assert_eq!(fmt(&formatted), formatted);
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(normalize_number("0x0014"), "0x14");
+        assert_eq!(normalize_number("0x00aB"), "0xab");
+        assert_eq!(normalize_number("0x0000"), "0x0");
+        assert_eq!(normalize_number("8'h0F"), "8'hf");
+        assert_eq!(normalize_number("08'd007"), "8'd7");
+        assert_eq!(normalize_number("4'b0_011"), "4'b11");
+        assert_eq!(normalize_number("4'b0000"), "4'b0");
+        assert_eq!(normalize_number("0010"), "10");
+        assert_eq!(normalize_number("000"), "0");
+        assert_eq!(fmt("x = {0x00, 16'h00FF};"), "x = {0x0, 16'hff};\n");
+    }
+
+    #[test]
+    fn test_comments_and_errors() {
+        assert_eq!(fmt(""), "");
+        assert_eq!(fmt("/* a */ /* b */\n\n// c"), "/* a */\n/* b */\n\n// c\n");
+        assert_eq!(
+            fmt("reg { field {} a // why\n [2]; } r;"),
+            "reg {\n    field {} a // why\n        [2];\n} r;\n"
+        );
+        assert_eq!(fmt("x = {a, {b,c}};"), "x = {a, {b, c}};\n");
+        let err = format("reg {\n  field $", &PrintOptions::default()).unwrap_err();
+        assert_eq!(err.to_string(), "unexpected input at line 2, column 9: `$`");
+    }
+
+    #[test]
+    fn test_corpus() {
+        for input in crate::printer::test::corpus() {
+            let formatted = fmt(&input);
+            assert_eq!(fmt(&formatted), formatted);
+            assert_eq!(
+                crate::parse(&formatted).unwrap(),
+                crate::parse(&input).unwrap()
+            );
+        }
+    }
+}
@@ -0,0 +1,130 @@
+// Licensed under the Apache-2.0 license
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// The number of chunks; chunk `k` holds `2^k` strings.
+const CHUNKS: usize = 32;
+
+/// A run of slots, each written at most once.
+type Chunk = Box<[OnceLock<Arc<str>>]>;
+
+/// A handle to a string in an [`Interner`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Symbol(u32);
+
+/// An append-only set of strings that hands out references living as long as
+/// the interner itself, and that can be shared between threads.
+///
+/// Strings are stored in slots that are written once and never moved or
+/// freed until the interner is dropped, which is what allows `&self` methods
+/// to return references into it without `unsafe`.
+#[derive(Default)]
+pub struct Interner {
+    chunks: [OnceLock<Chunk>; CHUNKS],
+    symbols: Mutex<HashMap<Arc<str>, Symbol>>,
+}
+
+/// The chunk and the slot within it of a symbol.
+fn slot(symbol: Symbol) -> (usize, usize) {
+    let n = symbol.0 as usize + 1;
+    let chunk = n.ilog2() as usize;
+    (chunk, n - (1 << chunk))
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The symbol for `s`, adding it if it is not already interned.
+    pub fn intern(&self, s: &str) -> Symbol {
+        self.insert(s, || s.into())
+    }
+
+    /// Intern a string and return the interned copy.
+    pub fn add(&self, s: String) -> &str {
+        let symbol = self.insert(&s, || s.as_str().into());
+        self.get(symbol)
+    }
+
+    /// The string for a symbol returned by this interner.
+    ///
+    /// Panics if the symbol came from a different interner and is out of
+    /// range for this one.
+    pub fn get(&self, symbol: Symbol) -> &str {
+        let (chunk, index) = slot(symbol);
+        self.chunks[chunk]
+            .get()
+            .and_then(|slots| slots[index].get())
+            .expect("symbol from another interner")
+    }
+
+    /// The symbol for `s`, if it has been interned.
+    pub fn lookup(&self, s: &str) -> Option<Symbol> {
+        self.symbols.lock().unwrap().get(s).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn insert(&self, s: &str, to_owned: impl FnOnce() -> Arc<str>) -> Symbol {
+        let mut symbols = self.symbols.lock().unwrap();
+        if let Some(symbol) = symbols.get(s) {
+            return *symbol;
+        }
+        let symbol = Symbol(u32::try_from(symbols.len()).expect("too many interned strings"));
+        let (chunk, index) = slot(symbol);
+        let slots =
+            self.chunks[chunk].get_or_init(|| (0..1 << chunk).map(|_| OnceLock::new()).collect());
+        let s = to_owned();
+        // the lock is held, so no other thread can be filling this slot
+        slots[index].set(s.clone()).unwrap();
+        symbols.insert(s, symbol);
+        symbol
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interner() {
+        let interner = Interner::new();
+        let a = interner.add("Hello world".into());
+        let b = interner.add("Foo".into());
+        let c = interner.add("Foo".into());
+        assert_eq!(a, "Hello world");
+        assert_eq!(b, "Foo");
+        assert_eq!(c, "Foo");
+        assert_eq!(b.as_ptr(), c.as_ptr());
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.lookup("Foo"), Some(interner.intern("Foo")));
+        assert_eq!(interner.lookup("Bar"), None);
+    }
+
+    #[test]
+    fn test_threads() {
+        let interner = Interner::new();
+        let names: Vec<String> = (0..1000).map(|i| format!("name{}", i % 300)).collect();
+        let symbols: Vec<Vec<Symbol>> = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| names.iter().map(|name| interner.intern(name)).collect()))
+                .collect();
+            threads.into_iter().map(|t| t.join().unwrap()).collect()
+        });
+        assert_eq!(interner.len(), 300);
+        for symbols in &symbols {
+            for (name, symbol) in names.iter().zip(symbols) {
+                assert_eq!(interner.get(*symbol), name);
+            }
+        }
+        assert!(symbols.windows(2).all(|w| w[0] == w[1]));
+    }
+}
@@ -0,0 +1,291 @@
+// Licensed under the Apache-2.0 license
+
+use crate::ast::{InterruptType, PrimaryLiteral};
+use crate::{Field, Node, NodeContent, NodeId, PropertyValue, RootNamespace};
+use anyhow::{bail, ensure};
+
+/// The interrupt behavior of a field with the `intr` property.
+///
+/// See [`Field::interrupt`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Interrupt {
+    /// How the input from hardware is detected: `level` unless the field was
+    /// given `posedge`, `negedge` or `bothedge`.
+    pub detection: InterruptType,
+    /// Whether the field holds its value once set until software clears it,
+    /// which it does unless given `nonsticky` or `stickybit = false`.
+    pub sticky: bool,
+    /// The field whose bits enable this one onto the register's `intr`.
+    pub enable: Option<NodeId>,
+    /// The field whose bits mask this one from the register's `intr`.
+    pub mask: Option<NodeId>,
+    /// The field whose bits enable this one onto the register's `halt`.
+    pub haltenable: Option<NodeId>,
+    /// The field whose bits mask this one from the register's `halt`.
+    pub haltmask: Option<NodeId>,
+}
+
+impl Interrupt {
+    /// Whether the interrupt also contributes to the register's `halt`.
+    pub fn halt(&self) -> bool {
+        self.haltenable.is_some() || self.haltmask.is_some()
+    }
+
+    /// Every field the interrupt refers to, with the property that names it.
+    pub fn references(&self) -> impl Iterator<Item = (&'static str, NodeId)> {
+        [
+            ("enable", self.enable),
+            ("mask", self.mask),
+            ("haltenable", self.haltenable),
+            ("haltmask", self.haltmask),
+        ]
+        .into_iter()
+        .filter_map(|(name, id)| Some((name, id?)))
+    }
+}
+
+const DETECTION: [(&str, InterruptType); 4] = [
+    ("level", InterruptType::Level),
+    ("posedge", InterruptType::PosEdge),
+    ("negedge", InterruptType::NegEdge),
+    ("bothedge", InterruptType::BothEdge),
+];
+
+const INTERRUPT_PROPERTIES: [&str; 9] = [
+    "level",
+    "posedge",
+    "negedge",
+    "bothedge",
+    "nonsticky",
+    "enable",
+    "mask",
+    "haltenable",
+    "haltmask",
+];
+
+impl Field {
+    /// The interrupt behavior of the field, or `None` if it is not an
+    /// interrupt (SystemRDL 2.0 section 9.9).
+    ///
+    /// Fails if the field has conflicting modifiers, such as both `posedge`
+    /// and `negedge`, if it has both an enable and a mask for `intr` or for
+    /// `halt`, or if it has interrupt properties without being an interrupt.
+    pub fn interrupt(&self) -> Result<Option<Interrupt>, anyhow::Error> {
+        if !self.is_set("intr") {
+            if let Some(property) = INTERRUPT_PROPERTIES.iter().find(|p| self.is_set(p)) {
+                bail!("{property} is only allowed on interrupt fields");
+            }
+            return Ok(None);
+        }
+
+        let mut detection = None;
+        for (name, value) in DETECTION {
+            if !self.is_set(name) {
+                continue;
+            }
+            if let Some((first, _)) = detection {
+                bail!("{name} conflicts with {first}");
+            }
+            detection = Some((name, value));
+        }
+
+        let nonsticky = self.is_set("nonsticky");
+        if nonsticky {
+            for property in ["sticky", "stickybit"] {
+                ensure!(
+                    !self.is_set(property),
+                    "nonsticky conflicts with {property}"
+                );
+            }
+        }
+        let stickybit = !matches!(
+            self.property("stickybit"),
+            Some(PropertyValue::Literal(PrimaryLiteral::BooleanLiteral(
+                false
+            )))
+        );
+
+        let interrupt = Interrupt {
+            detection: detection.map_or_else(InterruptType::default, |(_, value)| value),
+            sticky: !nonsticky && stickybit,
+            enable: self.reference("enable")?,
+            mask: self.reference("mask")?,
+            haltenable: self.reference("haltenable")?,
+            haltmask: self.reference("haltmask")?,
+        };
+        ensure!(
+            interrupt.enable.is_none() || interrupt.mask.is_none(),
+            "enable and mask cannot both be set"
+        );
+        ensure!(
+            interrupt.haltenable.is_none() || interrupt.haltmask.is_none(),
+            "haltenable and haltmask cannot both be set"
+        );
+        Ok(Some(interrupt))
+    }
+
+    /// The node a reference property names, if assigned.
+    fn reference(&self, name: &str) -> Result<Option<NodeId>, anyhow::Error> {
+        match self.property(name) {
+            Some(PropertyValue::Reference(id)) => Ok(Some(*id)),
+            Some(PropertyValue::Literal(literal)) => {
+                bail!("{name} must be a reference to a field, got {literal}")
+            }
+            Some(other) => bail!("{name} must be a reference to a field, got {other:?}"),
+            None => Ok(None),
+        }
+    }
+}
+
+/// A node with interrupt fields below it, and the children that lead to
+/// them; see [`RootNamespace::interrupt_tree`].
+#[derive(Clone, Debug)]
+pub struct InterruptTree<'a> {
+    pub node: &'a Node,
+    /// The children with interrupts, in order, or none if `node` is an
+    /// interrupt field.
+    pub children: Vec<InterruptTree<'a>>,
+}
+
+impl InterruptTree<'_> {
+    /// The interrupt fields in the tree, depth first.
+    pub fn fields(&self) -> Vec<&Node> {
+        match self.node.content() {
+            NodeContent::Field(_) => vec![self.node],
+            _ => self
+                .children
+                .iter()
+                .flat_map(|child| child.fields())
+                .collect(),
+        }
+    }
+}
+
+impl RootNamespace {
+    /// The interrupts of a block: the tree of the node's descendants that
+    /// leads to each of its interrupt fields, or `None` if it has none.
+    ///
+    /// Fields whose interrupt properties are invalid are left out;
+    /// [`validate`](crate::validate) reports them.
+    pub fn interrupt_tree(&self, id: NodeId) -> Option<InterruptTree<'_>> {
+        let node = &self[id];
+        if let NodeContent::Field(field) = node.content() {
+            return matches!(field.interrupt(), Ok(Some(_))).then(|| InterruptTree {
+                node,
+                children: Vec::new(),
+            });
+        }
+        let children: Vec<_> = node
+            .children()
+            .iter()
+            .filter_map(|child| self.interrupt_tree(*child))
+            .collect();
+        (!children.is_empty()).then_some(InterruptTree { node, children })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interrupts() {
+        let input = r#"
+            addrmap {
+                reg {
+                    field { sw = rw; hw = na; } en[2];
+                    field { sw = rw; hw = na; } halt_en[2];
+                } ctrl;
+                reg {
+                    field { level intr; woclr; } a = 0;
+                    field { posedge intr; nonsticky intr; woclr; } b = 0;
+                    field { intr; woclr; } c[2];
+                    field { sw = r; } plain;
+                } status;
+                status.c->enable = ctrl.en;
+                status.c->haltenable = ctrl.halt_en;
+                reg { field {} d; } other;
+            } top;
+        "#;
+        let namespace = crate::elaborate(crate::parse(input).unwrap()).unwrap();
+        let field = |path: &str| match namespace.find(path).unwrap().content() {
+            NodeContent::Field(field) => field.interrupt().unwrap(),
+            _ => panic!("{path} is not a field"),
+        };
+        let id = |path: &str| namespace.find(path).unwrap().id();
+
+        let a = field("top.status.a").unwrap();
+        assert_eq!(a.detection, InterruptType::Level);
+        assert!(a.sticky);
+        let b = field("top.status.b").unwrap();
+        assert_eq!(b.detection, InterruptType::PosEdge);
+        assert!(!b.sticky);
+        let c = field("top.status.c").unwrap();
+        assert_eq!(c.enable, Some(id("top.ctrl.en")));
+        assert_eq!(c.haltenable, Some(id("top.ctrl.halt_en")));
+        assert!(c.halt());
+        assert_eq!(field("top.status.plain"), None);
+
+        let status = namespace.find("top.status").unwrap();
+        let set = PropertyValue::Literal(PrimaryLiteral::BooleanLiteral(true));
+        assert_eq!(status.property("intr"), Some(&set));
+        assert_eq!(status.property("halt"), Some(&set));
+        assert_eq!(namespace.find("top.ctrl").unwrap().property("intr"), None);
+
+        let tree = namespace.interrupt_tree(id("top")).unwrap();
+        assert_eq!(tree.children.len(), 1);
+        assert_eq!(tree.children[0].node.id(), status.id());
+        let fields: Vec<_> = tree.fields().iter().map(|node| node.name()).collect();
+        assert_eq!(fields, ["a", "b", "c"]);
+        assert!(namespace.interrupt_tree(id("top.other")).is_none());
+    }
+
+    #[test]
+    fn test_interrupt_errors() {
+        let error = |body: &str| {
+            let input = format!("addrmap {{ reg {{ field {{ {body} }} f; }} r0; }} top;");
+            let namespace = crate::elaborate(crate::parse(&input).unwrap()).unwrap();
+            match namespace.find("top.r0.f").unwrap().content() {
+                NodeContent::Field(field) => field.interrupt().unwrap_err().to_string(),
+                _ => unreachable!(),
+            }
+        };
+        assert_eq!(
+            error("posedge intr; negedge intr;"),
+            "negedge conflicts with posedge"
+        );
+        assert_eq!(
+            error("nonsticky intr; stickybit;"),
+            "nonsticky conflicts with stickybit"
+        );
+        assert_eq!(
+            error("intr; enable = 1;"),
+            "enable must be a reference to a field, got 1"
+        );
+        assert_eq!(
+            error("mask = 1;"),
+            "mask is only allowed on interrupt fields"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_intr() {
+        let error = |body: &str| {
+            let input = format!("addrmap {{ reg {{ field {{ {body} }} f; }} r0; }} top;");
+            crate::elaborate(crate::parse(&input).unwrap())
+                .unwrap_err()
+                .to_string()
+        };
+        // a modifier assigns intr, so a plain intr is a duplicate either way
+        assert_eq!(error("posedge intr; intr;"), "duplicate property intr");
+        assert_eq!(error("intr; posedge intr;"), "duplicate property intr");
+        assert_eq!(
+            error("nonsticky intr; intr = false;"),
+            "duplicate property intr"
+        );
+        assert_eq!(
+            error("posedge intr; posedge intr;"),
+            "duplicate property modifier posedge"
+        );
+    }
+}
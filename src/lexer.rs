@@ -5,10 +5,61 @@ use std::str::{Chars, FromStr};
 
 pub type Span = std::ops::Range<usize>;
 
+/// The 1-based line and column of a byte offset in `input`.
+pub(crate) fn line_column(input: &str, offset: usize) -> (usize, usize) {
+    let before = &input[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+/// Finds the line and column of byte offsets into an input, cheaply when
+/// the offsets come in increasing order, as those of tokens do.
+pub(crate) struct LineTracker<'a> {
+    input: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> LineTracker<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    pub(crate) fn input(&self) -> &'a str {
+        self.input
+    }
+
+    /// The same as [`line_column`], but only scanning the input from the
+    /// last offset found.
+    pub(crate) fn line_column(&mut self, offset: usize) -> (usize, usize) {
+        if offset < self.offset {
+            *self = Self::new(self.input);
+        }
+        for byte in &self.input.as_bytes()[self.offset..offset] {
+            if *byte == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        self.offset = offset;
+        (self.line, self.column)
+    }
+}
+
 pub struct Lexer<'a> {
     start_ptr: *const u8,
     token_start_ptr: *const u8,
     iter: std::str::Chars<'a>,
+    trivia: bool,
 }
 impl<'a> Lexer<'a> {
     pub fn new(s: &'a str) -> Self {
@@ -16,6 +67,17 @@ impl<'a> Lexer<'a> {
             start_ptr: s.as_bytes().as_ptr(),
             token_start_ptr: s.as_bytes().as_ptr(),
             iter: s.chars(),
+            trivia: false,
+        }
+    }
+
+    /// A lexer that also returns whitespace and comments, as
+    /// [`TokenKind::Whitespace`] and [`TokenKind::Comment`], so that the
+    /// tokens cover every byte of the input.
+    pub fn lossless(s: &'a str) -> Self {
+        Self {
+            trivia: true,
+            ..Self::new(s)
         }
     }
 
@@ -33,7 +95,10 @@ impl<'a> Iterator for Lexer<'a> {
         let mut iter = self.iter.clone();
         loop {
             let result = match iter.next() {
-                Some(' ' | '\t' | '\n' | '\r') => Some(TokenKind::Skip),
+                Some(' ' | '\t' | '\n' | '\r') => {
+                    next_while(&mut iter, |ch| matches!(ch, ' ' | '\t' | '\n' | '\r'));
+                    Some(TokenKind::Whitespace)
+                }
                 Some('/') => {
                     match iter.next() {
                         Some('*') => {
@@ -41,7 +106,7 @@ impl<'a> Iterator for Lexer<'a> {
                             loop {
                                 match iter.next() {
                                     Some('*') => match iter.next() {
-                                        Some('/') => break Some(TokenKind::Skip),
+                                        Some('/') => break Some(TokenKind::Comment),
                                         Some(_) => continue,
                                         None => break Some(TokenKind::Error),
                                     },
@@ -50,13 +115,11 @@ impl<'a> Iterator for Lexer<'a> {
                                 }
                             }
                         }
-                        Some('/') => loop {
-                            match iter.next() {
-                                Some('\n') => break Some(TokenKind::Skip),
-                                Some(_) => continue,
-                                None => break None,
-                            }
-                        },
+                        Some('/') => {
+                            // the newline is not part of the comment
+                            next_while(&mut iter, |ch| ch != '\n');
+                            Some(TokenKind::Comment)
+                        }
                         _ => Some(TokenKind::Error),
                     }
                 }
@@ -208,7 +271,7 @@ impl<'a> Iterator for Lexer<'a> {
                 }
                 Some('+') => match iter.next() {
                     Some('=') => Some(TokenKind::PlusEqual),
-                    _ => return Some(TokenKind::Error),
+                    _ => Some(TokenKind::Error),
                 },
                 Some('%') => match iter.next() {
                     Some('=') => Some(TokenKind::PercentEqual),
@@ -230,7 +293,7 @@ impl<'a> Iterator for Lexer<'a> {
                 _ => Some(TokenKind::Error),
             };
             match result {
-                Some(TokenKind::Skip) => {
+                Some(TokenKind::Whitespace | TokenKind::Comment) if !self.trivia => {
                     self.iter = iter.clone();
                     continue;
                 }
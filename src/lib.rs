@@ -2,18 +2,50 @@
 
 //! General-purpose parser for systemrdl files.
 
+#![forbid(unsafe_code)]
+
+mod access;
 pub mod ast;
 mod bits;
+mod compiler;
+mod counter;
+mod dependencies;
 mod elaborator;
 mod file_source;
+mod formatter;
+mod interner;
+mod interrupt;
 mod lexer;
 mod parser;
-mod string_arena;
+mod printer;
+mod properties;
 mod token;
 mod token_iter;
+pub mod token_tree;
+mod validate;
+pub mod visit;
+pub mod visit_mut;
+mod walker;
 
+pub use access::SwAccessPolicy;
 pub use bits::Bits;
-pub use elaborator::elaborate;
-pub use file_source::{FileSource, FsFileSource};
+pub use compiler::Compiler;
+pub use counter::{CounterSpec, CounterValue};
+pub use dependencies::{Dependencies, DependencyTracker};
+pub use elaborator::{
+    AddrMap, AddressMatch, Array, ElaborateOptions, Field, FieldImplementation, Mem, Node,
+    NodeContent, NodeId, PropertyValue, RegFile, Register, RegisterMasks, RootNamespace, Signal,
+    TypeDef, TypeId, elaborate, elaborate_with_options,
+};
+pub use file_source::{
+    FileSource, FsFileSource, MemFileSource, OverlayFileSource, Patch, SourceMap,
+};
+pub use formatter::format;
+pub use interner::{Interner, Symbol};
+pub use interrupt::{Interrupt, InterruptTree};
 pub use parser::parse;
+pub use printer::{PrintOptions, ToRdl};
 pub use token::*;
+pub use token_iter::ParseOptions;
+pub use validate::{Violation, validate};
+pub use walker::{Instance, Listener, Walker, WalkerAction};
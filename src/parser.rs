@@ -3,7 +3,7 @@
 //! Contains the winnow parser production rules for the SystemRDL language.
 
 use crate::ast::*;
-use crate::lexer::Lexer;
+use crate::lexer::{Lexer, LineTracker};
 use crate::token::TokenKind;
 use crate::token::{Token, Tokens};
 use winnow::combinator::{alt, fail, opt, preceded, repeat, separated, terminated};
@@ -97,7 +97,9 @@ fn integer_type_bit(i: &mut Tokens<'_>) -> Result<IntegerType> {
 
 // integer_atom_type ::= longint
 fn integer_type_longint(i: &mut Tokens<'_>) -> Result<IntegerType> {
-    TokenKind::Longint.parse_next(i).map(|_| IntegerType::Bit)
+    TokenKind::Longint
+        .parse_next(i)
+        .map(|_| IntegerType::Longint)
 }
 
 // simple_type ::= integer_type
@@ -1227,7 +1229,7 @@ fn constant_expr_continue(i: &mut Tokens<'_>) -> Result<ConstantExprContinue> {
 //     constant_primary [ constant_expression_continue ]
 //   | unary_operator constant_primary [ constant_expression_continue ]
 
-fn constant_expr(i: &mut Tokens<'_>) -> Result<ConstantExpr> {
+pub(crate) fn constant_expr(i: &mut Tokens<'_>) -> Result<ConstantExpr> {
     alt((
         constant_expr_constant_primary,
         constant_expr_unary_operator_constant_primary,
@@ -1280,6 +1282,7 @@ fn component_inst_array_or_range(i: &mut Tokens<'_>) -> Result<ArrayOrRange> {
 //   [ += constant_expression ]
 //   [ %= constant_expression ]
 fn component_inst(i: &mut Tokens<'_>) -> Result<ComponentInst> {
+    let location = i.peek_token().map(|token| token.location.clone());
     let (id, array_or_range, equals, at, plus_equals, percent_equals) = (
         identifier,
         opt(component_inst_array_or_range),
@@ -1297,6 +1300,7 @@ fn component_inst(i: &mut Tokens<'_>) -> Result<ComponentInst> {
         at,
         plus_equals,
         percent_equals,
+        location,
     })
 }
 
@@ -1679,7 +1683,20 @@ pub(crate) fn root(i: &mut Tokens<'_>) -> Result<Root> {
 
 /// Lex string to tokens
 pub(crate) fn tokens<'s>(i: &mut &'s str) -> Result<Vec<Token<'s>>> {
-    repeat(1.., token).parse_next(i)
+    let input = *i;
+    let mut tokens: Vec<Token> = repeat(1.., token).parse_next(i)?;
+    let mut lines = LineTracker::new(input);
+    for token in &mut tokens {
+        let (line, column) =
+            lines.line_column(token.raw.as_ptr() as usize - input.as_ptr() as usize);
+        token.location.line = line;
+        token.location.column = column;
+    }
+    // trailing whitespace and comments
+    if Lexer::lossless(i).all(|t| t.is_trivia()) {
+        *i = "";
+    }
+    Ok(tokens)
 }
 
 pub(crate) fn token<'s>(i: &mut &'s str) -> Result<Token<'s>> {
@@ -1691,6 +1708,7 @@ pub(crate) fn token<'s>(i: &mut &'s str) -> Result<Token<'s>> {
             let token = Token {
                 kind,
                 raw: &i[l.span()],
+                location: Default::default(),
             };
             *i = &i[r.end..];
             Ok(token)
@@ -0,0 +1,171 @@
+// Licensed under the Apache-2.0 license
+
+//! The components that each built-in property applies to.
+
+use crate::ast::ComponentType;
+
+const FIELD: u8 = 1 << 0;
+const REG: u8 = 1 << 1;
+const REGFILE: u8 = 1 << 2;
+const ADDRMAP: u8 = 1 << 3;
+const MEM: u8 = 1 << 4;
+const SIGNAL: u8 = 1 << 5;
+const ALL: u8 = FIELD | REG | REGFILE | ADDRMAP | MEM | SIGNAL;
+
+/// Built-in properties and the components they can be assigned to
+/// (SystemRDL 2.0 sections 5.2.1 and 6 to 12). The interrupt modifiers are
+/// included under their own names, which is how the elaborator records them.
+const BUILTIN: &[(&str, u8)] = &[
+    // universal
+    ("name", ALL),
+    ("desc", ALL),
+    ("ispresent", ALL),
+    // structural
+    ("dontcompare", FIELD | REG | REGFILE | ADDRMAP),
+    ("donttest", FIELD | REG | REGFILE | ADDRMAP),
+    ("hdl_path", REG | REGFILE | ADDRMAP),
+    ("hdl_path_gate", REG | REGFILE | ADDRMAP),
+    ("hdl_path_slice", FIELD | MEM),
+    ("hdl_path_gate_slice", FIELD | MEM),
+    ("errextbus", REG | REGFILE | ADDRMAP),
+    ("sharedextbus", REGFILE | ADDRMAP),
+    ("alignment", REGFILE | ADDRMAP),
+    ("sw", FIELD | MEM),
+    // field
+    ("hw", FIELD),
+    ("reset", FIELD),
+    ("resetsignal", FIELD),
+    ("fieldwidth", FIELD),
+    ("rclr", FIELD),
+    ("rset", FIELD),
+    ("onread", FIELD),
+    ("woclr", FIELD),
+    ("woset", FIELD),
+    ("onwrite", FIELD),
+    ("swwe", FIELD),
+    ("swwel", FIELD),
+    ("swmod", FIELD),
+    ("swacc", FIELD),
+    ("singlepulse", FIELD),
+    ("we", FIELD),
+    ("wel", FIELD),
+    ("anded", FIELD),
+    ("ored", FIELD),
+    ("xored", FIELD),
+    ("hwclr", FIELD),
+    ("hwset", FIELD),
+    ("hwenable", FIELD),
+    ("hwmask", FIELD),
+    ("next", FIELD),
+    ("precedence", FIELD),
+    ("paritycheck", FIELD),
+    ("encode", FIELD),
+    ("counter", FIELD),
+    ("incr", FIELD),
+    ("incrvalue", FIELD),
+    ("incrwidth", FIELD),
+    ("decr", FIELD),
+    ("decrvalue", FIELD),
+    ("decrwidth", FIELD),
+    ("incrsaturate", FIELD),
+    ("decrsaturate", FIELD),
+    ("incrthreshold", FIELD),
+    ("decrthreshold", FIELD),
+    ("saturate", FIELD),
+    ("threshold", FIELD),
+    ("overflow", FIELD),
+    ("underflow", FIELD),
+    ("intr", FIELD | REG),
+    ("level", FIELD),
+    ("posedge", FIELD),
+    ("negedge", FIELD),
+    ("bothedge", FIELD),
+    ("nonsticky", FIELD),
+    ("enable", FIELD),
+    ("mask", FIELD),
+    ("haltenable", FIELD),
+    ("haltmask", FIELD),
+    ("sticky", FIELD),
+    ("stickybit", FIELD),
+    // reg
+    ("regwidth", REG),
+    ("accesswidth", REG),
+    ("shared", REG),
+    ("halt", REG),
+    // addrmap
+    ("addressing", ADDRMAP),
+    ("bigendian", ADDRMAP),
+    ("littleendian", ADDRMAP),
+    ("lsb0", ADDRMAP),
+    ("msb0", ADDRMAP),
+    ("rsvdset", ADDRMAP),
+    ("rsvdsetX", ADDRMAP),
+    ("bridge", ADDRMAP),
+    // mem
+    ("mementries", MEM),
+    ("memwidth", MEM),
+    // signal
+    ("signalwidth", SIGNAL),
+    ("sync", SIGNAL),
+    ("async", SIGNAL),
+    ("cpuif_reset", SIGNAL),
+    ("field_reset", SIGNAL),
+    ("activelow", SIGNAL),
+    ("activehigh", SIGNAL),
+];
+
+/// Whether `property` can be assigned to a component of the given type.
+/// User-defined properties are assumed to apply everywhere.
+pub(crate) fn applies_to(property: &str, component_type: ComponentType) -> bool {
+    let component = match component_type {
+        ComponentType::Field => FIELD,
+        ComponentType::Reg => REG,
+        ComponentType::RegFile => REGFILE,
+        ComponentType::AddrMap => ADDRMAP,
+        ComponentType::Mem => MEM,
+        ComponentType::Signal => SIGNAL,
+        ComponentType::Enum | ComponentType::EnumVariant | ComponentType::Constraint => {
+            return false;
+        }
+    };
+    BUILTIN
+        .iter()
+        .find(|(name, _)| *name == property)
+        .is_none_or(|(_, components)| components & component != 0)
+}
+
+/// Whether a component of type `child` can be instantiated within one of
+/// type `parent`.
+pub(crate) fn can_contain(parent: ComponentType, child: ComponentType) -> bool {
+    use ComponentType::*;
+    match child {
+        Field => parent == Reg,
+        Reg => matches!(parent, RegFile | AddrMap | Mem),
+        RegFile => matches!(parent, RegFile | AddrMap),
+        AddrMap | Mem => parent == AddrMap,
+        Signal => matches!(parent, Reg | RegFile | AddrMap),
+        Enum | EnumVariant | Constraint => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_applies_to() {
+        assert!(applies_to("sw", ComponentType::Field));
+        assert!(applies_to("sw", ComponentType::Mem));
+        assert!(!applies_to("sw", ComponentType::Reg));
+        assert!(applies_to("regwidth", ComponentType::Reg));
+        assert!(!applies_to("regwidth", ComponentType::AddrMap));
+        assert!(applies_to("desc", ComponentType::Signal));
+        assert!(applies_to("my_udp", ComponentType::RegFile));
+
+        assert!(can_contain(ComponentType::Reg, ComponentType::Field));
+        assert!(!can_contain(ComponentType::RegFile, ComponentType::Field));
+        assert!(can_contain(ComponentType::Mem, ComponentType::Reg));
+        assert!(!can_contain(ComponentType::RegFile, ComponentType::AddrMap));
+        assert!(!can_contain(ComponentType::Mem, ComponentType::Signal));
+    }
+}
@@ -3,14 +3,36 @@
 use crate::Bits;
 use crate::ast::{AccessType, AddressingType, OnReadType, OnWriteType, PrecedenceType};
 use std::fmt::Display;
+use std::path::Path;
+use std::sync::Arc;
 use winnow::Parser;
 use winnow::Result;
 use winnow::{error::ContextError, stream::TokenSlice, token::literal};
 
+/// Where something appears in the source, with its line and column counted
+/// from 1.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SourceLocation {
+    /// The file it appears in, or `None` if it was parsed from a string.
+    pub file: Option<Arc<Path>>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+        }
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
 #[derive(Clone, PartialEq, Eq)]
 pub struct Token<'s> {
     pub kind: TokenKind<'s>,
     pub raw: &'s str,
+    pub location: SourceLocation,
 }
 
 impl Display for Token<'_> {
@@ -147,7 +169,10 @@ pub enum TokenKind<'a> {
     ComponentWidth,
 
     EndOfFile,
-    Skip,
+    /// Whitespace, only produced by a lossless lexer.
+    Whitespace,
+    /// A line or block comment, only produced by a lossless lexer.
+    Comment,
 
     PreprocInclude,
     UnableToOpenFile(&'a str),
@@ -163,4 +188,9 @@ impl TokenKind<'_> {
     pub fn is_identifier(&self) -> bool {
         matches!(self, Self::Identifier(_))
     }
+
+    /// Whitespace and comments, which carry no meaning for the parser.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, Self::Whitespace | Self::Comment)
+    }
 }
@@ -3,9 +3,11 @@
 use anyhow::bail;
 
 use crate::file_source::FileSource;
-use crate::lexer::{Lexer, Span};
+use crate::lexer::{Lexer, Span, line_column};
 use crate::token::TokenKind;
+use same_file::is_same_file;
 use std::collections::{HashSet, VecDeque};
+use std::fmt::Write;
 use std::path::{Path, PathBuf};
 
 struct IncludeStackEntry<'a> {
@@ -23,6 +25,17 @@ pub fn parse_str_literal(s: &str) -> Result<String, anyhow::Error> {
         .replace("\\\\", "\\"))
 }
 
+/// Settings for the preprocessor when parsing files.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ParseOptions {
+    /// Directories searched, in order, for `` `include `` files that are not
+    /// found relative to the including file.
+    pub include_paths: Vec<PathBuf>,
+    /// Names that are defined before the first file is read, as if by
+    /// `` `define ``.
+    pub defines: Vec<String>,
+}
+
 pub struct TokenIter<'a> {
     lex: Lexer<'a>,
     fifo: VecDeque<(TokenKind<'a>, Span)>,
@@ -33,7 +46,20 @@ pub struct TokenIter<'a> {
     file_source: Option<&'a dyn FileSource>,
     iter_stack: Vec<IncludeStackEntry<'a>>,
     defines: HashSet<String>,
+    include_paths: Vec<PathBuf>,
+    /// Every file read so far, so that a file included twice is only read
+    /// once.
+    files: Vec<(PathBuf, &'a str)>,
+    /// Why the last `` `include `` failed.
+    error: Option<anyhow::Error>,
 }
+
+/// Whether two paths name the same file. Paths that do not exist on disk, as
+/// with an in-memory [`FileSource`], are compared by name.
+fn same_path(a: &Path, b: &Path) -> bool {
+    a == b || is_same_file(a, b).unwrap_or_default()
+}
+
 impl<'a> TokenIter<'a> {
     pub fn from_path(file_source: &'a dyn FileSource, file_path: &Path) -> std::io::Result<Self> {
         let file_contents = file_source.read_to_string(file_path)?;
@@ -48,9 +74,18 @@ impl<'a> TokenIter<'a> {
             iter_stack: Vec::new(),
             file_source: Some(file_source),
             defines: HashSet::new(),
+            include_paths: Vec::new(),
+            files: vec![(file_path.into(), file_contents)],
+            error: None,
         })
     }
 
+    pub fn with_options(mut self, options: &ParseOptions) -> Self {
+        self.include_paths = options.include_paths.clone();
+        self.defines.extend(options.defines.iter().cloned());
+        self
+    }
+
     #[allow(unused)]
     pub fn from_str(s: &'a str) -> Self {
         Self {
@@ -63,7 +98,60 @@ impl<'a> TokenIter<'a> {
             file_source: Default::default(),
             iter_stack: Default::default(),
             defines: Default::default(),
+            include_paths: Default::default(),
+            files: Default::default(),
+            error: Default::default(),
+        }
+    }
+
+    /// The error behind the last [`TokenKind::UnableToOpenFile`] or
+    /// [`TokenKind::IncludeDepthLimitReached`] token, with the chain of files
+    /// that led to the failing `` `include ``.
+    pub fn take_error(&mut self) -> Option<anyhow::Error> {
+        self.error.take()
+    }
+
+    /// Record an include failure, listing the location of the current
+    /// `` `include `` and of each directive that led to it.
+    fn include_error(&mut self, message: String) {
+        let mut message = message;
+        let file_source = self.file_source;
+        let location = |path: &Path, contents: &str, span: Span| {
+            let (line, column) = file_source
+                .and_then(|file_source| file_source.original_location(path, span.start))
+                .unwrap_or_else(|| line_column(contents, span.start));
+            format!("{}:{line}:{column}", path.display())
+        };
+        let here = location(
+            &self.current_file_path,
+            self.current_file_contents,
+            self.lex.span(),
+        );
+        write!(message, "\n    at {here}").unwrap();
+        for entry in self.iter_stack.iter().rev() {
+            let from = location(&entry.file_path, entry.file_contents, entry.lex.span());
+            write!(message, "\n    included from {from}").unwrap();
         }
+        self.error = Some(anyhow::Error::msg(message));
+    }
+
+    /// Read a file, or reuse its contents if it has been read before.
+    /// Returns `Ok(None)` if the file does not exist.
+    fn read(
+        &mut self,
+        file_source: &'a dyn FileSource,
+        path: &Path,
+    ) -> std::io::Result<Option<&'a str>> {
+        if let Some((_, contents)) = self.files.iter().find(|(p, _)| same_path(p, path)) {
+            return Ok(Some(contents));
+        }
+        let contents = match file_source.read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        self.files.push((path.into(), contents));
+        Ok(Some(contents))
     }
 
     fn lex_next(&mut self) -> Option<TokenKind<'a>> {
@@ -96,21 +184,59 @@ impl<'a> TokenIter<'a> {
                         return Some(TokenKind::Error);
                     };
                     let Some(file_source) = self.file_source else {
+                        self.include_error(format!(
+                            "cannot include {filename} when parsing from a string"
+                        ));
                         return Some(TokenKind::UnableToOpenFile(filename));
                     };
                     let Ok(parsed_filename) = parse_str_literal(filename) else {
+                        self.include_error(format!("invalid include file name {filename}"));
                         return Some(TokenKind::UnableToOpenFile(filename));
                     };
-                    let file_path = if let Some(parent) = self.current_file_path.parent() {
-                        parent.join(parsed_filename)
+                    let relative = if let Some(parent) = self.current_file_path.parent() {
+                        parent.join(&parsed_filename)
                     } else {
-                        PathBuf::from(parsed_filename)
+                        PathBuf::from(&parsed_filename)
                     };
-
-                    let Ok(file_contents) = file_source.read_to_string(&file_path) else {
+                    let candidates: Vec<_> = std::iter::once(relative)
+                        .chain(
+                            self.include_paths
+                                .iter()
+                                .map(|dir| dir.join(&parsed_filename)),
+                        )
+                        .collect();
+                    let mut found = None;
+                    for path in candidates {
+                        match self.read(file_source, &path) {
+                            Ok(Some(contents)) => {
+                                found = Some((path, contents));
+                                break;
+                            }
+                            Ok(None) => {}
+                            Err(e) => {
+                                self.include_error(format!("{}: {e}", path.display()));
+                                return Some(TokenKind::UnableToOpenFile(filename));
+                            }
+                        }
+                    }
+                    let Some((file_path, file_contents)) = found else {
+                        self.include_error(format!("cannot open include file {filename}"));
                         return Some(TokenKind::UnableToOpenFile(filename));
                     };
+                    let open = std::iter::once(&self.current_file_path)
+                        .chain(self.iter_stack.iter().map(|entry| &entry.file_path));
+                    if open.clone().any(|path| same_path(path, &file_path)) {
+                        self.include_error(format!(
+                            "include cycle: {} includes {}, which is already being included",
+                            self.current_file_path.display(),
+                            file_path.display()
+                        ));
+                        return Some(TokenKind::UnableToOpenFile(filename));
+                    }
                     if self.iter_stack.len() >= INCLUDE_DEPTH_LIMIT {
+                        self.include_error(format!(
+                            "includes nested more than {INCLUDE_DEPTH_LIMIT} deep"
+                        ));
                         return Some(TokenKind::IncludeDepthLimitReached);
                     }
                     let old_lex = std::mem::replace(&mut self.lex, Lexer::new(file_contents));
@@ -171,3 +297,92 @@ impl<'a> TokenIter<'a> {
         &self.current_file_path
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ast::Root;
+    use crate::file_source::MemFileSource;
+
+    #[test]
+    fn test_include_paths_and_defines() {
+        let files = MemFileSource::from_entries(&[
+            (
+                "src/top.rdl".into(),
+                "`include \"types.rdl\"\n`ifndef SMALL\naddrmap { ctrl_t a; } big;\n`endif\naddrmap { ctrl_t b; } top;".into(),
+            ),
+            ("lib/types.rdl".into(), "reg ctrl_t { field {} EN; };".into()),
+        ]);
+        let path = Path::new("src/top.rdl");
+        assert!(Root::from_file(&files, path).is_err());
+
+        let options = ParseOptions {
+            include_paths: vec!["lib".into()],
+            defines: vec![],
+        };
+        let root = Root::from_file_with_options(&files, path, &options).unwrap();
+        assert_eq!(root.descriptions.len(), 3);
+
+        let options = ParseOptions {
+            include_paths: vec!["lib".into()],
+            defines: vec!["SMALL".into()],
+        };
+        let root = Root::from_file_with_options(&files, path, &options).unwrap();
+        assert_eq!(root.descriptions.len(), 2);
+    }
+
+    #[test]
+    fn test_include_errors() {
+        let files = MemFileSource::from_entries(&[
+            (
+                "top.rdl".into(),
+                "reg r_t { field {} f; };\n`include \"a.rdl\"".into(),
+            ),
+            ("a.rdl".into(), "\n  `include \"b.rdl\"".into()),
+            ("b.rdl".into(), "`include \"top.rdl\"".into()),
+        ]);
+        let err = Root::from_file(&files, Path::new("top.rdl")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "include cycle: b.rdl includes top.rdl, which is already being included
+    at b.rdl:1:10
+    included from a.rdl:2:12
+    included from top.rdl:2:10"
+        );
+    }
+
+    #[test]
+    fn test_include_twice() {
+        struct Counting(MemFileSource, std::cell::Cell<usize>);
+        impl FileSource for Counting {
+            fn read_to_string(&self, path: &Path) -> std::io::Result<&str> {
+                self.1.set(self.1.get() + 1);
+                self.0.read_to_string(path)
+            }
+        }
+        let files = Counting(
+            MemFileSource::from_entries(&[
+                (
+                    "top.rdl".into(),
+                    "addrmap a { `include \"regs.rdl\" };\naddrmap b { `include \"regs.rdl\" };"
+                        .into(),
+                ),
+                ("regs.rdl".into(), "reg { field {} f; } ctrl;".into()),
+                (
+                    "bad.rdl".into(),
+                    "`include \"regs.rdl\"\n`include \"nope.rdl\"".into(),
+                ),
+            ]),
+            Default::default(),
+        );
+        let root = Root::from_file(&files, Path::new("top.rdl")).unwrap();
+        assert_eq!(root.descriptions.len(), 2);
+        assert_eq!(files.1.get(), 2);
+
+        let err = Root::from_file(&files, Path::new("bad.rdl")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot open include file \"nope.rdl\"\n    at bad.rdl:2:10"
+        );
+    }
+}
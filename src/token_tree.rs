@@ -0,0 +1,499 @@
+// Licensed under the Apache-2.0 license
+
+//! Lossless token tree.
+//!
+//! Unlike the [`ast`](crate::ast), the token tree keeps every byte of the
+//! input, including whitespace and comments, so printing it reproduces the
+//! source exactly. This makes it the starting point for tools such as
+//! formatters that need to leave the rest of a file untouched.
+//!
+//! The tree only groups the tokens of the source by punctuation; it is not
+//! built by the parser, so its nodes say nothing about the grammar. The root
+//! and the inside of each `{ }` block is a list of statements, each ending in
+//! a `;`, whether it defines a component, instantiates one or assigns a
+//! property, and brackets and parentheses are grouped. Whitespace and comments
+//! before a statement belong to that statement, while a comment on the same
+//! line after its `;` is kept as part of it.
+//!
+//! [`SyntaxNode::to_ast`] derives the [`ast`](crate::ast) by parsing the
+//! tokens of a tree again, without trivia. Nothing links the nodes of the
+//! resulting AST back to the nodes of the tree.
+//!
+//! ```
+//! let source = "// control\nreg { field {} EN; } CTRL; // trailing\n";
+//! let tree = systemrdl::token_tree::parse(source);
+//! assert_eq!(tree.to_string(), source);
+//! assert_eq!(tree.to_ast().unwrap(), systemrdl::parse(source).unwrap());
+//! ```
+
+use crate::ast::Root;
+use crate::lexer::{Lexer, LineTracker, Span};
+use crate::parser::root;
+use crate::token::{SourceLocation, Token, TokenKind, Tokens};
+use std::fmt::{self, Display};
+use winnow::Parser;
+
+/// The kind of a [`SyntaxNode`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NodeKind {
+    /// A whole file.
+    Root,
+    /// Tokens up to and including a `;`, or to the end of the line for a
+    /// preprocessor directive, together with the whitespace and comments that
+    /// precede it.
+    Statement,
+    /// `{`, the statements within it and `}`.
+    Block,
+    /// `(`, `[` or `'{` and the tokens up to the matching close.
+    Group,
+}
+
+/// A token together with its source text.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SyntaxToken<'a> {
+    kind: TokenKind<'a>,
+    text: &'a str,
+    span: Span,
+}
+
+impl<'a> SyntaxToken<'a> {
+    pub fn kind(&self) -> &TokenKind<'a> {
+        &self.kind
+    }
+
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// Byte range of the token in the source.
+    pub fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        self.kind.is_trivia()
+    }
+}
+
+impl Display for SyntaxToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.text)
+    }
+}
+
+/// A child of a [`SyntaxNode`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SyntaxElement<'a> {
+    Node(SyntaxNode<'a>),
+    Token(SyntaxToken<'a>),
+}
+
+impl<'a> SyntaxElement<'a> {
+    pub fn as_node(&self) -> Option<&SyntaxNode<'a>> {
+        match self {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        }
+    }
+
+    pub fn as_token(&self) -> Option<&SyntaxToken<'a>> {
+        match self {
+            SyntaxElement::Node(_) => None,
+            SyntaxElement::Token(token) => Some(token),
+        }
+    }
+}
+
+impl Display for SyntaxElement<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyntaxElement::Node(node) => node.fmt(f),
+            SyntaxElement::Token(token) => token.fmt(f),
+        }
+    }
+}
+
+/// An interior node of the token tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SyntaxNode<'a> {
+    kind: NodeKind,
+    children: Vec<SyntaxElement<'a>>,
+}
+
+impl<'a> SyntaxNode<'a> {
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    pub fn children(&self) -> &[SyntaxElement<'a>] {
+        &self.children
+    }
+
+    /// Child nodes, skipping tokens.
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode<'a>> {
+        self.children.iter().filter_map(SyntaxElement::as_node)
+    }
+
+    /// Every token beneath this node, including trivia, in source order.
+    pub fn tokens(&self) -> impl Iterator<Item = &SyntaxToken<'a>> {
+        let mut stack = vec![self.children.iter()];
+        std::iter::from_fn(move || {
+            loop {
+                match stack.last_mut()?.next() {
+                    Some(SyntaxElement::Token(token)) => return Some(token),
+                    Some(SyntaxElement::Node(node)) => stack.push(node.children.iter()),
+                    None => {
+                        stack.pop();
+                    }
+                }
+            }
+        })
+    }
+
+    /// Byte range covered by this node, or `None` if it is empty.
+    pub fn span(&self) -> Option<Span> {
+        let start = self.tokens().next()?.span.start;
+        let end = self.tokens().last()?.span.end;
+        Some(start..end)
+    }
+
+    /// The source text of this node, without leading or trailing whitespace.
+    pub fn trimmed_text(&self) -> String {
+        let tokens: Vec<_> = self.tokens().collect();
+        let is_text = |t: &&&SyntaxToken| t.kind != TokenKind::Whitespace;
+        let Some(first) = tokens.iter().position(|t| is_text(&t)) else {
+            return String::new();
+        };
+        let last = tokens.iter().rposition(|t| is_text(&t)).unwrap();
+        tokens[first..=last].iter().map(|t| t.text).collect()
+    }
+
+    /// Parse the tokens of this tree, with trivia removed, into an AST using
+    /// the ordinary parser.
+    ///
+    /// Source locations in the AST are counted from the start of this node.
+    pub fn to_ast(&self) -> Result<Root, anyhow::Error> {
+        let text = self.to_string();
+        let start = self.span().map_or(0, |span| span.start);
+        let mut lines = LineTracker::new(&text);
+        let tokens: Vec<Token> = self
+            .tokens()
+            .filter(|t| !t.is_trivia())
+            .map(|t| {
+                let (line, column) = lines.line_column(t.span.start - start);
+                Token {
+                    kind: t.kind.clone(),
+                    raw: t.text,
+                    location: SourceLocation {
+                        file: None,
+                        line,
+                        column,
+                    },
+                }
+            })
+            .collect();
+        let tokens = Tokens::new(&tokens);
+        root.parse(tokens).map_err(|e| match e.input().first() {
+            Some(t) => {
+                anyhow::format_err!("Error parsing input at: `{}` token: {:?}", t.raw, t.kind)
+            }
+            None => anyhow::format_err!("Error parsing input: unexpected end of input"),
+        })
+    }
+}
+
+impl Display for SyntaxNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in self.tokens() {
+            f.write_str(token.text)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds the token tree of `input`.
+///
+/// This never fails: text that does not lex is kept as
+/// [`TokenKind::Error`] tokens and unbalanced brackets are closed at the end of
+/// the input, so the tree always prints back to `input`. Preprocessor
+/// directives are kept as ordinary tokens and are not expanded.
+pub fn parse(input: &str) -> SyntaxNode<'_> {
+    let mut lexer = Lexer::lossless(input);
+    let mut tokens = vec![];
+    let mut end = 0;
+    while let Some(kind) = lexer.next() {
+        let span = lexer.span();
+        end = span.end;
+        tokens.push(SyntaxToken {
+            kind,
+            text: &input[span.clone()],
+            span,
+        });
+    }
+    // the lexer gives up at the first character it cannot handle
+    if end < input.len() {
+        tokens.push(SyntaxToken {
+            kind: TokenKind::Error,
+            text: &input[end..],
+            span: end..input.len(),
+        });
+    }
+    let mut builder = Builder { tokens, pos: 0 };
+    let children = builder.statements(None);
+    debug_assert_eq!(builder.pos, builder.tokens.len());
+    SyntaxNode {
+        kind: NodeKind::Root,
+        children,
+    }
+}
+
+struct Builder<'a> {
+    tokens: Vec<SyntaxToken<'a>>,
+    pos: usize,
+}
+
+impl<'a> Builder<'a> {
+    fn peek(&self) -> Option<&TokenKind<'a>> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    fn bump(&mut self) -> SyntaxElement<'a> {
+        let token = self.tokens[self.pos].clone();
+        self.pos += 1;
+        SyntaxElement::Token(token)
+    }
+
+    /// Statements up to `close` (which is not consumed) or the end of input.
+    fn statements(&mut self, close: Option<&TokenKind>) -> Vec<SyntaxElement<'a>> {
+        let mut children = vec![];
+        loop {
+            let start = self.pos;
+            while self.peek().is_some_and(TokenKind::is_trivia) {
+                self.pos += 1;
+            }
+            match self.peek() {
+                Some(kind) if Some(kind) != close => {
+                    self.pos = start;
+                    let statement = self.statement(close);
+                    children.push(SyntaxElement::Node(statement));
+                }
+                _ => {
+                    // trivia at the end of a block or file
+                    let end = self.pos;
+                    self.pos = start;
+                    while self.pos < end {
+                        children.push(self.bump());
+                    }
+                    return children;
+                }
+            }
+        }
+    }
+
+    fn statement(&mut self, close: Option<&TokenKind>) -> SyntaxNode<'a> {
+        // preprocessor directives end at the end of the line
+        let directive = self.tokens[self.pos..]
+            .iter()
+            .find(|t| !t.is_trivia())
+            .is_some_and(|t| {
+                matches!(
+                    t.kind,
+                    TokenKind::PreprocInclude
+                        | TokenKind::PreprocDefine
+                        | TokenKind::PreprocIfndef
+                        | TokenKind::PreprocEndif
+                )
+            });
+        let mut children = vec![];
+        let mut started = false;
+        while let Some(kind) = self.peek() {
+            match kind {
+                kind if Some(kind) == close => break,
+                TokenKind::Whitespace
+                    if directive && started && self.tokens[self.pos].text.contains('\n') =>
+                {
+                    break;
+                }
+                kind if kind.is_trivia() => children.push(self.bump()),
+                _ if directive => {
+                    started = true;
+                    children.push(self.bump());
+                }
+                TokenKind::BraceOpen => children.push(SyntaxElement::Node(self.block())),
+                TokenKind::ParenOpen | TokenKind::BracketOpen | TokenKind::Quote => {
+                    children.push(SyntaxElement::Node(self.group()))
+                }
+                TokenKind::Semicolon => {
+                    children.push(self.bump());
+                    self.trailing_comment(&mut children);
+                    break;
+                }
+                _ => children.push(self.bump()),
+            }
+        }
+        SyntaxNode {
+            kind: NodeKind::Statement,
+            children,
+        }
+    }
+
+    /// Comments on the same line after the end of a statement.
+    fn trailing_comment(&mut self, children: &mut Vec<SyntaxElement<'a>>) {
+        let mut end = self.pos;
+        for (i, token) in self.tokens[self.pos..].iter().enumerate() {
+            match token.kind {
+                TokenKind::Comment => end = self.pos + i + 1,
+                TokenKind::Whitespace if !token.text.contains('\n') => continue,
+                _ => break,
+            }
+        }
+        while self.pos < end {
+            children.push(self.bump());
+        }
+    }
+
+    fn block(&mut self) -> SyntaxNode<'a> {
+        let mut children = vec![self.bump()];
+        children.extend(self.statements(Some(&TokenKind::BraceClose)));
+        if self.peek() == Some(&TokenKind::BraceClose) {
+            children.push(self.bump());
+        }
+        SyntaxNode {
+            kind: NodeKind::Block,
+            children,
+        }
+    }
+
+    fn group(&mut self) -> SyntaxNode<'a> {
+        let open = self.bump();
+        let close = match open.as_token().map(|t| &t.kind) {
+            Some(TokenKind::ParenOpen) => TokenKind::ParenClose,
+            Some(TokenKind::BracketOpen) => TokenKind::BracketClose,
+            _ => TokenKind::BraceClose,
+        };
+        let mut children = vec![open];
+        // a quote only starts a group when it is followed by a brace
+        if close == TokenKind::BraceClose {
+            if self.peek() != Some(&TokenKind::BraceOpen) {
+                return SyntaxNode {
+                    kind: NodeKind::Group,
+                    children,
+                };
+            }
+            children.push(self.bump());
+        }
+        while let Some(kind) = self.peek() {
+            match kind {
+                kind if *kind == close => {
+                    children.push(self.bump());
+                    break;
+                }
+                TokenKind::BraceOpen => children.push(SyntaxElement::Node(self.block())),
+                TokenKind::ParenOpen | TokenKind::BracketOpen | TokenKind::Quote => {
+                    children.push(SyntaxElement::Node(self.group()))
+                }
+                _ => children.push(self.bump()),
+            }
+        }
+        SyntaxNode {
+            kind: NodeKind::Group,
+            children,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"property p { type = number; component = reg; };
+
+// A block with comments
+addrmap {
+    /* control
+       register */
+    reg {
+        field { sw = rw; } EN[0:0] = 1'b0; // enable
+        field {} MODE[3:1];
+    } CTRL @ 0x10;
+
+    reg { field {} VALUE[8]; } regs[2] @ 0x20 += 0x8;
+    enum e { A = 2'd1; };
+    struct s { string a; };
+    CTRL.EN->reset = 1 ? 2 : 0; /* after */  /* and after */
+} top;
+// trailing"#;
+
+    #[test]
+    fn test_lossless() {
+        let tree = parse(INPUT);
+        assert_eq!(tree.to_string(), INPUT);
+        assert_eq!(tree.span(), Some(0..INPUT.len()));
+        assert_eq!(tree.to_ast().unwrap(), crate::parse(INPUT).unwrap());
+
+        for input in [
+            "",
+            "  \n",
+            "reg { f(1, [2]);",
+            "} ) ] ;",
+            "a $ + b",
+            "/* open",
+        ] {
+            assert_eq!(parse(input).to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_structure() {
+        let tree = parse(INPUT);
+        let statements: Vec<_> = tree.child_nodes().collect();
+        assert_eq!(statements.len(), 2);
+        assert_eq!(statements[1].kind(), NodeKind::Statement);
+        assert!(statements[1].to_string().starts_with("\n\n// A block"));
+        let trailing: String = tree.children()[2..].iter().map(|e| e.to_string()).collect();
+        assert_eq!(trailing, "\n// trailing");
+
+        let block = statements[1].child_nodes().next().unwrap();
+        assert_eq!(block.kind(), NodeKind::Block);
+        let inner: Vec<_> = block.child_nodes().map(|s| s.trimmed_text()).collect();
+        assert_eq!(inner[0].lines().next(), Some("/* control"));
+        assert_eq!(
+            inner[1],
+            "reg { field {} VALUE[8]; } regs[2] @ 0x20 += 0x8;"
+        );
+        assert_eq!(
+            inner[4],
+            "CTRL.EN->reset = 1 ? 2 : 0; /* after */  /* and after */"
+        );
+
+        let reg = block.child_nodes().next().unwrap();
+        let fields: Vec<_> = reg
+            .child_nodes()
+            .next()
+            .unwrap()
+            .child_nodes()
+            .map(|s| s.trimmed_text())
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "field { sw = rw; } EN[0:0] = 1'b0; // enable",
+                "field {} MODE[3:1];"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_directives() {
+        let tree = parse("`include \"types.rdl\" // types\n`define X\nreg { field {} a; } b;\n");
+        let statements: Vec<_> = tree.child_nodes().map(|s| s.trimmed_text()).collect();
+        assert_eq!(
+            statements,
+            [
+                "`include \"types.rdl\" // types",
+                "`define X",
+                "reg { field {} a; } b;"
+            ]
+        );
+    }
+}
@@ -0,0 +1,874 @@
+// Licensed under the Apache-2.0 license
+
+//! Checks of the semantic rules of the SystemRDL spec that can only be made
+//! once a design has been elaborated.
+
+use crate::ast::PrimaryLiteral;
+use crate::{Node, NodeContent, NodeId, PropertyValue, RootNamespace, Signal, SourceLocation};
+
+/// A violation of one of the semantic rules of the SystemRDL spec.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Violation {
+    /// The node that breaks the rule.
+    pub node: NodeId,
+    /// The node it conflicts with, for rules about pairs of nodes such as
+    /// overlapping fields.
+    pub other: Option<NodeId>,
+    /// A description of the problem that names every node involved by its
+    /// hierarchical path, along with its bits or addresses.
+    pub message: String,
+    /// Where `node` is instantiated in the source, if known.
+    pub location: Option<SourceLocation>,
+    /// Where `other` is instantiated in the source, if known.
+    pub other_location: Option<SourceLocation>,
+}
+
+/// The message, prefixed by the location of the node and followed by that
+/// of the other node, e.g. `top.rdl:3:9: field top.CTRL.B [5:2] overlaps
+/// field top.CTRL.A [3:0] (see top.rdl:2:9)`.
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(location) = &self.location {
+            write!(f, "{location}: ")?;
+        }
+        f.write_str(&self.message)?;
+        if let Some(other) = &self.other_location {
+            write!(f, " (see {other})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check an elaborated design, returning every violation found in
+/// depth-first order.
+///
+/// The rules checked are that:
+/// - fields fit within their register and do not overlap each other;
+/// - reset values fit within their field;
+/// - the `sw` and `hw` access of each field is a legal combination, and its
+///   other properties make sense for the implementation that implies (see
+///   [`Field::implementation`](crate::Field::implementation));
+/// - each field's side effects have a software access policy (see
+///   [`Field::sw_access_policy`](crate::Field::sw_access_policy));
+/// - interrupt properties are consistent (see
+///   [`Field::interrupt`](crate::Field::interrupt)), and each enable or mask
+///   is a field as wide as the interrupt;
+/// - counter properties are consistent (see
+///   [`Field::counter`](crate::Field::counter)), the nodes they refer to are
+///   fields, and references to a counter's outputs such as `cnt->overflow`
+///   name a counter that has them;
+/// - each `resetsignal` is a signal, and no component has more than one
+///   `field_reset` or `cpuif_reset` signal;
+/// - the `accesswidth` of a register is no wider than its `regwidth`, and
+///   the register is aligned to it so that no access crosses its boundary;
+/// - addressable siblings do not overlap;
+/// - each alias register has the width and array dimensions of its primary,
+///   its fields match fields of the primary in name and bits, and they do
+///   not have properties of the storage they share, such as `hw` or `reset`;
+/// - a `bridge` addrmap is at the top of the hierarchy and contains only
+///   addrmaps, of which it has at least two;
+/// - the virtual registers of a mem are as wide as its entries, fit within
+///   it, and give software no more access than the mem does;
+/// - array strides are at least the size of an element.
+pub fn validate(namespace: &RootNamespace) -> Vec<Violation> {
+    let mut validator = Validator {
+        namespace,
+        violations: Vec::new(),
+    };
+    for node in namespace.descendants() {
+        validator.check_stride(node);
+        validator.check_counter_outputs(node);
+        match node.content() {
+            NodeContent::Register(_) => {
+                validator.check_fields(node);
+                validator.check_accesswidth(node);
+                validator.check_reset_signals(node);
+                validator.check_alias(node);
+            }
+            NodeContent::Addrmap(_) => {
+                validator.check_overlaps(node);
+                validator.check_reset_signals(node);
+                validator.check_bridge(node);
+            }
+            NodeContent::RegFile(_) => {
+                validator.check_overlaps(node);
+                validator.check_reset_signals(node);
+            }
+            NodeContent::Field(_) => {
+                validator.check_reset(node);
+                validator.check_interrupt(node);
+                validator.check_counter(node);
+                validator.check_resetsignal(node);
+            }
+            NodeContent::Mem(_) => {
+                validator.check_overlaps(node);
+                validator.check_virtual_registers(node);
+            }
+            NodeContent::Signal(_) => {}
+        }
+    }
+    validator.violations
+}
+
+struct Validator<'a> {
+    namespace: &'a RootNamespace,
+    violations: Vec<Violation>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, node: &Node, other: Option<&Node>, message: String) {
+        self.violations.push(Violation {
+            node: node.id(),
+            other: other.map(Node::id),
+            message,
+            location: node.location().cloned(),
+            other_location: other.and_then(Node::location).cloned(),
+        });
+    }
+
+    /// The kind and path of a node, with its bits or its address range
+    /// within its parent, e.g. `field top.CTRL.EN [0:0]`.
+    fn describe(&self, node: &Node) -> String {
+        let path = self.namespace.path(node.id());
+        match node.content() {
+            NodeContent::Field(field) => format!("field {path} [{}:{}]", field.msb(), field.lsb()),
+            NodeContent::Signal(_) => format!("signal {path}"),
+            content => {
+                let kind = content.component_type();
+                let start = node.offset().unwrap_or_default();
+                let end = start + node.total_size().unwrap_or_default();
+                format!("{kind} {path} at {start:#x}..{end:#x}")
+            }
+        }
+    }
+
+    fn check_fields(&mut self, register: &Node) {
+        let NodeContent::Register(reg) = register.content() else {
+            return;
+        };
+        let mut fields: Vec<_> = self
+            .namespace
+            .children(register.id())
+            .filter_map(|node| match node.content() {
+                NodeContent::Field(field) => Some((node, field)),
+                _ => None,
+            })
+            .collect();
+        for (node, field) in &fields {
+            if field.msb() >= reg.width() {
+                let message = format!(
+                    "{} does not fit in {}, which is {} bits wide",
+                    self.describe(node),
+                    self.namespace.path(register.id()),
+                    reg.width()
+                );
+                self.report(node, Some(register), message);
+            }
+        }
+        fields.sort_by_key(|(_, field)| field.lsb());
+        for (i, (node, field)) in fields.iter().enumerate() {
+            for (other, other_field) in &fields[..i] {
+                if other_field.msb() >= field.lsb() {
+                    let message =
+                        format!("{} overlaps {}", self.describe(node), self.describe(other));
+                    self.report(node, Some(other), message);
+                }
+            }
+        }
+    }
+
+    fn check_accesswidth(&mut self, register: &Node) {
+        let NodeContent::Register(reg) = register.content() else {
+            return;
+        };
+        let Some(accesswidth) = number(register, "accesswidth") else {
+            return;
+        };
+        let path = self.namespace.path(register.id());
+        if accesswidth < 8 || !accesswidth.is_power_of_two() || accesswidth > reg.width() {
+            let message = format!(
+                "accesswidth of reg {path} must be a power of two from 8 to its regwidth of {}, got {accesswidth}",
+                reg.width()
+            );
+            self.report(register, None, message);
+            return;
+        }
+        let offset = register.offset().unwrap_or_default();
+        let stride = register.array().map_or(0, |array| array.stride());
+        let bytes = accesswidth / 8;
+        if !offset.is_multiple_of(bytes) || !stride.is_multiple_of(bytes) {
+            let message = format!(
+                "{} crosses an accesswidth boundary; it must be aligned to {bytes} bytes",
+                self.describe(register)
+            );
+            self.report(register, None, message);
+        }
+    }
+
+    fn check_overlaps(&mut self, parent: &Node) {
+        let mut children: Vec<_> = self
+            .namespace
+            .children(parent.id())
+            .filter_map(|node| {
+                let start = node.offset()?;
+                Some((node, start, start + node.total_size()?))
+            })
+            .filter(|(_, start, end)| start < end)
+            .collect();
+        children.sort_by_key(|(_, start, _)| *start);
+        for (i, (node, start, _)) in children.iter().enumerate() {
+            for (other, _, other_end) in &children[..i] {
+                if other_end > start {
+                    let message =
+                        format!("{} overlaps {}", self.describe(node), self.describe(other));
+                    self.report(node, Some(other), message);
+                }
+            }
+        }
+    }
+
+    fn check_alias(&mut self, alias: &Node) {
+        const STORAGE: [&str; 10] = [
+            "hw",
+            "reset",
+            "resetsignal",
+            "counter",
+            "intr",
+            "hwset",
+            "hwclr",
+            "we",
+            "wel",
+            "next",
+        ];
+        let NodeContent::Register(reg) = alias.content() else {
+            return;
+        };
+        let Some(primary_id) = reg.alias_of() else {
+            return;
+        };
+        let primary = &self.namespace[primary_id];
+        let NodeContent::Register(primary_reg) = primary.content() else {
+            return;
+        };
+        if reg.width() != primary_reg.width() {
+            let message = format!(
+                "alias {} is {} bits wide, but its primary {} is {} bits wide",
+                self.describe(alias),
+                reg.width(),
+                self.describe(primary),
+                primary_reg.width()
+            );
+            self.report(alias, Some(primary), message);
+        }
+        let dims = |node: &Node| node.array().map(|array| array.dims().to_vec());
+        if dims(alias) != dims(primary) {
+            let message = format!(
+                "alias {} does not have the array dimensions of its primary {}",
+                self.describe(alias),
+                self.describe(primary)
+            );
+            self.report(alias, Some(primary), message);
+        }
+        for node in self.namespace.children(alias.id()) {
+            let NodeContent::Field(field) = node.content() else {
+                continue;
+            };
+            let matching = self.namespace.children(primary_id).find(|other| {
+                other.name() == node.name()
+                    && matches!(other.content(), NodeContent::Field(f) if f.lsb() == field.lsb() && f.width() == field.width())
+            });
+            if matching.is_none() {
+                let message = format!(
+                    "{} of alias {} has no field of the same name and bits in {}",
+                    self.describe(node),
+                    self.namespace.path(alias.id()),
+                    self.namespace.path(primary_id)
+                );
+                self.report(node, Some(primary), message);
+            }
+            for property in STORAGE {
+                if field.is_assigned(property) {
+                    let message = format!(
+                        "{} of alias {} cannot have {property}, which belongs to its primary",
+                        self.describe(node),
+                        self.namespace.path(alias.id())
+                    );
+                    self.report(node, None, message);
+                }
+            }
+        }
+    }
+
+    /// A bridge addrmap joins several addrmaps under one address space, so
+    /// it can only be the root, and only contains addrmaps (SystemRDL 2.0
+    /// section 13.4).
+    fn check_bridge(&mut self, node: &Node) {
+        if !matches!(
+            node.property("bridge"),
+            Some(PropertyValue::Literal(PrimaryLiteral::BooleanLiteral(true)))
+        ) {
+            return;
+        }
+        let path = self.namespace.path(node.id());
+        if let Some(parent) = node.parent() {
+            let message = format!(
+                "bridge addrmap {path} must be at the root, but it is within {}",
+                self.namespace.path(parent)
+            );
+            self.report(node, None, message);
+        }
+        let mut addrmaps = 0;
+        for child in self.namespace.children(node.id()) {
+            match child.content() {
+                NodeContent::Addrmap(_) => addrmaps += 1,
+                NodeContent::Signal(_) => {}
+                _ => {
+                    let message = format!(
+                        "bridge addrmap {path} can only contain addrmaps, not {}",
+                        self.describe(child)
+                    );
+                    self.report(child, Some(node), message);
+                }
+            }
+        }
+        if addrmaps < 2 {
+            let message =
+                format!("bridge addrmap {path} must contain at least two addrmaps, got {addrmaps}");
+            self.report(node, None, message);
+        }
+    }
+
+    fn check_reset(&mut self, node: &Node) {
+        let NodeContent::Field(field) = node.content() else {
+            return;
+        };
+        if let Err(e) = field.implementation() {
+            let message = format!("{}: {e}", self.describe(node));
+            self.report(node, None, message);
+        }
+        if let Err(e) = field.sw_access_policy() {
+            let message = format!("{}: {e}", self.describe(node));
+            self.report(node, None, message);
+        }
+        let reset = match node.property("reset") {
+            Some(PropertyValue::Literal(PrimaryLiteral::Number(value))) => *value,
+            Some(PropertyValue::Literal(PrimaryLiteral::Bits(bits))) => {
+                if bits.w() != field.width() {
+                    let message = format!(
+                        "reset value of {} is {} bits wide",
+                        self.describe(node),
+                        bits.w()
+                    );
+                    self.report(node, None, message);
+                    return;
+                }
+                bits.val()
+            }
+            _ => return,
+        };
+        if field.width() < 64 && reset >> field.width() != 0 {
+            let message = format!(
+                "reset value {reset:#x} of {} does not fit in {} bits",
+                self.describe(node),
+                field.width()
+            );
+            self.report(node, None, message);
+        }
+    }
+
+    fn check_interrupt(&mut self, node: &Node) {
+        let NodeContent::Field(field) = node.content() else {
+            return;
+        };
+        let interrupt = match field.interrupt() {
+            Ok(Some(interrupt)) => interrupt,
+            Ok(None) => return,
+            Err(e) => {
+                let message = format!("{}: {e}", self.describe(node));
+                self.report(node, None, message);
+                return;
+            }
+        };
+        for (property, id) in interrupt.references() {
+            let target = &self.namespace[id];
+            let message = match target.content() {
+                NodeContent::Field(other) if other.width() == field.width() => continue,
+                NodeContent::Field(other) => format!(
+                    "{property} of {} is {}, which is {} bits wide",
+                    self.describe(node),
+                    self.describe(target),
+                    other.width()
+                ),
+                _ => format!(
+                    "{property} of {} must be a field, not {}",
+                    self.describe(node),
+                    self.describe(target)
+                ),
+            };
+            self.report(node, Some(target), message);
+        }
+    }
+
+    fn check_counter(&mut self, node: &Node) {
+        let NodeContent::Field(field) = node.content() else {
+            return;
+        };
+        let counter = match field.counter() {
+            Ok(Some(counter)) => counter,
+            Ok(None) => return,
+            Err(e) => {
+                let message = format!("{}: {e}", self.describe(node));
+                self.report(node, None, message);
+                return;
+            }
+        };
+        for (property, id) in counter.references() {
+            let target = &self.namespace[id];
+            if !matches!(
+                target.content(),
+                NodeContent::Field(_) | NodeContent::Signal(_)
+            ) {
+                let message = format!(
+                    "{property} of {} must be a field or signal, not {}",
+                    self.describe(node),
+                    self.describe(target)
+                );
+                self.report(node, Some(target), message);
+            }
+        }
+    }
+
+    fn check_virtual_registers(&mut self, node: &Node) {
+        let NodeContent::Mem(mem) = node.content() else {
+            return;
+        };
+        let path = self.namespace.path(node.id());
+        let sw = match mem.sw() {
+            Ok(sw) => sw,
+            Err(e) => {
+                let message = format!("{}: {e}", self.describe(node));
+                self.report(node, None, message);
+                return;
+            }
+        };
+        for register in self.namespace.children(node.id()) {
+            let NodeContent::Register(reg) = register.content() else {
+                continue;
+            };
+            if reg.width() != mem.width() {
+                let message = format!(
+                    "{} is {} bits wide, but mem {path} has memwidth = {}",
+                    self.describe(register),
+                    reg.width(),
+                    mem.width()
+                );
+                self.report(register, Some(node), message);
+            }
+            let end =
+                register.offset().unwrap_or_default() + register.total_size().unwrap_or_default();
+            if end > mem.size() {
+                let message = format!(
+                    "{} does not fit in mem {path}, which is {:#x} bytes",
+                    self.describe(register),
+                    mem.size()
+                );
+                self.report(register, Some(node), message);
+            }
+            for field in self.namespace.children(register.id()) {
+                let NodeContent::Field(f) = field.content() else {
+                    continue;
+                };
+                let Ok(field_sw) = f.sw() else {
+                    continue;
+                };
+                use crate::ast::AccessType::*;
+                let problem = match (sw, field_sw) {
+                    (R, W | Rw | W1 | Rw1) => "writable",
+                    (W, R | Rw | Rw1) => "readable",
+                    _ => continue,
+                };
+                let message = format!(
+                    "{} is {problem}, but mem {path} has sw = {sw}",
+                    self.describe(field)
+                );
+                self.report(field, Some(node), message);
+            }
+        }
+    }
+
+    fn check_resetsignal(&mut self, node: &Node) {
+        let message = match node.property("resetsignal") {
+            None => return,
+            Some(PropertyValue::Reference(id)) => {
+                let target = &self.namespace[*id];
+                if let NodeContent::Signal(_) = target.content() {
+                    return;
+                }
+                format!(
+                    "resetsignal of {} must be a signal, not {}",
+                    self.describe(node),
+                    self.describe(target)
+                )
+            }
+            Some(_) => format!(
+                "resetsignal of {} must be a reference to a signal",
+                self.describe(node)
+            ),
+        };
+        self.report(node, None, message);
+    }
+
+    /// Only one signal within a component can be its `field_reset`, and
+    /// likewise its `cpuif_reset`.
+    fn check_reset_signals(&mut self, parent: &Node) {
+        for (property, is_reset) in [
+            ("field_reset", Signal::is_field_reset as fn(&Signal) -> bool),
+            ("cpuif_reset", Signal::is_cpuif_reset),
+        ] {
+            let signals: Vec<_> = self
+                .namespace
+                .children(parent.id())
+                .filter(|node| matches!(node.content(), NodeContent::Signal(signal) if is_reset(signal)))
+                .collect();
+            for signal in signals.iter().skip(1) {
+                let message = format!(
+                    "{} is a second {property} in {}, after {}",
+                    self.describe(signal),
+                    self.namespace.path(parent.id()),
+                    self.describe(signals[0])
+                );
+                self.report(signal, Some(signals[0]), message);
+            }
+        }
+    }
+
+    fn check_counter_outputs(&mut self, node: &Node) {
+        const OUTPUTS: [&str; 8] = [
+            "overflow",
+            "underflow",
+            "incrsaturate",
+            "saturate",
+            "decrsaturate",
+            "incrthreshold",
+            "threshold",
+            "decrthreshold",
+        ];
+        let mut references: Vec<_> = node
+            .properties()
+            .iter()
+            .filter_map(|(name, value)| match value {
+                PropertyValue::PropertyReference(id, output) if OUTPUTS.contains(&&**output) => {
+                    Some((name, *id, output))
+                }
+                _ => None,
+            })
+            .collect();
+        references.sort();
+        for (name, id, output) in references {
+            let target = &self.namespace[id];
+            let counter = match target.content() {
+                NodeContent::Field(field) => field.counter().ok().flatten(),
+                _ => None,
+            };
+            let problem = match counter {
+                Some(counter) if counter.has_output(output) => continue,
+                Some(_) => format!("has no {output}"),
+                None => "is not a counter".into(),
+            };
+            let message = format!(
+                "{name} of {} refers to {}->{output}, but {} {problem}",
+                self.describe(node),
+                self.namespace.path(id),
+                self.describe(target)
+            );
+            self.report(node, Some(target), message);
+        }
+    }
+
+    fn check_stride(&mut self, node: &Node) {
+        let (Some(array), Some(size)) = (node.array(), node.size()) else {
+            return;
+        };
+        if array.len() > 1 && array.stride() < size {
+            let message = format!(
+                "stride {:#x} of {} is smaller than its element size of {size:#x}",
+                array.stride(),
+                self.describe(node)
+            );
+            self.report(node, None, message);
+        }
+    }
+}
+
+/// The value of a numeric property.
+fn number(node: &Node, name: &str) -> Option<u64> {
+    match node.property(name)? {
+        PropertyValue::Literal(PrimaryLiteral::Number(value)) => Some(*value),
+        PropertyValue::Literal(PrimaryLiteral::Bits(bits)) => Some(bits.val()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn violations(input: &str) -> Vec<String> {
+        let namespace = crate::elaborate(crate::parse(input).unwrap()).unwrap();
+        validate(&namespace)
+            .into_iter()
+            .map(|violation| violation.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid() {
+        let input = r#"
+            addrmap {
+                reg { field {} A[3:0] = 4'hf; field {} B[7:4] = 2; } ctrl;
+                reg { regwidth = 64; accesswidth = 32; field {} D[64]; } data;
+                reg { field {} V; } regs[4] += 0x8;
+            } top;
+        "#;
+        assert!(violations(input).is_empty());
+    }
+
+    #[test]
+    fn test_locations() {
+        let input = "addrmap {\n    reg {\n        field {} A[3:0];\n        field {} B[5:2];\n    } ctrl;\n} top;";
+        let namespace = crate::elaborate(crate::parse(input).unwrap()).unwrap();
+        let violations = validate(&namespace);
+        assert_eq!(violations.len(), 1);
+        let location = |line, column| SourceLocation {
+            file: None,
+            line,
+            column,
+        };
+        assert_eq!(violations[0].location, Some(location(4, 18)));
+        assert_eq!(violations[0].other_location, Some(location(3, 18)));
+        assert_eq!(
+            violations[0].to_string(),
+            "4:18: field top.ctrl.B [5:2] overlaps field top.ctrl.A [3:0] (see 3:18)"
+        );
+    }
+
+    #[test]
+    fn test_fields() {
+        let input = r#"
+            addrmap {
+                reg {
+                    field {} A[3:0];
+                    field {} B[5:2] = 0x10;
+                    field {} C[40:33];
+                    field {} D[8:8] = 2'b1;
+                    field { sw = r; hw = w; } E[9:9] = 0;
+                    field { sw = r; woset; } F[10:10];
+                } ctrl;
+            } top;
+        "#;
+        assert_eq!(
+            violations(input),
+            [
+                "field top.ctrl.C [40:33] does not fit in top.ctrl, which is 32 bits wide",
+                "field top.ctrl.B [5:2] overlaps field top.ctrl.A [3:0]",
+                "reset value 0x10 of field top.ctrl.B [5:2] does not fit in 4 bits",
+                "reset value of field top.ctrl.D [8:8] is 2 bits wide",
+                "field top.ctrl.E [9:9]: a wire (sw = r, hw = w) cannot have a reset",
+                "field top.ctrl.F [10:10]: onwrite = woset needs software write access, but sw = r",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interrupts() {
+        let input = r#"
+            addrmap {
+                reg { field {} en[2]; field {} mask[3]; } ctrl;
+                reg {
+                    field { intr; woclr; } A[2];
+                    field { intr; woclr; } B[2];
+                    field { intr; woclr; } C[2];
+                    field { posedge intr; bothedge intr; woclr; } D;
+                } status;
+                status.A->enable = ctrl.en;
+                status.B->mask = ctrl.mask;
+                status.C->haltmask = ctrl;
+            } top;
+        "#;
+        assert_eq!(
+            violations(input),
+            [
+                "mask of field top.status.B [3:2] is field top.ctrl.mask [4:2], which is 3 bits wide",
+                "haltmask of field top.status.C [5:4] must be a field, not reg top.ctrl at 0x0..0x4",
+                "field top.status.D [6:6]: bothedge conflicts with posedge",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_counters() {
+        let input = r#"
+            addrmap {
+                reg {
+                    field { counter; incrsaturate; } up[4];
+                    field { counter; decrvalue = 2; } down[4];
+                    field { counter; incrthreshold = 0x10; } big[4];
+                    field { sw = r; hw = w; } ovf;
+                    field { sw = r; hw = w; } unf;
+                    field { sw = r; hw = w; } sat;
+                    field { sw = r; hw = w; } plain_ovf;
+                } cnt;
+                cnt.ovf->next = cnt.up->overflow;
+                cnt.unf->next = cnt.up->underflow;
+                cnt.sat->next = cnt.down->decrsaturate;
+                cnt.plain_ovf->next = cnt.ovf->overflow;
+            } top;
+        "#;
+        assert_eq!(
+            violations(input),
+            [
+                "field top.cnt.big [11:8]: incrthreshold value 0x10 does not fit in 4 bits",
+                "next of field top.cnt.unf [13:13] refers to top.cnt.up->underflow, but field top.cnt.up [3:0] has no underflow",
+                "next of field top.cnt.sat [14:14] refers to top.cnt.down->decrsaturate, but field top.cnt.down [7:4] has no decrsaturate",
+                "next of field top.cnt.plain_ovf [15:15] refers to top.cnt.ovf->overflow, but field top.cnt.ovf [12:12] is not a counter",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_signals() {
+        let input = r#"
+            addrmap {
+                signal { field_reset; } rst_a;
+                signal { field_reset; } rst_b;
+                reg {
+                    field {} rst;
+                    field { resetsignal = rst; } A;
+                    field { resetsignal = rst_a; } B;
+                } ctrl;
+            } top;
+        "#;
+        assert_eq!(
+            violations(input),
+            [
+                "signal top.rst_b is a second field_reset in top, after signal top.rst_a",
+                "resetsignal of field top.ctrl.A [1:1] must be a signal, not field top.ctrl.rst [0:0]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mem() {
+        let input = r#"
+            addrmap {
+                mem {
+                    mementries = 8;
+                    sw = r;
+                    reg { field { sw = r; } A[8]; } ok;
+                    reg { regwidth = 64; field { sw = r; } B; } wide;
+                    reg { field { sw = rw; } C; } writable;
+                    reg { field { sw = r; } D; } past_end @ 0x20;
+                } table;
+            } top;
+        "#;
+        assert_eq!(
+            violations(input),
+            [
+                "reg top.table.wide at 0x8..0x10 is 64 bits wide, but mem top.table has memwidth = 32",
+                "field top.table.writable.C [0:0] is writable, but mem top.table has sw = r",
+                "reg top.table.past_end at 0x20..0x24 does not fit in mem top.table, which is 0x20 bytes",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_alias() {
+        let input = r#"
+            addrmap {
+                reg ctrl_t { field { sw = rw; } A[4]; field {} B[4]; };
+                reg ro_t { field { sw = r; } A[4]; };
+                reg bad_t { field {} A[2]; field { hw = w; } B[7:4]; };
+                reg wide_t { regwidth = 64; field {} A[4]; };
+                ctrl_t ctrl;
+                alias ctrl ro_t ctrl_ro @ 0x0;
+                alias ctrl bad_t ctrl_bad;
+                alias ctrl wide_t ctrl_wide;
+            } top;
+        "#;
+        assert_eq!(
+            violations(input),
+            [
+                "reg top.ctrl_ro at 0x0..0x4 overlaps reg top.ctrl at 0x0..0x4",
+                "field top.ctrl_bad.A [1:0] of alias top.ctrl_bad has no field of the same name and bits in top.ctrl",
+                "field top.ctrl_bad.B [7:4] of alias top.ctrl_bad cannot have hw, which belongs to its primary",
+                "alias reg top.ctrl_wide at 0x8..0x10 is 64 bits wide, but its primary reg top.ctrl at 0x0..0x4 is 32 bits wide",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_alias_defaults() {
+        // properties inherited from defaults do not belong to the alias
+        let input = r#"
+            addrmap {
+                default hw = r;
+                reg ctrl_t { field {} A[4]; field {} B[4]; };
+                ctrl_t ctrl;
+                alias ctrl ctrl_t ctrl_ro;
+                alias ctrl ctrl_t ctrl_hw;
+                ctrl_hw.B->hw = w;
+            } top;
+        "#;
+        assert_eq!(
+            violations(input),
+            [
+                "field top.ctrl_hw.B [7:4] of alias top.ctrl_hw cannot have hw, which belongs to its primary"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bridge() {
+        let input = r#"
+            addrmap {
+                bridge;
+                addrmap { reg { field {} a; } r0; } ahb;
+                addrmap {
+                    bridge;
+                    addrmap { reg { field {} b; } r0; } inner;
+                } apb;
+                reg { field {} c; } stray;
+            } top;
+        "#;
+        assert_eq!(
+            violations(input),
+            [
+                "bridge addrmap top can only contain addrmaps, not reg top.stray at 0x8..0xc",
+                "bridge addrmap top.apb must be at the root, but it is within top",
+                "bridge addrmap top.apb must contain at least two addrmaps, got 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_registers() {
+        let input = r#"
+            addrmap {
+                reg { regwidth = 64; field {} A; } a @ 0x0;
+                reg { field {} B; } b @ 0x4;
+                reg { accesswidth = 64; field {} C; } c @ 0x10;
+                reg { regwidth = 64; accesswidth = 64; field {} D; } d @ 0x1c;
+                reg { field {} E; } regs[4] @ 0x40 += 0x2;
+            } top;
+        "#;
+        assert_eq!(
+            violations(input),
+            [
+                "reg top.b at 0x4..0x8 overlaps reg top.a at 0x0..0x8",
+                "accesswidth of reg top.c must be a power of two from 8 to its regwidth of 32, got 64",
+                "reg top.d at 0x1c..0x24 crosses an accesswidth boundary; it must be aligned to 8 bytes",
+                "stride 0x2 of reg top.regs at 0x40..0x4a is smaller than its element size of 0x4",
+            ]
+        );
+    }
+}
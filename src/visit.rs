@@ -0,0 +1,616 @@
+// Licensed under the Apache-2.0 license
+
+//! Traversal of an AST by shared reference.
+//!
+//! Each method of [`Visit`] is called on the way into a node of its type. The
+//! default implementations call the matching `walk_*` function, which visits
+//! the node's children, so an implementation that overrides a method should
+//! call the `walk_*` function itself if it still wants the children visited.
+
+use crate::ast::*;
+
+pub trait Visit<'ast> {
+    fn visit_root(&mut self, node: &'ast Root) {
+        walk_root(self, node);
+    }
+
+    fn visit_description(&mut self, node: &'ast Description) {
+        walk_description(self, node);
+    }
+
+    fn visit_component(&mut self, node: &'ast Component) {
+        walk_component(self, node);
+    }
+
+    fn visit_component_def(&mut self, node: &'ast ComponentDef) {
+        walk_component_def(self, node);
+    }
+
+    fn visit_component_body(&mut self, node: &'ast ComponentBody) {
+        walk_component_body(self, node);
+    }
+
+    fn visit_component_body_elem(&mut self, node: &'ast ComponentBodyElem) {
+        walk_component_body_elem(self, node);
+    }
+
+    fn visit_param_def(&mut self, node: &'ast ParamDef) {
+        walk_param_def(self, node);
+    }
+
+    fn visit_param_def_elem(&mut self, node: &'ast ParamDefElem) {
+        walk_param_def_elem(self, node);
+    }
+
+    fn visit_explicit_component_inst(&mut self, node: &'ast ExplicitComponentInst) {
+        walk_explicit_component_inst(self, node);
+    }
+
+    fn visit_component_insts(&mut self, node: &'ast ComponentInsts) {
+        walk_component_insts(self, node);
+    }
+
+    fn visit_param_elem(&mut self, node: &'ast ParamElem) {
+        walk_param_elem(self, node);
+    }
+
+    fn visit_component_inst(&mut self, node: &'ast ComponentInst) {
+        walk_component_inst(self, node);
+    }
+
+    fn visit_array_or_range(&mut self, node: &'ast ArrayOrRange) {
+        walk_array_or_range(self, node);
+    }
+
+    fn visit_range(&mut self, node: &'ast Range) {
+        walk_range(self, node);
+    }
+
+    fn visit_property_assignment(&mut self, node: &'ast PropertyAssignment) {
+        walk_property_assignment(self, node);
+    }
+
+    fn visit_explicit_prop_modifier(&mut self, _node: &'ast ExplicitPropModifier) {}
+
+    fn visit_explicit_property_assignment(&mut self, node: &'ast ExplicitPropertyAssignment) {
+        walk_explicit_property_assignment(self, node);
+    }
+
+    fn visit_prop_assignment_rhs(&mut self, node: &'ast PropAssignmentRhs) {
+        walk_prop_assignment_rhs(self, node);
+    }
+
+    fn visit_prop_ref(&mut self, node: &'ast PropRef) {
+        walk_prop_ref(self, node);
+    }
+
+    fn visit_post_encode_assignment(&mut self, node: &'ast PostEncodeAssignment) {
+        walk_post_encode_assignment(self, node);
+    }
+
+    fn visit_identity_or_prop_keyword(&mut self, _node: &'ast IdentityOrPropKeyword) {}
+
+    fn visit_enum_def(&mut self, node: &'ast EnumDef) {
+        walk_enum_def(self, node);
+    }
+
+    fn visit_enum_entry(&mut self, node: &'ast EnumEntry) {
+        walk_enum_entry(self, node);
+    }
+
+    fn visit_struct_def(&mut self, node: &'ast StructDef) {
+        walk_struct_def(self, node);
+    }
+
+    fn visit_struct_elem(&mut self, _node: &'ast StructElem) {}
+
+    fn visit_constraint_def(&mut self, node: &'ast ConstraintDef) {
+        walk_constraint_def(self, node);
+    }
+
+    fn visit_constraint_body(&mut self, node: &'ast ConstraintBody) {
+        walk_constraint_body(self, node);
+    }
+
+    fn visit_constraint_elem(&mut self, node: &'ast ConstraintElem) {
+        walk_constraint_elem(self, node);
+    }
+
+    fn visit_constraint_lhs(&mut self, node: &'ast ConstraintLhs) {
+        walk_constraint_lhs(self, node);
+    }
+
+    fn visit_constraint_value(&mut self, node: &'ast ConstraintValue) {
+        walk_constraint_value(self, node);
+    }
+
+    fn visit_property_definition(&mut self, node: &'ast PropertyDefinition) {
+        walk_property_definition(self, node);
+    }
+
+    fn visit_property_attribute(&mut self, node: &'ast PropertyAttribute) {
+        walk_property_attribute(self, node);
+    }
+
+    fn visit_constant_expr(&mut self, node: &'ast ConstantExpr) {
+        walk_constant_expr(self, node);
+    }
+
+    fn visit_constant_expr_continue(&mut self, node: &'ast ConstantExprContinue) {
+        walk_constant_expr_continue(self, node);
+    }
+
+    fn visit_constant_primary(&mut self, node: &'ast ConstantPrimary) {
+        walk_constant_primary(self, node);
+    }
+
+    fn visit_constant_primary_base(&mut self, node: &'ast ConstantPrimaryBase) {
+        walk_constant_primary_base(self, node);
+    }
+
+    fn visit_primary_literal(&mut self, _node: &'ast PrimaryLiteral) {}
+
+    fn visit_instance_or_prop_ref(&mut self, node: &'ast InstanceOrPropRef) {
+        walk_instance_or_prop_ref(self, node);
+    }
+
+    fn visit_instance_ref(&mut self, node: &'ast InstanceRef) {
+        walk_instance_ref(self, node);
+    }
+
+    fn visit_instance_ref_element(&mut self, node: &'ast InstanceRefElement) {
+        walk_instance_ref_element(self, node);
+    }
+
+    fn visit_struct_literal_element(&mut self, node: &'ast StructLiteralElement) {
+        walk_struct_literal_element(self, node);
+    }
+}
+
+pub fn walk_root<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Root) {
+    for description in &node.descriptions {
+        v.visit_description(description);
+    }
+}
+
+pub fn walk_description<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Description) {
+    match node {
+        Description::ComponentDef(component) => v.visit_component(component),
+        Description::EnumDef(enum_def) => v.visit_enum_def(enum_def),
+        Description::PropertyDefinition(property_definition) => {
+            v.visit_property_definition(property_definition)
+        }
+        Description::StructDef(struct_def) => v.visit_struct_def(struct_def),
+        Description::ConstraintDef(constraint_def) => v.visit_constraint_def(constraint_def),
+        Description::ExplicitComponentInst(explicit_component_inst) => {
+            v.visit_explicit_component_inst(explicit_component_inst)
+        }
+        Description::PropertyAssignment(property_assignment) => {
+            v.visit_property_assignment(property_assignment)
+        }
+    }
+}
+
+pub fn walk_component<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Component) {
+    v.visit_component_def(&node.def);
+    if let Some(insts) = &node.insts {
+        v.visit_component_insts(insts);
+    }
+}
+
+pub fn walk_component_def<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ComponentDef) {
+    match node {
+        ComponentDef::Named(_, _, param_def, body) => {
+            if let Some(param_def) = param_def {
+                v.visit_param_def(param_def);
+            }
+            v.visit_component_body(body);
+        }
+        ComponentDef::Anon(_, body) => v.visit_component_body(body),
+    }
+}
+
+pub fn walk_component_body<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ComponentBody) {
+    for elem in &node.elements {
+        v.visit_component_body_elem(elem);
+    }
+}
+
+pub fn walk_component_body_elem<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ComponentBodyElem,
+) {
+    match node {
+        ComponentBodyElem::ComponentDef(component) => v.visit_component(component),
+        ComponentBodyElem::EnumDef(enum_def) => v.visit_enum_def(enum_def),
+        ComponentBodyElem::StructDef(struct_def) => v.visit_struct_def(struct_def),
+        ComponentBodyElem::ConstraintDef(constraint_def) => v.visit_constraint_def(constraint_def),
+        ComponentBodyElem::ExplicitComponentInst(explicit_component_inst) => {
+            v.visit_explicit_component_inst(explicit_component_inst)
+        }
+        ComponentBodyElem::PropertyAssignment(property_assignment) => {
+            v.visit_property_assignment(property_assignment)
+        }
+    }
+}
+
+pub fn walk_param_def<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ParamDef) {
+    let ParamDef::Params(params) = node;
+    for param in params {
+        v.visit_param_def_elem(param);
+    }
+}
+
+pub fn walk_param_def_elem<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ParamDefElem) {
+    let ParamDefElem::ParamDefElem(_, _, _, default) = node;
+    if let Some(default) = default {
+        v.visit_constant_expr(default);
+    }
+}
+
+pub fn walk_explicit_component_inst<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ExplicitComponentInst,
+) {
+    v.visit_component_insts(&node.component_insts);
+}
+
+pub fn walk_component_insts<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ComponentInsts) {
+    for param_inst in &node.param_insts {
+        v.visit_param_elem(param_inst);
+    }
+    for component_inst in &node.component_insts {
+        v.visit_component_inst(component_inst);
+    }
+}
+
+pub fn walk_param_elem<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ParamElem) {
+    v.visit_constant_expr(&node.param_value);
+}
+
+pub fn walk_component_inst<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ComponentInst) {
+    if let Some(array_or_range) = &node.array_or_range {
+        v.visit_array_or_range(array_or_range);
+    }
+    for expr in [
+        &node.equals,
+        &node.at,
+        &node.plus_equals,
+        &node.percent_equals,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        v.visit_constant_expr(expr);
+    }
+}
+
+pub fn walk_array_or_range<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ArrayOrRange) {
+    match node {
+        ArrayOrRange::Array(dims) => {
+            for dim in dims {
+                v.visit_constant_expr(dim);
+            }
+        }
+        ArrayOrRange::Range(range) => v.visit_range(range),
+    }
+}
+
+pub fn walk_range<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Range) {
+    let Range::Range(msb, lsb) = node;
+    v.visit_constant_expr(msb);
+    v.visit_constant_expr(lsb);
+}
+
+pub fn walk_property_assignment<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast PropertyAssignment,
+) {
+    match node {
+        PropertyAssignment::ExplicitOrDefaultPropAssignment(
+            ExplicitOrDefaultPropAssignment::ExplicitPropModifier(_, modifier),
+        ) => v.visit_explicit_prop_modifier(modifier),
+        PropertyAssignment::ExplicitOrDefaultPropAssignment(
+            ExplicitOrDefaultPropAssignment::ExplicitPropAssignment(_, assignment),
+        ) => v.visit_explicit_property_assignment(assignment),
+        PropertyAssignment::PostPropAssignment(PostPropAssignment::PropRef(prop_ref, rhs)) => {
+            v.visit_prop_ref(prop_ref);
+            if let Some(rhs) = rhs {
+                v.visit_prop_assignment_rhs(rhs);
+            }
+        }
+        PropertyAssignment::PostPropAssignment(PostPropAssignment::PostEncodeAssignment(
+            assignment,
+        )) => v.visit_post_encode_assignment(assignment),
+    }
+}
+
+pub fn walk_explicit_property_assignment<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ExplicitPropertyAssignment,
+) {
+    match node {
+        ExplicitPropertyAssignment::Assignment(id_or_prop, rhs) => {
+            v.visit_identity_or_prop_keyword(id_or_prop);
+            if let Some(rhs) = rhs {
+                v.visit_prop_assignment_rhs(rhs);
+            }
+        }
+        ExplicitPropertyAssignment::EncodeAssignment(_) => {}
+    }
+}
+
+pub fn walk_prop_assignment_rhs<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast PropAssignmentRhs,
+) {
+    match node {
+        PropAssignmentRhs::ConstantExpr(expr) => v.visit_constant_expr(expr),
+        PropAssignmentRhs::PrecedenceType(_) => {}
+    }
+}
+
+pub fn walk_prop_ref<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast PropRef) {
+    v.visit_instance_ref(&node.iref);
+    v.visit_identity_or_prop_keyword(&node.id_or_prop);
+}
+
+pub fn walk_post_encode_assignment<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast PostEncodeAssignment,
+) {
+    v.visit_instance_ref(&node.iref);
+}
+
+pub fn walk_enum_def<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast EnumDef) {
+    for entry in &node.body {
+        v.visit_enum_entry(entry);
+    }
+}
+
+pub fn walk_enum_entry<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast EnumEntry) {
+    if let Some(expr) = &node.expr {
+        v.visit_constant_expr(expr);
+    }
+    for assignment in &node.property_assignments {
+        v.visit_explicit_property_assignment(assignment);
+    }
+}
+
+pub fn walk_struct_def<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast StructDef) {
+    for elem in &node.body {
+        v.visit_struct_elem(elem);
+    }
+}
+
+pub fn walk_constraint_def<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ConstraintDef) {
+    match node {
+        ConstraintDef::Exp(_, body, _) | ConstraintDef::Anon(body, _) => {
+            v.visit_constraint_body(body)
+        }
+    }
+}
+
+pub fn walk_constraint_body<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ConstraintBody) {
+    for elem in &node.elements {
+        v.visit_constraint_elem(elem);
+    }
+}
+
+pub fn walk_constraint_elem<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ConstraintElem) {
+    match node {
+        ConstraintElem::ConstantExpr(expr) => v.visit_constant_expr(expr),
+        ConstraintElem::ConstraintPropAssignment(assignment) => {
+            v.visit_constant_expr(&assignment.expr)
+        }
+        ConstraintElem::ConstraintInsideValues(lhs, values) => {
+            v.visit_constraint_lhs(lhs);
+            for value in values {
+                v.visit_constraint_value(value);
+            }
+        }
+        ConstraintElem::ConstraintInsideId(lhs, _) => v.visit_constraint_lhs(lhs),
+    }
+}
+
+pub fn walk_constraint_lhs<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ConstraintLhs) {
+    match node {
+        ConstraintLhs::This => {}
+        ConstraintLhs::InstanceRef(iref) => v.visit_instance_ref(iref),
+    }
+}
+
+pub fn walk_constraint_value<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ConstraintValue,
+) {
+    match node {
+        ConstraintValue::ConstantExpr(expr) => v.visit_constant_expr(expr),
+        ConstraintValue::Range(low, high) => {
+            v.visit_constant_expr(low);
+            v.visit_constant_expr(high);
+        }
+    }
+}
+
+pub fn walk_property_definition<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast PropertyDefinition,
+) {
+    for attribute in &node.body {
+        v.visit_property_attribute(attribute);
+    }
+}
+
+pub fn walk_property_attribute<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast PropertyAttribute,
+) {
+    match node {
+        PropertyAttribute::PropertyDefault(expr) => v.visit_constant_expr(expr),
+        PropertyAttribute::PropertyType(_)
+        | PropertyAttribute::PropertyUsage(_)
+        | PropertyAttribute::PropertyConstraint => {}
+    }
+}
+
+pub fn walk_constant_expr<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ConstantExpr) {
+    let cont = match node {
+        ConstantExpr::ConstantPrimary(primary, cont) => {
+            v.visit_constant_primary(primary);
+            cont
+        }
+        ConstantExpr::UnaryOp(_, expr, cont) => {
+            v.visit_constant_expr(expr);
+            cont
+        }
+    };
+    if let Some(cont) = cont {
+        v.visit_constant_expr_continue(cont);
+    }
+}
+
+pub fn walk_constant_expr_continue<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ConstantExprContinue,
+) {
+    let cont = match node {
+        ConstantExprContinue::BinaryOp(_, rhs, cont) => {
+            v.visit_constant_expr(rhs);
+            cont
+        }
+        ConstantExprContinue::TernaryOp(if_true, if_false, cont) => {
+            v.visit_constant_expr(if_true);
+            v.visit_constant_expr(if_false);
+            cont
+        }
+    };
+    if let Some(cont) = cont {
+        v.visit_constant_expr_continue(cont);
+    }
+}
+
+pub fn walk_constant_primary<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ConstantPrimary,
+) {
+    match node {
+        ConstantPrimary::Base(base) => v.visit_constant_primary_base(base),
+        ConstantPrimary::Cast(base, expr) => {
+            v.visit_constant_primary_base(base);
+            v.visit_constant_expr(expr);
+        }
+    }
+}
+
+pub fn walk_constant_primary_base<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ConstantPrimaryBase,
+) {
+    match node {
+        ConstantPrimaryBase::PrimaryLiteral(literal) => v.visit_primary_literal(literal),
+        ConstantPrimaryBase::ConstantConcat(exprs) | ConstantPrimaryBase::ArrayLiteral(exprs) => {
+            for expr in exprs {
+                v.visit_constant_expr(expr);
+            }
+        }
+        ConstantPrimaryBase::ConstantMultipleConcat(count, exprs) => {
+            v.visit_constant_expr(count);
+            for expr in exprs {
+                v.visit_constant_expr(expr);
+            }
+        }
+        ConstantPrimaryBase::ConstantExpr(expr)
+        | ConstantPrimaryBase::SimpleTypeCast(_, expr)
+        | ConstantPrimaryBase::BooleanCast(expr) => v.visit_constant_expr(expr),
+        ConstantPrimaryBase::InstanceOrPropRef(iref) => v.visit_instance_or_prop_ref(iref),
+        ConstantPrimaryBase::StructLiteral(_, elements) => {
+            for element in elements {
+                v.visit_struct_literal_element(element);
+            }
+        }
+    }
+}
+
+pub fn walk_instance_or_prop_ref<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast InstanceOrPropRef,
+) {
+    v.visit_instance_ref(&node.iref);
+    if let Some(id_or_prop) = &node.id_or_prop {
+        v.visit_identity_or_prop_keyword(id_or_prop);
+    }
+}
+
+pub fn walk_instance_ref<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast InstanceRef) {
+    for element in &node.elements {
+        v.visit_instance_ref_element(element);
+    }
+}
+
+pub fn walk_instance_ref_element<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast InstanceRefElement,
+) {
+    for index in &node.arrays {
+        v.visit_constant_expr(index);
+    }
+}
+
+pub fn walk_struct_literal_element<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast StructLiteralElement,
+) {
+    v.visit_constant_expr(&node.expr);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Collects the names of instances and the properties assigned to them.
+    #[derive(Default)]
+    struct Collector<'ast> {
+        instances: Vec<&'ast str>,
+        properties: Vec<String>,
+        literals: usize,
+    }
+
+    impl<'ast> Visit<'ast> for Collector<'ast> {
+        fn visit_component_inst(&mut self, node: &'ast ComponentInst) {
+            self.instances.push(&node.id);
+            walk_component_inst(self, node);
+        }
+
+        fn visit_identity_or_prop_keyword(&mut self, node: &'ast IdentityOrPropKeyword) {
+            self.properties.push(match node {
+                IdentityOrPropKeyword::Id(id) => id.clone(),
+                IdentityOrPropKeyword::PropKeyword(keyword) => keyword.to_string(),
+            });
+        }
+
+        fn visit_primary_literal(&mut self, _node: &'ast PrimaryLiteral) {
+            self.literals += 1;
+        }
+    }
+
+    #[test]
+    fn test_visit() {
+        let root: Root = r#"
+            addrmap top {
+                reg {
+                    field { sw = rw; desc = "enable"; } EN[1] = 0;
+                } CTRL @ 0x10;
+                CTRL.EN->reset = 1;
+            };
+        "#
+        .parse()
+        .unwrap();
+        let mut collector = Collector::default();
+        collector.visit_root(&root);
+        assert_eq!(collector.instances, ["EN", "CTRL"]);
+        assert_eq!(collector.properties, ["sw", "desc", "reset"]);
+        // rw, "enable", [1], 0, 0x10, 1
+        assert_eq!(collector.literals, 6);
+    }
+}
@@ -0,0 +1,586 @@
+// Licensed under the Apache-2.0 license
+
+//! Traversal of an AST by mutable reference, for in-place rewriting.
+//!
+//! Each method of [`VisitMut`] is called on the way into a node of its type.
+//! The default implementations call the matching `walk_*_mut` function, which
+//! visits the node's children, so an implementation that overrides a method
+//! should call the `walk_*_mut` function itself if it still wants the children
+//! visited.
+
+use crate::ast::*;
+
+pub trait VisitMut {
+    fn visit_root_mut(&mut self, node: &mut Root) {
+        walk_root_mut(self, node);
+    }
+
+    fn visit_description_mut(&mut self, node: &mut Description) {
+        walk_description_mut(self, node);
+    }
+
+    fn visit_component_mut(&mut self, node: &mut Component) {
+        walk_component_mut(self, node);
+    }
+
+    fn visit_component_def_mut(&mut self, node: &mut ComponentDef) {
+        walk_component_def_mut(self, node);
+    }
+
+    fn visit_component_body_mut(&mut self, node: &mut ComponentBody) {
+        walk_component_body_mut(self, node);
+    }
+
+    fn visit_component_body_elem_mut(&mut self, node: &mut ComponentBodyElem) {
+        walk_component_body_elem_mut(self, node);
+    }
+
+    fn visit_param_def_mut(&mut self, node: &mut ParamDef) {
+        walk_param_def_mut(self, node);
+    }
+
+    fn visit_param_def_elem_mut(&mut self, node: &mut ParamDefElem) {
+        walk_param_def_elem_mut(self, node);
+    }
+
+    fn visit_explicit_component_inst_mut(&mut self, node: &mut ExplicitComponentInst) {
+        walk_explicit_component_inst_mut(self, node);
+    }
+
+    fn visit_component_insts_mut(&mut self, node: &mut ComponentInsts) {
+        walk_component_insts_mut(self, node);
+    }
+
+    fn visit_param_elem_mut(&mut self, node: &mut ParamElem) {
+        walk_param_elem_mut(self, node);
+    }
+
+    fn visit_component_inst_mut(&mut self, node: &mut ComponentInst) {
+        walk_component_inst_mut(self, node);
+    }
+
+    fn visit_array_or_range_mut(&mut self, node: &mut ArrayOrRange) {
+        walk_array_or_range_mut(self, node);
+    }
+
+    fn visit_range_mut(&mut self, node: &mut Range) {
+        walk_range_mut(self, node);
+    }
+
+    fn visit_property_assignment_mut(&mut self, node: &mut PropertyAssignment) {
+        walk_property_assignment_mut(self, node);
+    }
+
+    fn visit_explicit_prop_modifier_mut(&mut self, _node: &mut ExplicitPropModifier) {}
+
+    fn visit_explicit_property_assignment_mut(&mut self, node: &mut ExplicitPropertyAssignment) {
+        walk_explicit_property_assignment_mut(self, node);
+    }
+
+    fn visit_prop_assignment_rhs_mut(&mut self, node: &mut PropAssignmentRhs) {
+        walk_prop_assignment_rhs_mut(self, node);
+    }
+
+    fn visit_prop_ref_mut(&mut self, node: &mut PropRef) {
+        walk_prop_ref_mut(self, node);
+    }
+
+    fn visit_post_encode_assignment_mut(&mut self, node: &mut PostEncodeAssignment) {
+        walk_post_encode_assignment_mut(self, node);
+    }
+
+    fn visit_identity_or_prop_keyword_mut(&mut self, _node: &mut IdentityOrPropKeyword) {}
+
+    fn visit_enum_def_mut(&mut self, node: &mut EnumDef) {
+        walk_enum_def_mut(self, node);
+    }
+
+    fn visit_enum_entry_mut(&mut self, node: &mut EnumEntry) {
+        walk_enum_entry_mut(self, node);
+    }
+
+    fn visit_struct_def_mut(&mut self, node: &mut StructDef) {
+        walk_struct_def_mut(self, node);
+    }
+
+    fn visit_struct_elem_mut(&mut self, _node: &mut StructElem) {}
+
+    fn visit_constraint_def_mut(&mut self, node: &mut ConstraintDef) {
+        walk_constraint_def_mut(self, node);
+    }
+
+    fn visit_constraint_body_mut(&mut self, node: &mut ConstraintBody) {
+        walk_constraint_body_mut(self, node);
+    }
+
+    fn visit_constraint_elem_mut(&mut self, node: &mut ConstraintElem) {
+        walk_constraint_elem_mut(self, node);
+    }
+
+    fn visit_constraint_lhs_mut(&mut self, node: &mut ConstraintLhs) {
+        walk_constraint_lhs_mut(self, node);
+    }
+
+    fn visit_constraint_value_mut(&mut self, node: &mut ConstraintValue) {
+        walk_constraint_value_mut(self, node);
+    }
+
+    fn visit_property_definition_mut(&mut self, node: &mut PropertyDefinition) {
+        walk_property_definition_mut(self, node);
+    }
+
+    fn visit_property_attribute_mut(&mut self, node: &mut PropertyAttribute) {
+        walk_property_attribute_mut(self, node);
+    }
+
+    fn visit_constant_expr_mut(&mut self, node: &mut ConstantExpr) {
+        walk_constant_expr_mut(self, node);
+    }
+
+    fn visit_constant_expr_continue_mut(&mut self, node: &mut ConstantExprContinue) {
+        walk_constant_expr_continue_mut(self, node);
+    }
+
+    fn visit_constant_primary_mut(&mut self, node: &mut ConstantPrimary) {
+        walk_constant_primary_mut(self, node);
+    }
+
+    fn visit_constant_primary_base_mut(&mut self, node: &mut ConstantPrimaryBase) {
+        walk_constant_primary_base_mut(self, node);
+    }
+
+    fn visit_primary_literal_mut(&mut self, _node: &mut PrimaryLiteral) {}
+
+    fn visit_instance_or_prop_ref_mut(&mut self, node: &mut InstanceOrPropRef) {
+        walk_instance_or_prop_ref_mut(self, node);
+    }
+
+    fn visit_instance_ref_mut(&mut self, node: &mut InstanceRef) {
+        walk_instance_ref_mut(self, node);
+    }
+
+    fn visit_instance_ref_element_mut(&mut self, node: &mut InstanceRefElement) {
+        walk_instance_ref_element_mut(self, node);
+    }
+
+    fn visit_struct_literal_element_mut(&mut self, node: &mut StructLiteralElement) {
+        walk_struct_literal_element_mut(self, node);
+    }
+}
+
+pub fn walk_root_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Root) {
+    for description in &mut node.descriptions {
+        v.visit_description_mut(description);
+    }
+}
+
+pub fn walk_description_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Description) {
+    match node {
+        Description::ComponentDef(component) => v.visit_component_mut(component),
+        Description::EnumDef(enum_def) => v.visit_enum_def_mut(enum_def),
+        Description::PropertyDefinition(property_definition) => {
+            v.visit_property_definition_mut(property_definition)
+        }
+        Description::StructDef(struct_def) => v.visit_struct_def_mut(struct_def),
+        Description::ConstraintDef(constraint_def) => v.visit_constraint_def_mut(constraint_def),
+        Description::ExplicitComponentInst(explicit_component_inst) => {
+            v.visit_explicit_component_inst_mut(explicit_component_inst)
+        }
+        Description::PropertyAssignment(property_assignment) => {
+            v.visit_property_assignment_mut(property_assignment)
+        }
+    }
+}
+
+pub fn walk_component_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Component) {
+    v.visit_component_def_mut(&mut node.def);
+    if let Some(insts) = &mut node.insts {
+        v.visit_component_insts_mut(insts);
+    }
+}
+
+pub fn walk_component_def_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ComponentDef) {
+    match node {
+        ComponentDef::Named(_, _, param_def, body) => {
+            if let Some(param_def) = param_def {
+                v.visit_param_def_mut(param_def);
+            }
+            v.visit_component_body_mut(body);
+        }
+        ComponentDef::Anon(_, body) => v.visit_component_body_mut(body),
+    }
+}
+
+pub fn walk_component_body_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ComponentBody) {
+    for elem in &mut node.elements {
+        v.visit_component_body_elem_mut(elem);
+    }
+}
+
+pub fn walk_component_body_elem_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ComponentBodyElem) {
+    match node {
+        ComponentBodyElem::ComponentDef(component) => v.visit_component_mut(component),
+        ComponentBodyElem::EnumDef(enum_def) => v.visit_enum_def_mut(enum_def),
+        ComponentBodyElem::StructDef(struct_def) => v.visit_struct_def_mut(struct_def),
+        ComponentBodyElem::ConstraintDef(constraint_def) => {
+            v.visit_constraint_def_mut(constraint_def)
+        }
+        ComponentBodyElem::ExplicitComponentInst(explicit_component_inst) => {
+            v.visit_explicit_component_inst_mut(explicit_component_inst)
+        }
+        ComponentBodyElem::PropertyAssignment(property_assignment) => {
+            v.visit_property_assignment_mut(property_assignment)
+        }
+    }
+}
+
+pub fn walk_param_def_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ParamDef) {
+    let ParamDef::Params(params) = node;
+    for param in params {
+        v.visit_param_def_elem_mut(param);
+    }
+}
+
+pub fn walk_param_def_elem_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ParamDefElem) {
+    let ParamDefElem::ParamDefElem(_, _, _, default) = node;
+    if let Some(default) = default {
+        v.visit_constant_expr_mut(default);
+    }
+}
+
+pub fn walk_explicit_component_inst_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut ExplicitComponentInst,
+) {
+    v.visit_component_insts_mut(&mut node.component_insts);
+}
+
+pub fn walk_component_insts_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ComponentInsts) {
+    for param_inst in &mut node.param_insts {
+        v.visit_param_elem_mut(param_inst);
+    }
+    for component_inst in &mut node.component_insts {
+        v.visit_component_inst_mut(component_inst);
+    }
+}
+
+pub fn walk_param_elem_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ParamElem) {
+    v.visit_constant_expr_mut(&mut node.param_value);
+}
+
+pub fn walk_component_inst_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ComponentInst) {
+    if let Some(array_or_range) = &mut node.array_or_range {
+        v.visit_array_or_range_mut(array_or_range);
+    }
+    for expr in [
+        &mut node.equals,
+        &mut node.at,
+        &mut node.plus_equals,
+        &mut node.percent_equals,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        v.visit_constant_expr_mut(expr);
+    }
+}
+
+pub fn walk_array_or_range_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ArrayOrRange) {
+    match node {
+        ArrayOrRange::Array(dims) => {
+            for dim in dims {
+                v.visit_constant_expr_mut(dim);
+            }
+        }
+        ArrayOrRange::Range(range) => v.visit_range_mut(range),
+    }
+}
+
+pub fn walk_range_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Range) {
+    let Range::Range(msb, lsb) = node;
+    v.visit_constant_expr_mut(msb);
+    v.visit_constant_expr_mut(lsb);
+}
+
+pub fn walk_property_assignment_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut PropertyAssignment,
+) {
+    match node {
+        PropertyAssignment::ExplicitOrDefaultPropAssignment(
+            ExplicitOrDefaultPropAssignment::ExplicitPropModifier(_, modifier),
+        ) => v.visit_explicit_prop_modifier_mut(modifier),
+        PropertyAssignment::ExplicitOrDefaultPropAssignment(
+            ExplicitOrDefaultPropAssignment::ExplicitPropAssignment(_, assignment),
+        ) => v.visit_explicit_property_assignment_mut(assignment),
+        PropertyAssignment::PostPropAssignment(PostPropAssignment::PropRef(prop_ref, rhs)) => {
+            v.visit_prop_ref_mut(prop_ref);
+            if let Some(rhs) = rhs {
+                v.visit_prop_assignment_rhs_mut(rhs);
+            }
+        }
+        PropertyAssignment::PostPropAssignment(PostPropAssignment::PostEncodeAssignment(
+            assignment,
+        )) => v.visit_post_encode_assignment_mut(assignment),
+    }
+}
+
+pub fn walk_explicit_property_assignment_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut ExplicitPropertyAssignment,
+) {
+    match node {
+        ExplicitPropertyAssignment::Assignment(id_or_prop, rhs) => {
+            v.visit_identity_or_prop_keyword_mut(id_or_prop);
+            if let Some(rhs) = rhs {
+                v.visit_prop_assignment_rhs_mut(rhs);
+            }
+        }
+        ExplicitPropertyAssignment::EncodeAssignment(_) => {}
+    }
+}
+
+pub fn walk_prop_assignment_rhs_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut PropAssignmentRhs) {
+    match node {
+        PropAssignmentRhs::ConstantExpr(expr) => v.visit_constant_expr_mut(expr),
+        PropAssignmentRhs::PrecedenceType(_) => {}
+    }
+}
+
+pub fn walk_prop_ref_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut PropRef) {
+    v.visit_instance_ref_mut(&mut node.iref);
+    v.visit_identity_or_prop_keyword_mut(&mut node.id_or_prop);
+}
+
+pub fn walk_post_encode_assignment_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut PostEncodeAssignment,
+) {
+    v.visit_instance_ref_mut(&mut node.iref);
+}
+
+pub fn walk_enum_def_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut EnumDef) {
+    for entry in &mut node.body {
+        v.visit_enum_entry_mut(entry);
+    }
+}
+
+pub fn walk_enum_entry_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut EnumEntry) {
+    if let Some(expr) = &mut node.expr {
+        v.visit_constant_expr_mut(expr);
+    }
+    for assignment in &mut node.property_assignments {
+        v.visit_explicit_property_assignment_mut(assignment);
+    }
+}
+
+pub fn walk_struct_def_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut StructDef) {
+    for elem in &mut node.body {
+        v.visit_struct_elem_mut(elem);
+    }
+}
+
+pub fn walk_constraint_def_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ConstraintDef) {
+    match node {
+        ConstraintDef::Exp(_, body, _) | ConstraintDef::Anon(body, _) => {
+            v.visit_constraint_body_mut(body)
+        }
+    }
+}
+
+pub fn walk_constraint_body_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ConstraintBody) {
+    for elem in &mut node.elements {
+        v.visit_constraint_elem_mut(elem);
+    }
+}
+
+pub fn walk_constraint_elem_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ConstraintElem) {
+    match node {
+        ConstraintElem::ConstantExpr(expr) => v.visit_constant_expr_mut(expr),
+        ConstraintElem::ConstraintPropAssignment(assignment) => {
+            v.visit_constant_expr_mut(&mut assignment.expr)
+        }
+        ConstraintElem::ConstraintInsideValues(lhs, values) => {
+            v.visit_constraint_lhs_mut(lhs);
+            for value in values {
+                v.visit_constraint_value_mut(value);
+            }
+        }
+        ConstraintElem::ConstraintInsideId(lhs, _) => v.visit_constraint_lhs_mut(lhs),
+    }
+}
+
+pub fn walk_constraint_lhs_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ConstraintLhs) {
+    match node {
+        ConstraintLhs::This => {}
+        ConstraintLhs::InstanceRef(iref) => v.visit_instance_ref_mut(iref),
+    }
+}
+
+pub fn walk_constraint_value_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ConstraintValue) {
+    match node {
+        ConstraintValue::ConstantExpr(expr) => v.visit_constant_expr_mut(expr),
+        ConstraintValue::Range(low, high) => {
+            v.visit_constant_expr_mut(low);
+            v.visit_constant_expr_mut(high);
+        }
+    }
+}
+
+pub fn walk_property_definition_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut PropertyDefinition,
+) {
+    for attribute in &mut node.body {
+        v.visit_property_attribute_mut(attribute);
+    }
+}
+
+pub fn walk_property_attribute_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut PropertyAttribute) {
+    match node {
+        PropertyAttribute::PropertyDefault(expr) => v.visit_constant_expr_mut(expr),
+        PropertyAttribute::PropertyType(_)
+        | PropertyAttribute::PropertyUsage(_)
+        | PropertyAttribute::PropertyConstraint => {}
+    }
+}
+
+pub fn walk_constant_expr_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ConstantExpr) {
+    let cont = match node {
+        ConstantExpr::ConstantPrimary(primary, cont) => {
+            v.visit_constant_primary_mut(primary);
+            cont
+        }
+        ConstantExpr::UnaryOp(_, expr, cont) => {
+            v.visit_constant_expr_mut(expr);
+            cont
+        }
+    };
+    if let Some(cont) = cont {
+        v.visit_constant_expr_continue_mut(cont);
+    }
+}
+
+pub fn walk_constant_expr_continue_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut ConstantExprContinue,
+) {
+    let cont = match node {
+        ConstantExprContinue::BinaryOp(_, rhs, cont) => {
+            v.visit_constant_expr_mut(rhs);
+            cont
+        }
+        ConstantExprContinue::TernaryOp(if_true, if_false, cont) => {
+            v.visit_constant_expr_mut(if_true);
+            v.visit_constant_expr_mut(if_false);
+            cont
+        }
+    };
+    if let Some(cont) = cont {
+        v.visit_constant_expr_continue_mut(cont);
+    }
+}
+
+pub fn walk_constant_primary_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ConstantPrimary) {
+    match node {
+        ConstantPrimary::Base(base) => v.visit_constant_primary_base_mut(base),
+        ConstantPrimary::Cast(base, expr) => {
+            v.visit_constant_primary_base_mut(base);
+            v.visit_constant_expr_mut(expr);
+        }
+    }
+}
+
+pub fn walk_constant_primary_base_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut ConstantPrimaryBase,
+) {
+    match node {
+        ConstantPrimaryBase::PrimaryLiteral(literal) => v.visit_primary_literal_mut(literal),
+        ConstantPrimaryBase::ConstantConcat(exprs) | ConstantPrimaryBase::ArrayLiteral(exprs) => {
+            for expr in exprs {
+                v.visit_constant_expr_mut(expr);
+            }
+        }
+        ConstantPrimaryBase::ConstantMultipleConcat(count, exprs) => {
+            v.visit_constant_expr_mut(count);
+            for expr in exprs {
+                v.visit_constant_expr_mut(expr);
+            }
+        }
+        ConstantPrimaryBase::ConstantExpr(expr)
+        | ConstantPrimaryBase::SimpleTypeCast(_, expr)
+        | ConstantPrimaryBase::BooleanCast(expr) => v.visit_constant_expr_mut(expr),
+        ConstantPrimaryBase::InstanceOrPropRef(iref) => v.visit_instance_or_prop_ref_mut(iref),
+        ConstantPrimaryBase::StructLiteral(_, elements) => {
+            for element in elements {
+                v.visit_struct_literal_element_mut(element);
+            }
+        }
+    }
+}
+
+pub fn walk_instance_or_prop_ref_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut InstanceOrPropRef,
+) {
+    v.visit_instance_ref_mut(&mut node.iref);
+    if let Some(id_or_prop) = &mut node.id_or_prop {
+        v.visit_identity_or_prop_keyword_mut(id_or_prop);
+    }
+}
+
+pub fn walk_instance_ref_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut InstanceRef) {
+    for element in &mut node.elements {
+        v.visit_instance_ref_element_mut(element);
+    }
+}
+
+pub fn walk_instance_ref_element_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut InstanceRefElement,
+) {
+    for index in &mut node.arrays {
+        v.visit_constant_expr_mut(index);
+    }
+}
+
+pub fn walk_struct_literal_element_mut<V: VisitMut + ?Sized>(
+    v: &mut V,
+    node: &mut StructLiteralElement,
+) {
+    v.visit_constant_expr_mut(&mut node.expr);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Renames every instance of a component.
+    struct Rename;
+
+    impl VisitMut for Rename {
+        fn visit_component_inst_mut(&mut self, node: &mut ComponentInst) {
+            node.id = node.id.to_lowercase();
+            walk_component_inst_mut(self, node);
+        }
+
+        fn visit_primary_literal_mut(&mut self, node: &mut PrimaryLiteral) {
+            if let PrimaryLiteral::Number(value) = node {
+                *value += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_visit_mut() {
+        let mut root: Root = "addrmap top { reg { field {} EN[1] = 0; } CTRL @ 0x10; };"
+            .parse()
+            .unwrap();
+        Rename.visit_root_mut(&mut root);
+        let expected: Root = "addrmap top { reg { field {} en[2] = 1; } ctrl @ 0x11; };"
+            .parse()
+            .unwrap();
+        assert_eq!(root, expected);
+    }
+}
@@ -0,0 +1,328 @@
+// Licensed under the Apache-2.0 license
+
+use crate::{Node, NodeContent, NodeId, RootNamespace};
+
+/// What a [`Walker`] should do after entering a node.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum WalkerAction {
+    #[default]
+    Continue,
+    /// Do not visit the children of this node. The matching `exit_*` method
+    /// is still called.
+    SkipDescendants,
+}
+
+/// A node as seen by a [`Listener`] during a walk.
+///
+/// When arrays are unrolled each element is visited as a separate instance
+/// with its own index, path and address.
+#[derive(Debug, Clone)]
+pub struct Instance<'a> {
+    namespace: &'a RootNamespace,
+    node: &'a Node,
+    path: String,
+    index: Option<Vec<u64>>,
+    address: Option<u64>,
+}
+
+impl<'a> Instance<'a> {
+    pub fn namespace(&self) -> &'a RootNamespace {
+        self.namespace
+    }
+
+    pub fn node(&self) -> &'a Node {
+        self.node
+    }
+
+    /// Hierarchical path of the instance, including the index of each
+    /// unrolled array element, e.g. `top.regs[2].EN`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The index of this element within its array, if arrays are being
+    /// unrolled and the node is an array.
+    pub fn index(&self) -> Option<&[u64]> {
+        self.index.as_deref()
+    }
+
+    /// Absolute address of the instance, or of the first element of an array
+    /// that is not being unrolled. `None` for nodes that do not occupy address
+    /// space.
+    pub fn address(&self) -> Option<u64> {
+        self.address
+    }
+}
+
+/// Callbacks made by a [`Walker`] as it enters and leaves each node.
+///
+/// Every method has a default implementation that does nothing, so a listener
+/// only needs to implement those for the kinds of node it is interested in.
+pub trait Listener {
+    fn enter_addrmap(&mut self, _instance: &Instance) -> WalkerAction {
+        WalkerAction::Continue
+    }
+
+    fn exit_addrmap(&mut self, _instance: &Instance) {}
+
+    fn enter_regfile(&mut self, _instance: &Instance) -> WalkerAction {
+        WalkerAction::Continue
+    }
+
+    fn exit_regfile(&mut self, _instance: &Instance) {}
+
+    fn enter_reg(&mut self, _instance: &Instance) -> WalkerAction {
+        WalkerAction::Continue
+    }
+
+    fn exit_reg(&mut self, _instance: &Instance) {}
+
+    fn enter_field(&mut self, _instance: &Instance) -> WalkerAction {
+        WalkerAction::Continue
+    }
+
+    fn exit_field(&mut self, _instance: &Instance) {}
+
+    fn enter_mem(&mut self, _instance: &Instance) -> WalkerAction {
+        WalkerAction::Continue
+    }
+
+    fn exit_mem(&mut self, _instance: &Instance) {}
+
+    fn enter_signal(&mut self, _instance: &Instance) -> WalkerAction {
+        WalkerAction::Continue
+    }
+
+    fn exit_signal(&mut self, _instance: &Instance) {}
+}
+
+/// Walks an elaborated tree depth-first, calling a [`Listener`] on the way
+/// into and out of each node.
+#[derive(Clone, Debug, Default)]
+pub struct Walker {
+    unroll: bool,
+}
+
+impl Walker {
+    pub fn new() -> Self {
+        Walker::default()
+    }
+
+    /// Visit each element of an array separately, rather than visiting the
+    /// array once as a whole.
+    pub fn unroll(mut self, unroll: bool) -> Self {
+        self.unroll = unroll;
+        self
+    }
+
+    /// Walk every top-level node of `namespace`.
+    pub fn walk(&self, namespace: &RootNamespace, listener: &mut impl Listener) {
+        for root in namespace.roots() {
+            self.walk_from(namespace, root, Some(0), "", listener);
+        }
+    }
+
+    /// Walk the subtree rooted at the node `id`.
+    pub fn walk_node(&self, namespace: &RootNamespace, id: NodeId, listener: &mut impl Listener) {
+        let node = &namespace[id];
+        let parent_path = match namespace.parent(id) {
+            Some(parent) => namespace.path(parent.id()),
+            None => String::new(),
+        };
+        // the address of the first element of each enclosing array
+        let base = namespace
+            .ancestors(id)
+            .try_fold(0, |base, ancestor| Some(base + ancestor.offset()?));
+        self.walk_from(namespace, node, base, &parent_path, listener);
+    }
+
+    fn walk_from(
+        &self,
+        namespace: &RootNamespace,
+        node: &Node,
+        base: Option<u64>,
+        parent_path: &str,
+        listener: &mut impl Listener,
+    ) {
+        let path = if parent_path.is_empty() {
+            node.name().to_string()
+        } else {
+            format!("{parent_path}.{}", node.name())
+        };
+        let address = base.zip(node.offset()).map(|(base, offset)| base + offset);
+
+        match node.array() {
+            Some(array) if self.unroll => {
+                for i in 0..array.len() {
+                    let index = array.unflatten(i);
+                    let mut path = path.clone();
+                    for i in &index {
+                        path += &format!("[{i}]");
+                    }
+                    let instance = Instance {
+                        namespace,
+                        node,
+                        path,
+                        index: Some(index),
+                        address: address.map(|address| address + i * array.stride()),
+                    };
+                    self.visit(instance, listener);
+                }
+            }
+            _ => {
+                let instance = Instance {
+                    namespace,
+                    node,
+                    path,
+                    index: None,
+                    address,
+                };
+                self.visit(instance, listener);
+            }
+        }
+    }
+
+    fn visit(&self, instance: Instance, listener: &mut impl Listener) {
+        let action = match instance.node.content() {
+            NodeContent::Addrmap(_) => listener.enter_addrmap(&instance),
+            NodeContent::RegFile(_) => listener.enter_regfile(&instance),
+            NodeContent::Register(_) => listener.enter_reg(&instance),
+            NodeContent::Field(_) => listener.enter_field(&instance),
+            NodeContent::Signal(_) => listener.enter_signal(&instance),
+            NodeContent::Mem(_) => listener.enter_mem(&instance),
+        };
+
+        if action == WalkerAction::Continue {
+            // fields are positioned within their register, not by address
+            let base = match instance.node.content() {
+                NodeContent::Field(_) => None,
+                _ => instance.address,
+            };
+            for child in instance.namespace.children(instance.node.id()) {
+                self.walk_from(instance.namespace, child, base, &instance.path, listener);
+            }
+        }
+
+        match instance.node.content() {
+            NodeContent::Addrmap(_) => listener.exit_addrmap(&instance),
+            NodeContent::RegFile(_) => listener.exit_regfile(&instance),
+            NodeContent::Register(_) => listener.exit_reg(&instance),
+            NodeContent::Field(_) => listener.exit_field(&instance),
+            NodeContent::Signal(_) => listener.exit_signal(&instance),
+            NodeContent::Mem(_) => listener.exit_mem(&instance),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+        skip: Option<&'static str>,
+    }
+
+    impl Recorder {
+        fn enter(&mut self, kind: &str, instance: &Instance) -> WalkerAction {
+            let address = match instance.address() {
+                Some(address) => format!(" @{address:#x}"),
+                None => String::new(),
+            };
+            self.events
+                .push(format!("{kind} {}{address}", instance.path()));
+            if Some(instance.node().name()) == self.skip {
+                WalkerAction::SkipDescendants
+            } else {
+                WalkerAction::Continue
+            }
+        }
+    }
+
+    impl Listener for Recorder {
+        fn enter_addrmap(&mut self, instance: &Instance) -> WalkerAction {
+            self.enter("addrmap", instance)
+        }
+
+        fn enter_reg(&mut self, instance: &Instance) -> WalkerAction {
+            self.enter("reg", instance)
+        }
+
+        fn enter_field(&mut self, instance: &Instance) -> WalkerAction {
+            self.enter("field", instance)
+        }
+
+        fn exit_reg(&mut self, instance: &Instance) {
+            self.events.push(format!("/reg {}", instance.path()));
+        }
+    }
+
+    const INPUT: &str = r#"
+        addrmap {
+            reg { field {} EN; } CTRL @ 0x10;
+            reg { field {} VALUE[8]; } regs[2] @ 0x20 += 0x8;
+        } top;
+    "#;
+
+    fn namespace() -> RootNamespace {
+        crate::elaborate(crate::parse(INPUT).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_walk() {
+        let mut recorder = Recorder::default();
+        Walker::new().walk(&namespace(), &mut recorder);
+        assert_eq!(
+            recorder.events,
+            [
+                "addrmap top @0x0",
+                "reg top.CTRL @0x10",
+                "field top.CTRL.EN",
+                "/reg top.CTRL",
+                "reg top.regs @0x20",
+                "field top.regs.VALUE",
+                "/reg top.regs",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unroll_and_skip() {
+        let mut recorder = Recorder {
+            skip: Some("CTRL"),
+            ..Default::default()
+        };
+        Walker::new().unroll(true).walk(&namespace(), &mut recorder);
+        assert_eq!(
+            recorder.events,
+            [
+                "addrmap top @0x0",
+                "reg top.CTRL @0x10",
+                "/reg top.CTRL",
+                "reg top.regs[0] @0x20",
+                "field top.regs[0].VALUE",
+                "/reg top.regs[0]",
+                "reg top.regs[1] @0x28",
+                "field top.regs[1].VALUE",
+                "/reg top.regs[1]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_walk_node() {
+        let namespace = namespace();
+        let regs = namespace.find("top.regs").unwrap();
+        let mut recorder = Recorder::default();
+        Walker::new().walk_node(&namespace, regs.id(), &mut recorder);
+        assert_eq!(
+            recorder.events,
+            [
+                "reg top.regs @0x20",
+                "field top.regs.VALUE",
+                "/reg top.regs"
+            ]
+        );
+    }
+}
@@ -0,0 +1,133 @@
+// Licensed under the Apache-2.0 license
+
+//! Tests of the `systemrdl` command line: its arguments, output and exit
+//! status.
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Write `contents` to a file of its own in the temporary directory.
+fn input(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("systemrdl-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn systemrdl(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_systemrdl"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn status(output: &Output) -> i32 {
+    output.status.code().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+const VALID: &str = r#"
+    enum mode_t { A; B; };
+    addrmap {
+        reg { field { sw = rw; hw = r; } EN = 0; } CTRL;
+    } top;
+"#;
+
+#[test]
+fn test_help() {
+    for arg in ["-h", "--help"] {
+        let output = systemrdl(&[arg]);
+        assert_eq!(status(&output), 0);
+        assert!(stdout(&output).starts_with("Usage: systemrdl"));
+    }
+}
+
+#[test]
+fn test_usage_errors() {
+    let file = input("usage.rdl", VALID);
+    let file = file.to_str().unwrap();
+    let cases: [(&[&str], &str); 7] = [
+        (&[], "error: no command given"),
+        (&["check"], "error: no files given"),
+        (&["build", file], "error: unknown command build"),
+        (
+            &["check", "--verbose", file],
+            "error: unknown option --verbose",
+        ),
+        (&["export", file], "error: export requires --format"),
+        (
+            &["check", "--format", "json", file],
+            "error: --format can only be used with export",
+        ),
+        (
+            &["check", "-P", "WIDTH", file],
+            "error: -P WIDTH: expected NAME=VALUE",
+        ),
+    ];
+    for (args, message) in cases {
+        let output = systemrdl(args);
+        assert_eq!(status(&output), 2, "{args:?}");
+        assert!(
+            stderr(&output).starts_with(message),
+            "{args:?}: {}",
+            stderr(&output)
+        );
+        assert!(stderr(&output).contains("Usage: systemrdl"));
+    }
+
+    let output = systemrdl(&["check", "missing.rdl"]);
+    assert_eq!(status(&output), 2);
+    assert_eq!(stderr(&output), "error: could not read missing.rdl\n");
+}
+
+#[test]
+fn test_check() {
+    let valid = input("valid.rdl", VALID);
+    let output = systemrdl(&["check", valid.to_str().unwrap()]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert_eq!(stdout(&output), "");
+
+    let syntax = input("syntax.rdl", "addrmap { reg { field {} } } top;");
+    let output = systemrdl(&["check", syntax.to_str().unwrap()]);
+    assert_eq!(status(&output), 1);
+    assert!(stderr(&output).starts_with("error: "));
+
+    let invalid = input(
+        "invalid.rdl",
+        "addrmap { reg { field {} A[3:0]; field {} B[5:2]; } ctrl; } top;",
+    );
+    let invalid = invalid.to_str().unwrap();
+    let output = systemrdl(&["check", invalid]);
+    assert_eq!(status(&output), 1);
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "error: {invalid}:1:43: field top.ctrl.B [5:2] overlaps field top.ctrl.A [3:0] (see {invalid}:1:26)\nerror: found 1 error\n"
+        )
+    );
+}
+
+#[test]
+fn test_elaborate_and_export() {
+    let valid = input("export.rdl", VALID);
+    let valid = valid.to_str().unwrap();
+    let output = systemrdl(&["elaborate", valid]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert!(stdout(&output).contains("CTRL"));
+
+    let output = systemrdl(&["export", "--format", "json", valid]);
+    assert_eq!(status(&output), 0, "{}", stderr(&output));
+    assert!(stdout(&output).contains(r#""path": "top.CTRL.EN""#));
+
+    let output = systemrdl(&["elaborate", "--top", "nope", valid]);
+    assert_eq!(status(&output), 1);
+    assert_eq!(stderr(&output), "error: no root addrmap named nope\n");
+}